@@ -3,21 +3,31 @@
 use anyhow::{Result, Context};
 use log::{info, error, debug};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::signal;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 use crate::config::AppConfig;
+use crate::scheduler::Scheduler;
 
 use camera_core::camera::Camera;
+use camera_core::trigger::{EventTrigger, TriggerHandle, TriggerSource};
 use camera_core::video::{VideoRecorder, VideoSplitter};
 use camera_storage::file_manager::FileManager;
 use camera_storage::frame_manager::FrameManager;
 use camera_storage::package::PackageManager;
-use camera_storage::disk::DiskManager;
+use camera_storage::disk::{DiskGuard, DiskGuardConfig, DiskManager};
+use camera_storage::retention::RetentionManager;
+use camera_core::guard::{StorageEvent, StorageJob};
 use camera_monitor::system::SystemMonitor;
 use camera_monitor::service::ServiceMonitor;
 use camera_monitor::logger::Logger;
 use camera_api::server::Server;
 
+/// 拆分队列的推进间隔
+const SPLIT_QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// 应用状态
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AppState {
@@ -61,6 +71,18 @@ pub struct App {
     logger: Option<Arc<Logger>>,
     /// API服务器
     server: Option<Server>,
+    /// 事件触发句柄，未启用事件触发录制时为空
+    trigger_handle: Option<TriggerHandle>,
+    /// 录制帧泵任务
+    recording_pump: Option<JoinHandle<()>>,
+    /// 录制调度任务
+    scheduler_task: Option<JoinHandle<()>>,
+    /// 存储空间事件处理任务
+    storage_event_task: Option<JoinHandle<()>>,
+    /// 保留策略清理任务
+    retention_task: Option<JoinHandle<()>>,
+    /// 拆分队列推进任务
+    split_queue_task: Option<JoinHandle<()>>,
 }
 
 impl App {
@@ -80,6 +102,12 @@ impl App {
             service_monitor: None,
             logger: None,
             server: None,
+            trigger_handle: None,
+            recording_pump: None,
+            scheduler_task: None,
+            storage_event_task: None,
+            retention_task: None,
+            split_queue_task: None,
         })
     }
     
@@ -114,6 +142,13 @@ impl App {
         let disk_manager = Arc::new(camera_storage::disk::DiskManager::new());
         self.disk_manager = Some(disk_manager);
         
+        // 初始化存储空间保护，录制、拆分和打包共用
+        let disk_guard = Arc::new(DiskGuard::new(DiskGuardConfig {
+            max_usage: self.config.storage.max_disk_usage,
+            min_free_bytes: self.config.storage.min_free_space * 1024 * 1024,
+        }).context("初始化存储空间保护失败")?);
+        let storage_events = disk_guard.subscribe();
+        
         // 初始化文件管理器
         let file_manager = Arc::new(Mutex::new(
             camera_storage::file_manager::FileManager::new(
@@ -122,6 +157,16 @@ impl App {
             ).context("初始化文件管理器失败")?
         ));
         
+        // 修复上次异常退出时未正常结束的录制文件
+        let recording_dirs = self.config.storage.recording_dirs(&self.config.recording);
+        for dir in &recording_dirs {
+            match FileManager::recover_dir(dir) {
+                Ok(files) if !files.is_empty() => info!("已修复 {} 个录制文件: {}", files.len(), dir),
+                Ok(_) => {}
+                Err(e) => error!("检查录制文件失败: {}: {}", dir, e),
+            }
+        }
+        
         self.file_manager = Some(file_manager);
         
         // 初始化帧管理器
@@ -134,11 +179,11 @@ impl App {
         self.frame_manager = Some(frame_manager);
         
         // 初始化打包管理器
-        let package_manager = Arc::new(Mutex::new(
-            camera_storage::package::PackageManager::new(
-                &self.config.storage.package_dir,
-            ).context("初始化打包管理器失败")?
-        ));
+        let mut packager = camera_storage::package::PackageManager::new(
+            &self.config.storage.package_dir,
+        ).context("初始化打包管理器失败")?;
+        packager.set_storage_guard(disk_guard.clone());
+        let package_manager = Arc::new(Mutex::new(packager));
         
         self.package_manager = Some(package_manager);
         
@@ -173,18 +218,78 @@ impl App {
         self.camera = Some(camera.clone());
         
         // 初始化视频录制器
-        let recorder = Arc::new(Mutex::new(
-            camera_core::video::VideoRecorder::new(self.config.recording.clone())
-        ));
+        let mut video_recorder = camera_core::video::VideoRecorder::new(self.config.recording.clone());
+        video_recorder.set_camera(&self.config.camera);
+        video_recorder.set_storage_guard(disk_guard.clone());
+        let recorder = Arc::new(Mutex::new(video_recorder));
         
         self.recorder = Some(recorder.clone());
         
-        // 初始化视频拆分器
-        let splitter = Arc::new(Mutex::new(
-            camera_core::video::VideoSplitter::new(self.config.split.clone())
+        // 事件触发录制
+        let event_trigger = if self.config.event.enabled {
+            info!("启用事件触发录制，预录 {} 秒，后录 {} 秒",
+                self.config.event.pre_roll_secs, self.config.event.post_roll_secs);
+            Some(EventTrigger::new(self.config.event.clone()).context("初始化事件触发录制失败")?)
+        } else {
+            None
+        };
+        self.trigger_handle = event_trigger.as_ref().map(EventTrigger::handle);
+        
+        // 启动录制帧泵
+        self.recording_pump = Some(Self::spawn_recording_pump(
+            camera.clone(),
+            recorder.clone(),
+            self.config.recording.fps,
+            event_trigger,
         ));
         
+        // 启动定时录制
+        if self.config.schedule.enabled {
+            let scheduler = Scheduler::new(
+                self.config.schedule.clone(),
+                self.config.recording.clone(),
+            ).context("初始化录制调度器失败")?;
+            
+            match scheduler.next_run() {
+                Some(run) => info!("下一次计划录制: {} {} - {}", run.rule, run.start, run.end),
+                None => info!("没有待执行的录制计划"),
+            }
+            
+            self.scheduler_task = Some(Self::spawn_scheduler(scheduler, recorder.clone()));
+        }
+        
+        // 初始化视频拆分器
+        let mut video_splitter = camera_core::video::VideoSplitter::new(self.config.split.clone());
+        video_splitter.set_storage_guard(disk_guard.clone());
+        
+        // 恢复上次未完成的拆分任务，队列文件损坏时从空队列开始
+        if let Err(e) = video_splitter.load_queue() {
+            error!("恢复拆分队列失败: {}", e);
+        }
+        
+        let splitter = Arc::new(Mutex::new(video_splitter));
+        
         self.splitter = Some(splitter.clone());
+        self.split_queue_task = Some(Self::spawn_split_queue(splitter.clone()));
+        
+        // 按保留策略定期清理旧文件
+        if self.config.storage.retention.enabled {
+            let retention = RetentionManager::new(
+                self.config.storage.retention.clone(),
+                &recording_dirs,
+                self.config.storage.frames_dir.clone(),
+                self.config.storage.package_dir.clone(),
+            ).context("初始化保留策略失败")?;
+            
+            info!("启用文件保留策略，每 {} 秒检查一次{}",
+                retention.config().check_interval,
+                if retention.config().dry_run { "（只生成报告）" } else { "" });
+            
+            self.retention_task = Some(Self::spawn_retention(retention, recorder.clone()));
+        }
+        
+        // 存储空间不足时更新对应服务的状态
+        self.storage_event_task = Some(Self::spawn_storage_events(storage_events, service_monitor.clone()));
         
         // 初始化API服务器
         // 注意：这里只是示例，实际实现需要根据camera-api模块的具体接口
@@ -211,6 +316,204 @@ impl App {
         Ok(())
     }
     
+    /// 发送一次录制触发事件，由录制帧泵在下一帧处理
+    pub fn trigger_event(&self, source: TriggerSource) -> Result<()> {
+        let handle = self.trigger_handle.as_ref()
+            .context("未启用事件触发录制")?;
+        handle.fire(source);
+        Ok(())
+    }
+    
+    /// 启动录制帧泵
+    ///
+    /// 摄像头正在采集且录制器正在录制时，按录制帧率抓取图像写入录制器。
+    /// 启用事件触发录制时，不论是否在录制都持续抓取图像交给事件控制器处理。
+    /// 采集和编码写入都在阻塞线程中执行，摄像头和录制器不会同时加锁。
+    fn spawn_recording_pump(
+        camera: Arc<Mutex<Camera>>,
+        recorder: Arc<Mutex<VideoRecorder>>,
+        fps: u32,
+        mut event_trigger: Option<EventTrigger>,
+    ) -> JoinHandle<()> {
+        let period = Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            
+            loop {
+                ticker.tick().await;
+                
+                if event_trigger.is_none() && !recorder.lock().await.is_recording() {
+                    continue;
+                }
+                
+                let mut camera = camera.clone().lock_owned().await;
+                let captured = tokio::task::spawn_blocking(move || {
+                    camera.is_capturing().then(|| camera.capture_frame())
+                }).await;
+                
+                let frame = match captured {
+                    Ok(Some(Ok(frame))) => frame,
+                    Ok(Some(Err(e))) => {
+                        error!("采集录制帧失败: {}", e);
+                        continue;
+                    }
+                    Ok(None) => continue,
+                    Err(e) => {
+                        error!("录制帧采集任务异常退出: {}", e);
+                        continue;
+                    }
+                };
+                
+                let mut recorder = recorder.clone().lock_owned().await;
+                let mut trigger = event_trigger.take();
+                let written = tokio::task::spawn_blocking(move || {
+                    let result = match &mut trigger {
+                        Some(trigger) => trigger.process_frame(&mut recorder, &frame),
+                        None => recorder.write_frame(&frame),
+                    };
+                    (trigger, result)
+                }).await;
+                
+                match written {
+                    Ok((trigger, result)) => {
+                        event_trigger = trigger;
+                        if let Err(e) = result {
+                            error!("写入录制帧失败: {}", e);
+                        }
+                    }
+                    // 事件控制器随任务一起丢失，之后只按手动和定时录制写入
+                    Err(e) => error!("录制帧写入任务异常退出，事件触发录制已停用: {}", e),
+                }
+            }
+        })
+    }
+    
+    /// 启动录制调度任务
+    fn spawn_scheduler(
+        mut scheduler: Scheduler,
+        recorder: Arc<Mutex<VideoRecorder>>,
+    ) -> JoinHandle<()> {
+        let period = scheduler.check_interval();
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            
+            loop {
+                ticker.tick().await;
+                
+                // 开始和停止录制要创建目录、写元数据和等待编码进程退出，在阻塞线程中执行
+                let recorder = recorder.clone();
+                let ticked = tokio::task::spawn_blocking(move || {
+                    let mut recorder = recorder.blocking_lock_owned();
+                    scheduler.tick(&mut recorder);
+                    scheduler
+                }).await;
+                
+                match ticked {
+                    Ok(ticked) => scheduler = ticked,
+                    Err(e) => {
+                        error!("录制调度任务异常退出，定时录制已停用: {}", e);
+                        return;
+                    }
+                }
+            }
+        })
+    }
+    
+    /// 启动保留策略清理任务
+    fn spawn_retention(
+        retention: RetentionManager,
+        recorder: Arc<Mutex<VideoRecorder>>,
+    ) -> JoinHandle<()> {
+        let period = Duration::from_secs(retention.config().check_interval);
+        let retention = Arc::new(retention);
+        
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            
+            loop {
+                ticker.tick().await;
+                
+                // 正在录制的文件不能删除
+                let active: Vec<_> = {
+                    let recorder = recorder.lock().await;
+                    let status = recorder.status();
+                    if status.state.is_active() {
+                        status.current_file.into_iter().collect()
+                    } else {
+                        Vec::new()
+                    }
+                };
+                
+                let retention = retention.clone();
+                match tokio::task::spawn_blocking(move || retention.enforce(&active)).await {
+                    Ok(Ok(report)) => {
+                        for item in report.deleted.iter().filter(|_| report.dry_run) {
+                            info!("保留策略计划删除: {} ({})", item.path.display(), item.reason);
+                        }
+                    }
+                    Ok(Err(e)) => error!("执行保留策略失败: {}", e),
+                    Err(e) => error!("保留策略任务异常退出: {}", e),
+                }
+            }
+        })
+    }
+    
+    /// 启动拆分队列推进任务，回收已结束的任务并启动排队中的任务
+    ///
+    /// 回收任务要等待工作线程退出，启动任务要打开视频文件，都在阻塞线程中执行。
+    fn spawn_split_queue(splitter: Arc<Mutex<VideoSplitter>>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(SPLIT_QUEUE_POLL_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            
+            loop {
+                ticker.tick().await;
+                let mut splitter = splitter.clone().lock_owned().await;
+                if let Err(e) = tokio::task::spawn_blocking(move || splitter.poll()).await {
+                    error!("拆分队列推进任务异常退出: {}", e);
+                }
+            }
+        })
+    }
+    
+    /// 启动存储空间事件处理任务
+    fn spawn_storage_events(
+        mut events: tokio::sync::broadcast::Receiver<StorageEvent>,
+        service_monitor: Arc<Mutex<ServiceMonitor>>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("存储空间事件处理过慢，丢弃 {} 个事件", skipped);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                
+                error!("存储空间不足，{} 任务已停止: {}: {}",
+                    event.job.as_str(), event.path.display(), event.message);
+                
+                let service = match event.job {
+                    StorageJob::Recording => "recorder",
+                    StorageJob::Split => "splitter",
+                    // 打包没有注册为服务，只记录日志
+                    StorageJob::Package => continue,
+                };
+                let mut monitor = service_monitor.lock().await;
+                if let Err(e) = monitor.set_service_error(service, &format!("存储空间不足: {}", event.message)) {
+                    error!("更新服务状态失败: {}", e);
+                }
+            }
+        })
+    }
+    
     /// 运行应用
     pub async fn run(&mut self) -> Result<()> {
         // 初始化应用
@@ -226,7 +529,7 @@ impl App {
         */
         
         // 等待终止信号
-        match signal::ctrl_c().await {
+        match self.wait_for_shutdown().await {
             Ok(()) => {
                 info!("接收到终止信号，开始关闭应用...");
                 self.shutdown().await?;
@@ -240,6 +543,31 @@ impl App {
         Ok(())
     }
     
+    /// 等待终止信号，期间收到 SIGUSR1 时作为外部命令触发一次事件录制
+    #[cfg(unix)]
+    async fn wait_for_shutdown(&self) -> std::io::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        let mut user_signal = signal(SignalKind::user_defined1())?;
+        loop {
+            tokio::select! {
+                result = signal::ctrl_c() => return result,
+                _ = user_signal.recv() => {
+                    info!("接收到 SIGUSR1，触发事件录制");
+                    if let Err(e) = self.trigger_event(TriggerSource::Command) {
+                        error!("触发事件录制失败: {}", e);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// 等待终止信号
+    #[cfg(not(unix))]
+    async fn wait_for_shutdown(&self) -> std::io::Result<()> {
+        signal::ctrl_c().await
+    }
+    
     /// 关闭应用
     async fn shutdown(&mut self) -> Result<()> {
         info!("关闭应用...");
@@ -252,6 +580,35 @@ impl App {
         }
         */
         
+        // 停止录制调度，避免关闭过程中重新开始录制
+        if let Some(task) = self.scheduler_task.take() {
+            task.abort();
+        }
+        
+        // 停止录制帧泵
+        if let Some(pump) = self.recording_pump.take() {
+            pump.abort();
+        }
+        
+        if let Some(task) = self.storage_event_task.take() {
+            task.abort();
+        }
+        
+        if let Some(task) = self.retention_task.take() {
+            task.abort();
+        }
+        
+        // 停止拆分，未完成的任务保留在队列文件中，下次启动时重新排队，已输出的图片保留
+        if let Some(task) = self.split_queue_task.take() {
+            task.abort();
+        }
+        if let Some(splitter) = self.splitter.take() {
+            let mut splitter = splitter.lock_owned().await;
+            if let Err(e) = tokio::task::spawn_blocking(move || splitter.shutdown()).await {
+                error!("停止拆分任务失败: {}", e);
+            }
+        }
+        
         // 停止摄像头
         if let Some(camera) = &self.camera {
             let mut camera = camera.lock().await;
@@ -261,10 +618,14 @@ impl App {
         }
         
         // 停止录制
-        if let Some(recorder) = &self.recorder {
-            let mut recorder = recorder.lock().await;
-            if recorder.is_recording() {
-                recorder.stop_recording().context("停止录制失败")?;
+        if let Some(recorder) = self.recorder.clone() {
+            let stopped = tokio::task::spawn_blocking(move || {
+                let mut recorder = recorder.blocking_lock_owned();
+                recorder.state().is_active().then(|| recorder.stop_recording()).transpose()
+            }).await.context("停止录制任务异常退出")?;
+            
+            if let Some(summary) = stopped.context("停止录制失败")? {
+                info!("录制已停止: {:?}, {} 帧", summary.path, summary.frames);
             }
         }
         
@@ -45,4 +45,20 @@ pub struct Cli {
     /// 日志级别
     #[clap(long)]
     pub log_level: Option<String>,
+    
+    /// 列出接下来的计划录制时间后退出
+    #[clap(long)]
+    pub list_schedule: bool,
+    
+    /// 按保留策略列出将被清理的文件后退出，不删除任何文件
+    #[clap(long)]
+    pub retention_report: bool,
+    
+    /// 为录像或帧文件夹生成联系表后退出
+    #[clap(long, value_name = "PATH")]
+    pub contact_sheet: Option<String>,
+    
+    /// 为录像或帧文件夹生成雪碧图及 WebVTT/JSON 索引后退出
+    #[clap(long, value_name = "PATH")]
+    pub sprite_sheet: Option<String>,
 }
@@ -4,7 +4,9 @@ use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
-use camera_core::config::{CameraConfig, RecordingConfig, SplitConfig};
+use camera_core::config::{CameraConfig, EventRecordingConfig, RecordingConfig, SheetConfig, SplitConfig};
+use camera_storage::retention::RetentionConfig;
+use crate::scheduler::ScheduleConfig;
 
 /// 服务器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +52,23 @@ pub struct StorageConfig {
     pub package_dir: String,
     /// 最大磁盘使用率（0.0 - 1.0）
     pub max_disk_usage: f32,
+    /// 至少保留的可用空间（MB），低于该值时拒绝或停止录制、拆分和打包
+    #[serde(default = "default_min_free_space")]
+    pub min_free_space: u64,
+    /// 文件保留策略
+    #[serde(default)]
+    pub retention: RetentionConfig,
+}
+
+impl StorageConfig {
+    /// 录制文件所在的目录，录制输出目录与视频目录不同时两者都包含
+    pub fn recording_dirs(&self, recording: &RecordingConfig) -> Vec<String> {
+        let mut dirs = vec![self.video_dir.clone()];
+        if recording.output_dir != self.video_dir {
+            dirs.push(recording.output_dir.clone());
+        }
+        dirs
+    }
 }
 
 impl Default for StorageConfig {
@@ -59,10 +78,16 @@ impl Default for StorageConfig {
             frames_dir: "./data/frames".to_string(),
             package_dir: "./data/packages".to_string(),
             max_disk_usage: 0.9,
+            min_free_space: default_min_free_space(),
+            retention: RetentionConfig::default(),
         }
     }
 }
 
+fn default_min_free_space() -> u64 {
+    200
+}
+
 /// 日志配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogConfig {
@@ -99,12 +124,21 @@ pub struct AppConfig {
     pub recording: RecordingConfig,
     /// 拆分配置
     pub split: SplitConfig,
+    /// 联系表/雪碧图配置
+    #[serde(default)]
+    pub sheet: SheetConfig,
     /// 服务器配置
     pub server: ServerConfig,
     /// 存储配置
     pub storage: StorageConfig,
     /// 日志配置
     pub log: LogConfig,
+    /// 定时录制配置
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    /// 事件触发录制配置
+    #[serde(default)]
+    pub event: EventRecordingConfig,
 }
 
 impl Default for AppConfig {
@@ -113,9 +147,12 @@ impl Default for AppConfig {
             camera: CameraConfig::default(),
             recording: RecordingConfig::default(),
             split: SplitConfig::default(),
+            sheet: SheetConfig::default(),
             server: ServerConfig::default(),
             storage: StorageConfig::default(),
             log: LogConfig::default(),
+            schedule: ScheduleConfig::default(),
+            event: EventRecordingConfig::default(),
         }
     }
 }
@@ -146,9 +183,27 @@ pub fn load_config<P: AsRef<Path>>(path: P) -> Result<AppConfig> {
         _ => return Err(anyhow::anyhow!("不支持的配置文件格式")),
     };
     
+    validate_templates(&config)?;
+    
     Ok(config)
 }
 
+/// 校验配置中的文件名模板和拆分参数，避免录制或拆分时才发现配置错误
+fn validate_templates(config: &AppConfig) -> Result<()> {
+    config.recording.filename_template()?;
+    camera_core::split::validate(&config.split).context("拆分配置无效")?;
+    camera_core::sheet::validate(&config.sheet).context("联系表配置无效")?;
+    
+    for rule in &config.schedule.rules {
+        if let Some(recording) = &rule.recording {
+            recording.filename_template()
+                .context(format!("录制计划 {} 的配置无效", rule.name))?;
+        }
+    }
+    
+    Ok(())
+}
+
 /// 保存配置文件
 pub fn save_config<P: AsRef<Path>>(path: P, config: &AppConfig) -> Result<()> {
     let path = path.as_ref();
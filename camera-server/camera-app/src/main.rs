@@ -3,12 +3,15 @@
 mod config;
 mod app;
 mod cli;
+mod scheduler;
 
 use anyhow::{Result, Context};
 use clap::Parser;
 use log::{info, error, debug};
 use cli::Cli;
 use app::App;
+use camera_core::config::{SheetConfig, SheetKind};
+use std::path::Path;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -25,6 +28,63 @@ async fn main() -> Result<()> {
         
     info!("配置加载成功: {}", config_path);
     
+    // 只列出计划录制时间
+    if cli.list_schedule {
+        let scheduler = scheduler::Scheduler::new(config.schedule.clone(), config.recording.clone())
+            .context("加载录制计划失败")?;
+        for run in scheduler.upcoming(10) {
+            println!("{}\t{}\t{}", run.start, run.end, run.rule);
+        }
+        return Ok(());
+    }
+    
+    // 只生成保留策略清理报告
+    if cli.retention_report {
+        let storage = &config.storage;
+        let manager = camera_storage::retention::RetentionManager::new(
+            storage.retention.clone(),
+            &storage.recording_dirs(&config.recording),
+            storage.frames_dir.clone(),
+            storage.package_dir.clone(),
+        ).context("加载保留策略失败")?;
+        let report = manager.report(&[]).context("生成清理报告失败")?;
+        for item in &report.deleted {
+            println!("{:?}\t{}\t{}\t{}", item.category, item.size, item.path.display(), item.reason);
+        }
+        for path in &report.protected {
+            println!("protected\t-\t{}\t受保护", path.display());
+        }
+        for overage in &report.overages {
+            println!("{:?}\t受保护的文件共 {} MB，超过大小上限 {} MB",
+                overage.category, overage.protected_bytes / (1024 * 1024), overage.limit_bytes / (1024 * 1024));
+        }
+        println!("共 {} 项，可释放 {} MB", report.deleted.len(), report.freed_bytes / (1024 * 1024));
+        return Ok(());
+    }
+    
+    // 只生成联系表或雪碧图
+    let sheet = cli.contact_sheet.map(|path| (path, SheetKind::Contact))
+        .or(cli.sprite_sheet.map(|path| (path, SheetKind::Sprite)));
+    if let Some((path, kind)) = sheet {
+        let sheet_config = SheetConfig { kind, ..config.sheet.clone() };
+        let path = Path::new(&path);
+        let index = if path.is_dir() {
+            camera_storage::frame_manager::FrameManager::new(&config.storage.frames_dir)
+                .and_then(|manager| manager.build_frame_sheet(path, &sheet_config))
+                .context("生成帧文件夹网格图失败")?
+        } else {
+            camera_core::sheet::build_for_video(path, &sheet_config)
+                .context("生成录像网格图失败")?
+        };
+        let dir = index.source.parent().unwrap_or(Path::new(""));
+        println!("{}\t{} 张缩略图", dir.join(&index.image).display(), index.tiles.len());
+        if kind == SheetKind::Sprite {
+            println!("{}", camera_core::sheet::sprite_vtt_path(&index.source).display());
+            println!("{}", camera_core::sheet::sprite_index_path(&index.source).display());
+        }
+        return Ok(());
+    }
+    
     // 创建应用实例
     let mut app = App::new(config)
         .context("创建应用实例失败")?;
@@ -0,0 +1,746 @@
+//! 定时录制计划模块
+//!
+//! 按配置中的规则自动开始和停止录制。规则可以是 cron 表达式（在匹配的时刻
+//! 开始，持续指定分钟数），也可以是每周固定的时间窗口（如早班 08:00-16:00）。
+//! 每条规则可以带有自己的录制配置，未设置时使用全局录制配置。
+//!
+//! 调度状态保存在状态文件中，重启后如果仍处于计划窗口内会自动恢复录制，
+//! 并且只会停止由调度器自己开始的录制，不影响手动录制。计划录制被手动停止后，
+//! 在当前窗口结束前不会再自动开始。
+//!
+//! 时区只支持系统本地时区、UTC 和固定偏移，不支持 IANA 时区名称
+//! （如 "Asia/Shanghai"），需要夏令时切换的地区请使用 "local"。
+
+use anyhow::{anyhow, Context, Result};
+use camera_core::config::RecordingConfig;
+use camera_core::trigger::TriggerSource;
+use camera_core::video::{RecorderState, VideoRecorder};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike, Utc, Weekday,
+};
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 查找下一次运行时向后搜索的最大天数
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+/// cron 月份字段可用的名称，从1开始
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// cron 星期字段可用的名称，从0（周日）开始
+const WEEKDAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// 定时录制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// 是否启用定时录制
+    #[serde(default)]
+    pub enabled: bool,
+    /// 时区："local" 表示系统时区，"UTC"，或固定偏移如 "+08:00"
+    ///
+    /// 不支持 IANA 时区名称，配置为其他值时创建调度器会返回错误。
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// 调度状态文件路径
+    #[serde(default = "default_state_file")]
+    pub state_file: String,
+    /// 检查间隔（秒）
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+    /// 录制规则，按顺序匹配，多个规则同时生效时使用靠前的规则
+    #[serde(default)]
+    pub rules: Vec<ScheduleRule>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timezone: default_timezone(),
+            state_file: default_state_file(),
+            check_interval: default_check_interval(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+fn default_timezone() -> String {
+    "local".to_string()
+}
+
+fn default_state_file() -> String {
+    "./data/schedule_state.json".to_string()
+}
+
+fn default_check_interval() -> u64 {
+    15
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 录制规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    /// 规则名称，需唯一
+    pub name: String,
+    /// 是否启用
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 触发方式
+    pub trigger: ScheduleTrigger,
+    /// 该规则使用的录制配置，未设置时使用全局录制配置
+    #[serde(default)]
+    pub recording: Option<RecordingConfig>,
+}
+
+/// 规则触发方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ScheduleTrigger {
+    /// cron 表达式（分 时 日 月 周），在匹配的时刻开始录制，持续 `duration_minutes` 分钟
+    Cron {
+        /// 5 段 cron 表达式，如 "0 8 * * 1-5"
+        expression: String,
+        /// 每次录制的时长（分钟）
+        duration_minutes: u32,
+    },
+    /// 每周时间窗口，`end` 不晚于 `start` 时表示跨越午夜
+    Weekly {
+        /// 生效的星期，如 ["mon", "tue"]
+        days: Vec<String>,
+        /// 开始时间，如 "08:00"
+        start: String,
+        /// 结束时间，如 "16:00"
+        end: String,
+    },
+}
+
+/// 计划中的一次录制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedRun {
+    /// 规则名称
+    pub rule: String,
+    /// 开始时间（RFC 3339）
+    pub start: String,
+    /// 结束时间（RFC 3339）
+    pub end: String,
+}
+
+/// 持久化的调度状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+    /// 由调度器开始、尚未结束的录制所属规则
+    active_rule: Option<String>,
+    /// 当前窗口的结束时间（RFC 3339）
+    window_end: Option<String>,
+    /// 最近一次开始录制的时间（RFC 3339）
+    last_started: Option<String>,
+    /// 最近一次停止录制的时间（RFC 3339）
+    last_stopped: Option<String>,
+    /// 计划录制被手动停止后，到该时间（RFC 3339）之前不再自动开始
+    #[serde(default)]
+    override_until: Option<String>,
+}
+
+/// 调度时区
+#[derive(Debug, Clone, Copy)]
+enum ScheduleTimezone {
+    /// 系统本地时区
+    Local,
+    /// 固定偏移
+    Fixed(FixedOffset),
+}
+
+impl ScheduleTimezone {
+    /// 解析时区配置
+    ///
+    /// 只接受 "local"、"UTC"/"Z" 和 "+08:00" 形式的固定偏移，
+    /// IANA 时区名称等其他值返回错误。
+    fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+        if value.eq_ignore_ascii_case("utc") || value == "Z" {
+            return Ok(Self::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+
+        let (sign, rest) = match value.as_bytes().first() {
+            Some(b'+') => (1, &value[1..]),
+            Some(b'-') => (-1, &value[1..]),
+            _ if value.contains('/') => return Err(anyhow!(
+                "不支持 IANA 时区名称: {}，请使用 local（系统时区）、UTC 或 +08:00 形式的固定偏移", value
+            )),
+            _ => return Err(anyhow!("无效的时区: {}，应为 local、UTC 或 +08:00 形式", value)),
+        };
+        let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+        let hours: i32 = hours.parse().map_err(|_| anyhow!("无效的时区偏移: {}", value))?;
+        let minutes: i32 = minutes.parse().map_err(|_| anyhow!("无效的时区偏移: {}", value))?;
+        if !(0..60).contains(&minutes) {
+            return Err(anyhow!("无效的时区偏移: {}", value));
+        }
+
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(Self::Fixed)
+            .ok_or_else(|| anyhow!("时区偏移超出范围: {}", value))
+    }
+
+    /// 将 UTC 时间转换为该时区的本地时间
+    fn to_local(self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Self::Local => time.with_timezone(&Local).naive_local(),
+            Self::Fixed(offset) => time.with_timezone(&offset).naive_local(),
+        }
+    }
+
+    /// 将 RFC 3339 时间转换为该时区的本地时间
+    fn parse_local(self, value: &str) -> Option<NaiveDateTime> {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| self.to_local(t.with_timezone(&Utc)))
+    }
+
+    /// 将该时区的本地时间格式化为 RFC 3339
+    fn format(self, time: NaiveDateTime) -> String {
+        match self {
+            Self::Local => Local.from_local_datetime(&time)
+                .earliest()
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| time.to_string()),
+            Self::Fixed(offset) => offset.from_local_datetime(&time)
+                .single()
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| time.to_string()),
+        }
+    }
+}
+
+/// cron 表达式中的一个字段
+#[derive(Debug, Clone)]
+struct CronField {
+    /// 允许的取值
+    values: Vec<bool>,
+    /// 是否为 "*"（不限制）
+    any: bool,
+}
+
+impl CronField {
+    /// 解析字段，支持 "*"、列表 "1,2"、范围 "1-5" 和步长 "*/15"、"0-30/10"，
+    /// `names` 为按顺序对应 `min` 起的取值名称，如 "mon"
+    fn parse(field: &str, min: u32, max: u32, names: &[&str]) -> Result<Self> {
+        let mut values = vec![false; max as usize + 1];
+
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().map_err(|_| anyhow!("无效的步长: {}", part))?;
+                    if step == 0 {
+                        return Err(anyhow!("步长不能为0: {}", part));
+                    }
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((a, b)) = range.split_once('-') {
+                (parse_cron_value(a, min, max, names)?, parse_cron_value(b, min, max, names)?)
+            } else {
+                let value = parse_cron_value(range, min, max, names)?;
+                // "5/10" 表示从5开始每10个
+                (value, if step > 1 { max } else { value })
+            };
+
+            if start > end {
+                return Err(anyhow!("无效的范围: {}", part));
+            }
+
+            for value in (start..=end).step_by(step as usize) {
+                values[value as usize] = true;
+            }
+        }
+
+        Ok(Self {
+            values,
+            any: field == "*",
+        })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.values.get(value as usize).copied().unwrap_or(false)
+    }
+}
+
+fn parse_cron_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32> {
+    if let Some(index) = names.iter().position(|name| name.eq_ignore_ascii_case(value)) {
+        return Ok(min + index as u32);
+    }
+
+    let parsed: u32 = value.parse().map_err(|_| anyhow!("无效的数值: {}", value))?;
+    if parsed < min || parsed > max {
+        return Err(anyhow!("数值 {} 超出范围 {}-{}", parsed, min, max));
+    }
+    Ok(parsed)
+}
+
+/// 5 段 cron 表达式
+#[derive(Debug, Clone)]
+struct CronSchedule {
+    minute: CronField,
+    hour: CronField,
+    day_of_month: CronField,
+    month: CronField,
+    day_of_week: CronField,
+}
+
+impl CronSchedule {
+    /// 解析 "分 时 日 月 周" 表达式，周取值 0-7，0 和 7 都表示周日
+    fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow!("cron表达式应包含5个字段: {}", expression));
+        }
+
+        let mut day_of_week = CronField::parse(fields[4], 0, 7, WEEKDAY_NAMES)?;
+        if day_of_week.values[7] {
+            day_of_week.values[0] = true;
+        }
+
+        Ok(Self {
+            minute: CronField::parse(fields[0], 0, 59, &[])?,
+            hour: CronField::parse(fields[1], 0, 23, &[])?,
+            day_of_month: CronField::parse(fields[2], 1, 31, &[])?,
+            month: CronField::parse(fields[3], 1, 12, MONTH_NAMES)?,
+            day_of_week,
+        })
+    }
+
+    /// 日期是否匹配；日和周都有限制时满足其一即可（与 cron 行为一致）
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.month.matches(date.month()) {
+            return false;
+        }
+
+        let dom = self.day_of_month.matches(date.day());
+        let dow = self.day_of_week.matches(date.weekday().num_days_from_sunday());
+        match (self.day_of_month.any, self.day_of_week.any) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
+        }
+    }
+
+    /// 不早于 `from` 的第一个匹配时刻（精确到分钟）
+    fn next_at_or_after(&self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = from.with_second(0)?.with_nanosecond(0)?;
+        if time < from {
+            time += Duration::minutes(1);
+        }
+        let limit = from + Duration::days(MAX_SEARCH_DAYS);
+
+        while time <= limit {
+            if !self.matches_date(time.date()) {
+                time = (time.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.hour.matches(time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minute.matches(time.minute()) {
+                time += Duration::minutes(1);
+                continue;
+            }
+            return Some(time);
+        }
+
+        None
+    }
+
+    /// 不晚于 `to` 且不早于 `from` 的最后一个匹配时刻
+    fn last_in(&self, from: NaiveDateTime, to: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut last = None;
+        let mut cursor = from;
+        while let Some(time) = self.next_at_or_after(cursor) {
+            if time > to {
+                break;
+            }
+            last = Some(time);
+            cursor = time + Duration::minutes(1);
+        }
+        last
+    }
+}
+
+/// 编译后的规则触发方式
+#[derive(Debug, Clone)]
+enum CompiledTrigger {
+    Cron {
+        schedule: CronSchedule,
+        duration: Duration,
+    },
+    Weekly {
+        days: Vec<Weekday>,
+        start: NaiveTime,
+        end: NaiveTime,
+    },
+}
+
+impl CompiledTrigger {
+    /// 编译规则触发方式并校验参数
+    fn compile(trigger: &ScheduleTrigger) -> Result<Self> {
+        match trigger {
+            ScheduleTrigger::Cron { expression, duration_minutes } => {
+                if *duration_minutes == 0 {
+                    return Err(anyhow!("录制时长不能为0"));
+                }
+                Ok(Self::Cron {
+                    schedule: CronSchedule::parse(expression)?,
+                    duration: Duration::minutes(*duration_minutes as i64),
+                })
+            }
+            ScheduleTrigger::Weekly { days, start, end } => {
+                if days.is_empty() {
+                    return Err(anyhow!("每周时间窗口至少需要一天"));
+                }
+                let days = days.iter()
+                    .map(|d| parse_weekday(d))
+                    .collect::<Result<Vec<_>>>()?;
+                let start = parse_time(start)?;
+                let end = parse_time(end)?;
+                Ok(Self::Weekly { days, start, end })
+            }
+        }
+    }
+
+    /// 包含 `now` 的录制窗口
+    fn window_at(&self, now: NaiveDateTime) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        match self {
+            Self::Cron { schedule, duration } => {
+                // 最近一次开始的录制如果还没结束，当前时间就在窗口内
+                let start = schedule.last_in(now - *duration, now)?;
+                let end = start + *duration;
+                (now < end).then_some((start, end))
+            }
+            Self::Weekly { .. } => {
+                // 今天和昨天开始的窗口都可能覆盖当前时间（跨越午夜）
+                [now.date() - Duration::days(1), now.date()].iter()
+                    .filter_map(|date| self.weekly_window(*date))
+                    .find(|(start, end)| *start <= now && now < *end)
+            }
+        }
+    }
+
+    /// `from` 之后（不含）开始的第一个录制窗口
+    fn next_window(&self, from: NaiveDateTime) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        match self {
+            Self::Cron { schedule, duration } => {
+                let start = schedule.next_at_or_after(from + Duration::minutes(1))?;
+                Some((start, start + *duration))
+            }
+            Self::Weekly { .. } => (0..=7)
+                .filter_map(|offset| self.weekly_window(from.date() + Duration::days(offset)))
+                .find(|(start, _)| *start > from),
+        }
+    }
+
+    /// 指定日期开始的每周窗口
+    fn weekly_window(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        match self {
+            Self::Weekly { days, start, end } => {
+                if !days.contains(&date.weekday()) {
+                    return None;
+                }
+                let window_start = date.and_time(*start);
+                let mut window_end = date.and_time(*end);
+                if window_end <= window_start {
+                    window_end += Duration::days(1);
+                }
+                Some((window_start, window_end))
+            }
+            Self::Cron { .. } => None,
+        }
+    }
+}
+
+/// 解析星期名称
+fn parse_weekday(value: &str) -> Result<Weekday> {
+    value.trim().parse::<Weekday>()
+        .map_err(|_| anyhow!("无效的星期: {}", value))
+}
+
+/// 解析 "HH:MM" 时间
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| anyhow!("无效的时间: {}，应为 HH:MM 格式", value))
+}
+
+/// 编译后的规则
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    /// 原始规则
+    rule: ScheduleRule,
+    /// 触发方式
+    trigger: CompiledTrigger,
+}
+
+/// 录制调度器
+pub struct Scheduler {
+    /// 调度配置
+    config: ScheduleConfig,
+    /// 编译后的启用规则
+    rules: Vec<CompiledRule>,
+    /// 时区
+    timezone: ScheduleTimezone,
+    /// 全局录制配置，重启后恢复的计划录制停止时使用
+    default_recording: RecordingConfig,
+    /// 持久化状态
+    state: PersistedState,
+    /// 状态文件路径
+    state_path: PathBuf,
+    /// 计划开始录制前录制器使用的配置，停止时恢复
+    previous_recording: Option<RecordingConfig>,
+    /// 本次运行中调度器开始的录制是否仍由调度器负责，重启后为 false
+    started: bool,
+}
+
+impl Scheduler {
+    /// 创建调度器，校验所有规则并加载上次保存的状态
+    pub fn new(config: ScheduleConfig, default_recording: RecordingConfig) -> Result<Self> {
+        let timezone = ScheduleTimezone::parse(&config.timezone)?;
+
+        let mut rules = Vec::new();
+        for rule in config.rules.iter().filter(|r| r.enabled) {
+            if rules.iter().any(|r: &CompiledRule| r.rule.name == rule.name) {
+                return Err(anyhow!("录制计划规则名称重复: {}", rule.name));
+            }
+            let trigger = CompiledTrigger::compile(&rule.trigger)
+                .with_context(|| format!("录制计划规则 {} 无效", rule.name))?;
+            rules.push(CompiledRule {
+                rule: rule.clone(),
+                trigger,
+            });
+        }
+
+        let state_path = PathBuf::from(&config.state_file);
+        let state = load_state(&state_path);
+
+        Ok(Self {
+            config,
+            rules,
+            timezone,
+            default_recording,
+            state,
+            state_path,
+            previous_recording: None,
+            started: false,
+        })
+    }
+
+    /// 检查间隔
+    pub fn check_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.config.check_interval.max(1))
+    }
+
+    /// 当前时区下的时间
+    fn now(&self) -> NaiveDateTime {
+        self.timezone.to_local(Utc::now())
+    }
+
+    /// 当前生效的规则及其窗口
+    fn active_window(&self, now: NaiveDateTime) -> Option<(&CompiledRule, NaiveDateTime)> {
+        self.rules.iter()
+            .find_map(|rule| rule.trigger.window_at(now).map(|(_, end)| (rule, end)))
+    }
+
+    /// 列出接下来的若干次计划录制，按开始时间排序
+    pub fn upcoming(&self, limit: usize) -> Vec<PlannedRun> {
+        let now = self.now();
+        let mut runs: Vec<(NaiveDateTime, NaiveDateTime, &str)> = Vec::new();
+
+        for rule in &self.rules {
+            let mut cursor = now;
+            while let Some((start, end)) = rule.trigger.next_window(cursor) {
+                runs.push((start, end, &rule.rule.name));
+                if runs.iter().filter(|r| r.2 == rule.rule.name).count() >= limit {
+                    break;
+                }
+                cursor = start;
+            }
+        }
+
+        runs.sort_by_key(|r| r.0);
+        runs.truncate(limit);
+        runs.into_iter()
+            .map(|(start, end, rule)| PlannedRun {
+                rule: rule.to_string(),
+                start: self.timezone.format(start),
+                end: self.timezone.format(end),
+            })
+            .collect()
+    }
+
+    /// 下一次计划录制
+    pub fn next_run(&self) -> Option<PlannedRun> {
+        self.upcoming(1).into_iter().next()
+    }
+
+    /// 根据当前时间开始或停止录制
+    pub fn tick(&mut self, recorder: &mut VideoRecorder) {
+        let now = self.now();
+        let desired = self.active_window(now)
+            .map(|(rule, end)| (rule.rule.clone(), end));
+
+        if self.state.override_until.as_deref()
+            .and_then(|t| self.timezone.parse_local(t))
+            .is_some_and(|until| now >= until)
+        {
+            self.state.override_until = None;
+            self.save_state();
+        }
+
+        match (desired, self.state.active_rule.clone()) {
+            // 计划录制已被手动停止，窗口结束前不再接管
+            (Some(_), None) if self.state.override_until.is_some() => {}
+            (Some((rule, end)), active) => {
+                let same_rule = active.as_deref() == Some(rule.name.as_str());
+
+                // 本次运行中开始的录制停止了但没有失败，说明被手动停止
+                if same_rule && self.started && recorder.state() == RecorderState::Idle {
+                    info!("录制计划 {} 的录制已被手动停止，{} 前不再自动开始",
+                        rule.name, self.timezone.format(end));
+                    self.state.override_until = Some(self.timezone.format(end));
+                    self.stop(recorder, now);
+                    return;
+                }
+
+                if !same_rule {
+                    if active.is_some() {
+                        // 上一个规则的窗口已被新规则接替
+                        self.stop(recorder, now);
+                    } else if recorder.state().is_active() {
+                        warn!("录制计划 {} 已到开始时间，但当前有手动录制在进行，暂不接管", rule.name);
+                        return;
+                    }
+                }
+
+                // 同一规则的录制因重启或失败中断时重新开始
+                if !recorder.state().is_active() {
+                    self.start(recorder, &rule, end, now);
+                }
+            }
+            (None, Some(_)) => self.stop(recorder, now),
+            (None, None) => {}
+        }
+    }
+
+    /// 按规则开始录制
+    ///
+    /// 规则没有指定录制配置时沿用录制器当前的配置，开始前的配置在停止时恢复。
+    fn start(&mut self, recorder: &mut VideoRecorder, rule: &ScheduleRule, end: NaiveDateTime, now: NaiveDateTime) {
+        let previous = self.previous_recording.get_or_insert_with(|| recorder.config().clone());
+        let config = rule.recording.clone().unwrap_or_else(|| previous.clone());
+        if let Err(e) = recorder.set_config(config) {
+            error!("应用录制计划 {} 的录制配置失败: {}", rule.name, e);
+            return;
+        }
+
+        match recorder.start_triggered(TriggerSource::Schedule) {
+            Ok(path) => {
+                info!("录制计划 {} 开始录制: {}，预计结束于 {}",
+                    rule.name, path.display(), self.timezone.format(end));
+                self.state.active_rule = Some(rule.name.clone());
+                self.state.window_end = Some(self.timezone.format(end));
+                self.state.last_started = Some(self.timezone.format(now));
+                self.started = true;
+                self.save_state();
+            }
+            Err(e) => {
+                error!("录制计划 {} 开始录制失败: {}", rule.name, e);
+                if let Some(previous) = self.previous_recording.take() {
+                    if let Err(e) = recorder.set_config(previous) {
+                        error!("恢复录制配置失败: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 停止调度器开始的录制并恢复开始前的录制配置
+    ///
+    /// 重启后恢复的录制不知道开始前的配置，此时恢复为全局录制配置。
+    fn stop(&mut self, recorder: &mut VideoRecorder, now: NaiveDateTime) {
+        let rule = self.state.active_rule.take().unwrap_or_default();
+        self.started = false;
+
+        if recorder.state().is_active() {
+            match recorder.stop_recording() {
+                Ok(summary) => info!("录制计划 {} 结束录制: {:?}，共 {} 帧",
+                    rule, summary.path, summary.frames),
+                Err(e) => error!("录制计划 {} 停止录制失败: {}", rule, e),
+            }
+        }
+
+        let previous = self.previous_recording.take()
+            .unwrap_or_else(|| self.default_recording.clone());
+        if let Err(e) = recorder.set_config(previous) {
+            error!("恢复录制配置失败: {}", e);
+        }
+
+        self.state.window_end = None;
+        self.state.last_stopped = Some(self.timezone.format(now));
+        self.save_state();
+
+        if let Some(next) = self.next_run() {
+            info!("下一次计划录制: {} {} - {}", next.rule, next.start, next.end);
+        }
+    }
+
+    /// 保存调度状态
+    fn save_state(&self) {
+        if let Err(e) = save_state(&self.state_path, &self.state) {
+            error!("保存调度状态失败: {}: {}", self.state_path.display(), e);
+        }
+    }
+}
+
+/// 读取调度状态，文件不存在或损坏时返回空状态
+fn load_state(path: &Path) -> PersistedState {
+    if !path.exists() {
+        return PersistedState::default();
+    }
+
+    match std::fs::read_to_string(path).map(|content| serde_json::from_str(&content)) {
+        Ok(Ok(state)) => state,
+        Ok(Err(e)) => {
+            warn!("调度状态文件损坏，已忽略: {}: {}", path.display(), e);
+            PersistedState::default()
+        }
+        Err(e) => {
+            warn!("读取调度状态失败: {}: {}", path.display(), e);
+            PersistedState::default()
+        }
+    }
+}
+
+/// 保存调度状态
+fn save_state(path: &Path, state: &PersistedState) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let content = serde_json::to_string_pretty(state)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)?;
+
+    Ok(())
+}
@@ -0,0 +1,842 @@
+//! AVI (MJPEG) 封装读写模块
+//!
+//! 纯 Rust 实现的 AVI 1.0 复用器和解复用器，视频流固定为单路 MJPEG，
+//! 每帧都是一张独立的 JPEG 图像，不依赖系统 FFmpeg。写入时在文件末尾
+//! 生成 idx1 索引，读取时优先使用索引，缺失时顺序扫描 movi 数据块。
+//!
+//! 录制过程中可以定期写入索引检查点，使文件在任意时刻断电后仍然可以播放；
+//! `repair_avi` 用于在启动时修复未正常结束的文件。
+
+use crate::{Error, Result};
+use std::fs::OpenOptions;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// 视频数据块标识（第0路流，压缩视频）
+const VIDEO_CHUNK_ID: &[u8; 4] = b"00dc";
+
+/// 未压缩视频数据块标识
+const VIDEO_CHUNK_ID_DIB: &[u8; 4] = b"00db";
+
+/// avih 标志：文件带有 idx1 索引
+const AVIF_HASINDEX: u32 = 0x10;
+
+/// idx1 标志：关键帧
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+/// AVI 1.0 单个 RIFF 块的大小上限
+const MAX_RIFF_SIZE: u64 = u32::MAX as u64;
+
+/// 接近大小上限时为后续帧和索引预留的空间，录制在此之前切换到新文件
+const ROLLOVER_MARGIN: u64 = 64 * 1024 * 1024;
+
+/// 索引项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AviIndexEntry {
+    /// 数据块负载在文件中的绝对偏移
+    pub offset: u64,
+    /// 负载大小
+    pub size: u32,
+    /// 是否为关键帧
+    pub keyframe: bool,
+}
+
+/// AVI 写入器
+pub struct AviWriter<W: Write + Seek> {
+    /// 底层输出
+    inner: W,
+    /// 帧宽度
+    width: u32,
+    /// 帧高度
+    height: u32,
+    /// 帧率
+    fps: u32,
+    /// movi 列表中 "movi" 标识的位置，idx1 偏移以此为基准
+    movi_fourcc_pos: u64,
+    /// 当前写入位置
+    position: u64,
+    /// 已写入的帧索引
+    index: Vec<AviIndexEntry>,
+    /// 最大帧大小
+    max_frame_size: u32,
+}
+
+impl<W: Write + Seek> AviWriter<W> {
+    /// 创建写入器并写出文件头
+    pub fn new(mut inner: W, width: u32, height: u32, fps: u32) -> Result<Self> {
+        let fps = fps.max(1);
+        let header = build_header(width, height, fps, 0, 0, 0);
+        inner.write_all(&header)?;
+
+        // 头部最后 12 字节是 "LIST" + 大小 + "movi"
+        let movi_fourcc_pos = header.len() as u64 - 4;
+
+        Ok(Self {
+            inner,
+            width,
+            height,
+            fps,
+            movi_fourcc_pos,
+            position: header.len() as u64,
+            index: Vec::new(),
+            max_frame_size: 0,
+        })
+    }
+
+    /// 写入一帧 JPEG 数据
+    pub fn write_frame(&mut self, jpeg: &[u8]) -> Result<()> {
+        let size = jpeg.len() as u64;
+        let padded = size + (size & 1);
+        let index_size = (self.index.len() as u64 + 1) * 16 + 8;
+
+        if self.position + 8 + padded + index_size > MAX_RIFF_SIZE {
+            return Err(Error::VideoProcessing("AVI文件超过4GB上限".to_string()));
+        }
+
+        self.inner.write_all(VIDEO_CHUNK_ID)?;
+        self.inner.write_all(&(size as u32).to_le_bytes())?;
+        self.inner.write_all(jpeg)?;
+        if size & 1 == 1 {
+            self.inner.write_all(&[0])?;
+        }
+
+        self.index.push(AviIndexEntry {
+            offset: self.position + 8,
+            size: size as u32,
+            keyframe: true,
+        });
+        self.position += 8 + padded;
+        self.max_frame_size = self.max_frame_size.max(size as u32);
+
+        Ok(())
+    }
+
+    /// 已写入帧数
+    pub fn frame_count(&self) -> u64 {
+        self.index.len() as u64
+    }
+
+    /// 已写入字节数（不含尚未写出的索引）
+    pub fn bytes_written(&self) -> u64 {
+        self.position
+    }
+
+    /// 是否已接近 4GB 上限，录制应在写入下一帧前切换到新文件
+    pub fn near_size_limit(&self) -> bool {
+        let index_size = self.index.len() as u64 * 16 + 8;
+        self.position + index_size + ROLLOVER_MARGIN >= MAX_RIFF_SIZE
+    }
+
+    /// 写入索引检查点
+    ///
+    /// 在当前 movi 数据之后写出 idx1 索引并回填文件头，使文件在此刻是完整
+    /// 可播放的。之后写入的帧会覆盖这份索引，结束录制时重新生成。
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.write_index()?;
+        self.inner.seek(SeekFrom::Start(self.position))?;
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    /// 获取底层输出的引用，例如用于将数据同步到磁盘
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// 写出 idx1 索引并回填文件头中的大小和帧数
+    pub fn finish(mut self) -> Result<W> {
+        let file_end = self.write_index()?;
+        self.inner.seek(SeekFrom::Start(file_end))?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    /// 在 movi 数据之后写出 idx1 索引并回填文件头，返回文件结束位置
+    fn write_index(&mut self) -> Result<u64> {
+        self.inner.seek(SeekFrom::Start(self.position))?;
+        let idx1 = build_idx1(&self.index, self.movi_fourcc_pos);
+        self.inner.write_all(&idx1)?;
+
+        let movi_end = self.position;
+        let file_end = movi_end + idx1.len() as u64;
+
+        let header = build_header(
+            self.width,
+            self.height,
+            self.fps,
+            self.index.len() as u32,
+            self.max_frame_size,
+            (movi_end - self.movi_fourcc_pos) as u32,
+        );
+        self.inner.seek(SeekFrom::Start(0))?;
+        self.inner.write_all(&header)?;
+
+        // RIFF 大小不含开头的 "RIFF" 和大小字段
+        self.inner.seek(SeekFrom::Start(4))?;
+        self.inner.write_all(&((file_end - 8) as u32).to_le_bytes())?;
+
+        Ok(file_end)
+    }
+}
+
+/// 生成 idx1 索引数据块
+fn build_idx1(index: &[AviIndexEntry], movi_fourcc_pos: u64) -> Vec<u8> {
+    let mut idx1 = Vec::with_capacity(8 + index.len() * 16);
+    idx1.extend_from_slice(b"idx1");
+    idx1.extend_from_slice(&((index.len() * 16) as u32).to_le_bytes());
+    for entry in index {
+        // idx1 中的偏移指向数据块头，相对 "movi" 标识
+        let relative = entry.offset - 8 - movi_fourcc_pos;
+        idx1.extend_from_slice(VIDEO_CHUNK_ID);
+        idx1.extend_from_slice(&(if entry.keyframe { AVIIF_KEYFRAME } else { 0 }).to_le_bytes());
+        idx1.extend_from_slice(&(relative as u32).to_le_bytes());
+        idx1.extend_from_slice(&entry.size.to_le_bytes());
+    }
+    idx1
+}
+
+/// 生成从 "RIFF" 到 "movi" 标识为止的文件头
+fn build_header(
+    width: u32,
+    height: u32,
+    fps: u32,
+    total_frames: u32,
+    max_frame_size: u32,
+    movi_size: u32,
+) -> Vec<u8> {
+    let mut avih = Vec::with_capacity(56);
+    put_u32(&mut avih, 1_000_000 / fps); // dwMicroSecPerFrame
+    put_u32(&mut avih, max_frame_size.saturating_mul(fps)); // dwMaxBytesPerSec
+    put_u32(&mut avih, 0); // dwPaddingGranularity
+    put_u32(&mut avih, AVIF_HASINDEX); // dwFlags
+    put_u32(&mut avih, total_frames); // dwTotalFrames
+    put_u32(&mut avih, 0); // dwInitialFrames
+    put_u32(&mut avih, 1); // dwStreams
+    put_u32(&mut avih, max_frame_size + 8); // dwSuggestedBufferSize
+    put_u32(&mut avih, width);
+    put_u32(&mut avih, height);
+    avih.extend_from_slice(&[0u8; 16]); // dwReserved
+
+    let mut strh = Vec::with_capacity(56);
+    strh.extend_from_slice(b"vids");
+    strh.extend_from_slice(b"MJPG");
+    put_u32(&mut strh, 0); // dwFlags
+    put_u16(&mut strh, 0); // wPriority
+    put_u16(&mut strh, 0); // wLanguage
+    put_u32(&mut strh, 0); // dwInitialFrames
+    put_u32(&mut strh, 1); // dwScale
+    put_u32(&mut strh, fps); // dwRate
+    put_u32(&mut strh, 0); // dwStart
+    put_u32(&mut strh, total_frames); // dwLength
+    put_u32(&mut strh, max_frame_size + 8); // dwSuggestedBufferSize
+    put_u32(&mut strh, u32::MAX); // dwQuality
+    put_u32(&mut strh, 0); // dwSampleSize
+    put_u16(&mut strh, 0); // rcFrame
+    put_u16(&mut strh, 0);
+    put_u16(&mut strh, width as u16);
+    put_u16(&mut strh, height as u16);
+
+    let mut strf = Vec::with_capacity(40);
+    put_u32(&mut strf, 40); // biSize
+    put_u32(&mut strf, width);
+    put_u32(&mut strf, height);
+    put_u16(&mut strf, 1); // biPlanes
+    put_u16(&mut strf, 24); // biBitCount
+    strf.extend_from_slice(b"MJPG");
+    put_u32(&mut strf, width * height * 3); // biSizeImage
+    strf.extend_from_slice(&[0u8; 16]);
+
+    let mut strl = Vec::new();
+    strl.extend_from_slice(b"strl");
+    put_chunk(&mut strl, b"strh", &strh);
+    put_chunk(&mut strl, b"strf", &strf);
+
+    let mut hdrl = Vec::new();
+    hdrl.extend_from_slice(b"hdrl");
+    put_chunk(&mut hdrl, b"avih", &avih);
+    put_chunk(&mut hdrl, b"LIST", &strl);
+
+    let mut header = Vec::new();
+    header.extend_from_slice(b"RIFF");
+    put_u32(&mut header, 0); // RIFF 大小在结束时回填
+    header.extend_from_slice(b"AVI ");
+    put_chunk(&mut header, b"LIST", &hdrl);
+    header.extend_from_slice(b"LIST");
+    put_u32(&mut header, movi_size);
+    header.extend_from_slice(b"movi");
+
+    header
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_chunk(buf: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    buf.extend_from_slice(id);
+    put_u32(buf, data.len() as u32);
+    buf.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        buf.push(0);
+    }
+}
+
+/// AVI 读取器
+pub struct AviReader<R: Read + Seek> {
+    /// 底层输入
+    inner: R,
+    /// 帧宽度
+    width: u32,
+    /// 帧高度
+    height: u32,
+    /// 帧率
+    fps: f64,
+    /// 视频压缩格式 FourCC
+    compression: [u8; 4],
+    /// 视频帧索引
+    index: Vec<AviIndexEntry>,
+}
+
+impl<R: Read + Seek> AviReader<R> {
+    /// 创建读取器并解析文件头和索引
+    pub fn new(mut inner: R) -> Result<Self> {
+        let file_size = inner.seek(SeekFrom::End(0))?;
+        let layout = parse_layout(&mut inner, file_size)?;
+
+        let movi_start = layout.movi_fourcc_pos + 4;
+        // 未正常结束的录制文件 movi 大小为0，数据一直延续到文件末尾
+        let movi_end = if layout.movi_size == 0 || layout.movi_fourcc_pos + layout.movi_size as u64 > file_size {
+            file_size
+        } else {
+            layout.movi_fourcc_pos + layout.movi_size as u64
+        };
+
+        let index = match layout.idx1 {
+            Some((start, size)) => parse_idx1(&mut inner, start, size, layout.movi_fourcc_pos, file_size)?,
+            None => Vec::new(),
+        };
+
+        // 索引缺失或为空时顺序扫描 movi 列表
+        let index = if index.is_empty() {
+            scan_movi(&mut inner, movi_start, movi_end)?
+        } else {
+            index
+        };
+
+        Ok(Self {
+            inner,
+            width: layout.width,
+            height: layout.height,
+            fps: layout.fps,
+            compression: layout.compression,
+            index,
+        })
+    }
+
+    /// 帧宽度
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// 帧高度
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 帧率
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// 视频压缩格式 FourCC，如 "MJPG"
+    pub fn compression(&self) -> String {
+        String::from_utf8_lossy(&self.compression).to_string()
+    }
+
+    /// 视频帧索引
+    pub fn index(&self) -> &[AviIndexEntry] {
+        &self.index
+    }
+
+    /// 总帧数
+    pub fn frame_count(&self) -> u64 {
+        self.index.len() as u64
+    }
+
+    /// 读取指定帧的原始数据
+    pub fn read_frame(&mut self, frame: usize) -> Result<Vec<u8>> {
+        let entry = *self.index.get(frame)
+            .ok_or_else(|| Error::VideoProcessing(format!("帧序号超出范围: {}", frame)))?;
+
+        let mut data = vec![0u8; entry.size as usize];
+        self.inner.seek(SeekFrom::Start(entry.offset))?;
+        self.inner.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+}
+
+/// 顶层数据块布局
+struct AviLayout {
+    /// RIFF 头中记录的大小
+    riff_size: u32,
+    /// 帧宽度
+    width: u32,
+    /// 帧高度
+    height: u32,
+    /// 帧率
+    fps: f64,
+    /// 视频压缩格式 FourCC
+    compression: [u8; 4],
+    /// "movi" 标识的位置
+    movi_fourcc_pos: u64,
+    /// movi 列表头中记录的大小（含 "movi" 标识）
+    movi_size: u32,
+    /// idx1 数据的位置和大小
+    idx1: Option<(u64, u32)>,
+}
+
+impl AviLayout {
+    /// 文件是否由写入器正常结束：大小字段完整，idx1 紧跟 movi 并延续到文件末尾
+    fn is_complete(&self, file_size: u64) -> bool {
+        let movi_end = self.movi_fourcc_pos + self.movi_size as u64;
+        self.riff_size as u64 + 8 == file_size
+            && self.movi_size != 0
+            && self.idx1.is_some_and(|(start, size)| {
+                start == movi_end + 8 && start + size as u64 == file_size
+            })
+    }
+}
+
+/// 遍历顶层数据块，解析文件头并定位 movi 和 idx1
+fn parse_layout<R: Read + Seek>(inner: &mut R, file_size: u64) -> Result<AviLayout> {
+    inner.seek(SeekFrom::Start(0))?;
+
+    let mut riff = [0u8; 12];
+    inner.read_exact(&mut riff)?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"AVI " {
+        return Err(Error::VideoProcessing("不是有效的AVI文件".to_string()));
+    }
+
+    let mut header = None;
+    let mut movi: Option<(u64, u32)> = None;
+    let mut idx1: Option<(u64, u32)> = None;
+
+    let mut pos = 12u64;
+    while pos + 8 <= file_size {
+        inner.seek(SeekFrom::Start(pos))?;
+        let (id, size) = read_chunk_header(inner)?;
+        let data_start = pos + 8;
+        // 被截断的文件里最后一个数据块的大小可能超出文件末尾
+        let data_end = (data_start + size as u64).min(file_size);
+
+        if &id == b"LIST" {
+            let mut list_type = [0u8; 4];
+            inner.read_exact(&mut list_type)?;
+            match &list_type {
+                b"hdrl" => {
+                    header = Some(parse_hdrl(inner, data_start + 4, data_end)?);
+                }
+                b"movi" => {
+                    movi = Some((data_start, size));
+                    // movi 大小未回填时后面不会再有可识别的数据块
+                    if size == 0 || data_start + size as u64 > file_size {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        } else if &id == b"idx1" {
+            idx1 = Some((data_start, size));
+        }
+
+        pos = data_start + size as u64 + (size as u64 & 1);
+    }
+
+    let (width, height, fps, compression) = header
+        .ok_or_else(|| Error::VideoProcessing("AVI文件中没有视频流".to_string()))?;
+    let (movi_fourcc_pos, movi_size) = movi
+        .ok_or_else(|| Error::VideoProcessing("AVI文件缺少movi数据".to_string()))?;
+
+    Ok(AviLayout {
+        riff_size: u32::from_le_bytes([riff[4], riff[5], riff[6], riff[7]]),
+        width,
+        height,
+        fps,
+        compression,
+        movi_fourcc_pos,
+        movi_size,
+        idx1,
+    })
+}
+
+/// 修复未正常结束的 AVI 文件
+///
+/// 文件完整时返回 `Ok(None)`。否则从 movi 开始重新扫描完整的视频数据块，
+/// 截掉末尾写了一半的数据，重建 idx1 索引并回填文件头，返回恢复出的帧数。
+/// 只能修复由 `AviWriter` 写出的文件。
+pub fn repair_avi(path: &Path) -> Result<Option<u64>> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let file_size = file.metadata()?.len();
+
+    let mut reader = BufReader::new(&file);
+    let layout = parse_layout(&mut reader, file_size)?;
+    if layout.is_complete(file_size) {
+        return Ok(None);
+    }
+
+    let header_len = build_header(0, 0, 1, 0, 0, 0).len() as u64;
+    if layout.movi_fourcc_pos + 4 != header_len {
+        return Err(Error::VideoProcessing(format!(
+            "无法修复非本程序写入的AVI文件: {}", path.display()
+        )));
+    }
+
+    let check_jpeg = matches!(&layout.compression, b"MJPG" | b"mjpg");
+    let index = scan_complete_frames(&mut reader, header_len, file_size, check_jpeg)?;
+    drop(reader);
+
+    let movi_end = index.last()
+        .map(|e| e.offset + e.size as u64 + (e.size as u64 & 1))
+        .unwrap_or(header_len);
+    let max_frame_size = index.iter().map(|e| e.size).max().unwrap_or(0);
+
+    file.set_len(movi_end)?;
+    file.seek(SeekFrom::Start(movi_end))?;
+    let idx1 = build_idx1(&index, layout.movi_fourcc_pos);
+    file.write_all(&idx1)?;
+    let file_end = movi_end + idx1.len() as u64;
+
+    let header = build_header(
+        layout.width,
+        layout.height,
+        (layout.fps.round() as u32).max(1),
+        index.len() as u32,
+        max_frame_size,
+        (movi_end - layout.movi_fourcc_pos) as u32,
+    );
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&header)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&((file_end - 8) as u32).to_le_bytes())?;
+    file.sync_all()?;
+
+    Ok(Some(index.len() as u64))
+}
+
+/// 从 `start` 开始扫描连续完整的视频数据块，遇到无法识别或不完整的数据块即停止
+///
+/// `check_jpeg` 为 true 时还要求数据以 JPEG 的 SOI 开头、EOI 结尾，
+/// 以排除断电后文件长度已增长但内容尚未落盘的数据块。
+fn scan_complete_frames<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    check_jpeg: bool,
+) -> Result<Vec<AviIndexEntry>> {
+    let mut index = Vec::new();
+
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (id, size) = read_chunk_header(reader)?;
+        let data_start = pos + 8;
+
+        if (&id != VIDEO_CHUNK_ID && &id != VIDEO_CHUNK_ID_DIB) || data_start + size as u64 > end {
+            break;
+        }
+
+        if check_jpeg {
+            let data = read_bytes(reader, size as usize)?;
+            if !data.starts_with(&[0xFF, 0xD8]) || !data.ends_with(&[0xFF, 0xD9]) {
+                break;
+            }
+        }
+
+        index.push(AviIndexEntry {
+            offset: data_start,
+            size,
+            keyframe: true,
+        });
+        pos = data_start + size as u64 + (size as u64 & 1);
+    }
+
+    Ok(index)
+}
+
+/// 读取数据块头（标识和大小）
+fn read_chunk_header<R: Read>(reader: &mut R) -> Result<([u8; 4], u32)> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let mut id = [0u8; 4];
+    id.copy_from_slice(&header[0..4]);
+    let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    Ok((id, size))
+}
+
+/// 解析 hdrl 列表，返回宽、高、帧率和压缩格式
+fn parse_hdrl<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<(u32, u32, f64, [u8; 4])> {
+    let mut width = 0;
+    let mut height = 0;
+    let mut fps = 0.0;
+    let mut compression = [0u8; 4];
+    let mut found_video = false;
+
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (id, size) = read_chunk_header(reader)?;
+        let data_start = pos + 8;
+
+        match &id {
+            b"avih" => {
+                let data = read_bytes(reader, size.min(56) as usize)?;
+                if data.len() >= 40 {
+                    width = le_u32(&data, 32);
+                    height = le_u32(&data, 36);
+                    let us_per_frame = le_u32(&data, 0);
+                    if us_per_frame > 0 && fps == 0.0 {
+                        fps = 1_000_000.0 / us_per_frame as f64;
+                    }
+                }
+            }
+            b"LIST" => {
+                let list_type = read_bytes(reader, 4)?;
+                if list_type.as_slice() == b"strl" && !found_video {
+                    if let Some(stream) = parse_strl(reader, data_start + 4, data_start + size as u64)? {
+                        if stream.rate > 0.0 {
+                            fps = stream.rate;
+                        }
+                        compression = stream.fourcc;
+                        if stream.width > 0 && stream.height > 0 {
+                            width = stream.width;
+                            height = stream.height;
+                        }
+                        found_video = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        pos = data_start + size as u64 + (size as u64 & 1);
+    }
+
+    if !found_video {
+        return Err(Error::VideoProcessing("AVI文件中没有视频流".to_string()));
+    }
+
+    Ok((width, height, fps, compression))
+}
+
+/// strl 列表中的视频流格式
+struct StreamFormat {
+    /// 帧率
+    rate: f64,
+    /// 压缩格式
+    fourcc: [u8; 4],
+    /// 帧宽度
+    width: u32,
+    /// 帧高度
+    height: u32,
+}
+
+/// 解析 strl 列表，仅处理视频流，其他流返回 None
+fn parse_strl<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<Option<StreamFormat>> {
+    let mut rate = 0.0;
+    let mut fourcc = [0u8; 4];
+    let mut width = 0;
+    let mut height = 0;
+    let mut is_video = false;
+
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (id, size) = read_chunk_header(reader)?;
+
+        match &id {
+            b"strh" => {
+                let data = read_bytes(reader, size.min(56) as usize)?;
+                if data.len() >= 28 && &data[0..4] == b"vids" {
+                    is_video = true;
+                    let scale = le_u32(&data, 20);
+                    let dw_rate = le_u32(&data, 24);
+                    if scale > 0 {
+                        rate = dw_rate as f64 / scale as f64;
+                    }
+                    fourcc.copy_from_slice(&data[4..8]);
+                }
+            }
+            b"strf" => {
+                let data = read_bytes(reader, size.min(40) as usize)?;
+                if data.len() >= 20 {
+                    width = le_u32(&data, 4);
+                    height = (le_u32(&data, 8) as i32).unsigned_abs();
+                    // BITMAPINFOHEADER 中的压缩格式比 strh 中的 fccHandler 更可靠
+                    fourcc.copy_from_slice(&data[16..20]);
+                }
+            }
+            _ => {}
+        }
+
+        pos += 8 + size as u64 + (size as u64 & 1);
+    }
+
+    Ok(is_video.then_some(StreamFormat { rate, fourcc, width, height }))
+}
+
+/// 解析 idx1 索引，只保留第0路视频流的数据块
+fn parse_idx1<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    size: u32,
+    movi_fourcc_pos: u64,
+    file_size: u64,
+) -> Result<Vec<AviIndexEntry>> {
+    let size = (size as u64).min(file_size.saturating_sub(start)) as usize;
+    reader.seek(SeekFrom::Start(start))?;
+    let data = read_bytes(reader, size - size % 16)?;
+
+    let raw: Vec<([u8; 4], u32, u32, u32)> = data.chunks_exact(16)
+        .map(|e| {
+            let mut id = [0u8; 4];
+            id.copy_from_slice(&e[0..4]);
+            (id, le_u32(e, 4), le_u32(e, 8), le_u32(e, 12))
+        })
+        .collect();
+
+    // 偏移可能相对 "movi" 标识，也可能是文件绝对偏移，用第一项判断
+    let base = match raw.first() {
+        Some((id, _, offset, _)) => {
+            reader.seek(SeekFrom::Start(movi_fourcc_pos + *offset as u64))?;
+            let mut probe = [0u8; 4];
+            if reader.read_exact(&mut probe).is_ok() && &probe == id {
+                movi_fourcc_pos
+            } else {
+                0
+            }
+        }
+        None => return Ok(Vec::new()),
+    };
+
+    Ok(raw.into_iter()
+        .filter(|(id, ..)| id == VIDEO_CHUNK_ID || id == VIDEO_CHUNK_ID_DIB)
+        .map(|(_, flags, offset, size)| AviIndexEntry {
+            offset: base + offset as u64 + 8,
+            size,
+            keyframe: flags & AVIIF_KEYFRAME != 0,
+        })
+        .filter(|e| e.offset + e.size as u64 <= file_size)
+        .collect())
+}
+
+/// 顺序扫描 movi 列表中的视频数据块
+fn scan_movi<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<Vec<AviIndexEntry>> {
+    let mut index = Vec::new();
+
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let (id, size) = read_chunk_header(reader)?;
+        let data_start = pos + 8;
+
+        if &id == b"LIST" {
+            // "rec " 列表中嵌套了数据块，进入列表内部继续扫描
+            pos = data_start + 4;
+            continue;
+        }
+
+        // 数据不完整的末尾数据块直接丢弃
+        if data_start + size as u64 > end {
+            break;
+        }
+
+        if &id == VIDEO_CHUNK_ID || &id == VIDEO_CHUNK_ID_DIB {
+            index.push(AviIndexEntry {
+                offset: data_start,
+                size,
+                keyframe: true,
+            });
+        }
+
+        pos = data_start + size as u64 + (size as u64 & 1);
+    }
+
+    Ok(index)
+}
+
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn frames_round_trip() {
+        // 奇数长度的数据块需要填充
+        let frames = [
+            vec![0xFF, 0xD8, 1, 0xFF, 0xD9],
+            vec![0xFF, 0xD8, 2, 2, 0xFF, 0xD9],
+            vec![0xFF, 0xD8, 3, 3, 3, 0xFF, 0xD9],
+        ];
+
+        let mut writer = AviWriter::new(Cursor::new(Vec::new()), 320, 240, 15).unwrap();
+        for frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = AviReader::new(Cursor::new(data)).unwrap();
+        assert_eq!((reader.width(), reader.height()), (320, 240));
+        assert_eq!(reader.fps(), 15.0);
+        assert_eq!(reader.compression(), "MJPG");
+        assert_eq!(reader.frame_count(), 3);
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(&reader.read_frame(i).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn repair_drops_partial_frame_and_rebuilds_index() {
+        let path = std::env::temp_dir().join(format!("repair-{}.avi", uuid::Uuid::new_v4()));
+        let frames = [
+            vec![0xFF, 0xD8, 1, 0xFF, 0xD9],
+            vec![0xFF, 0xD8, 2, 2, 0xFF, 0xD9],
+            [&[0xFF, 0xD8][..], &[3; 64], &[0xFF, 0xD9]].concat(),
+        ];
+
+        let mut writer = AviWriter::new(std::fs::File::create(&path).unwrap(), 16, 16, 10).unwrap();
+        writer.write_frame(&frames[0]).unwrap();
+        writer.checkpoint().unwrap();
+        writer.write_frame(&frames[1]).unwrap();
+        writer.write_frame(&frames[2]).unwrap();
+        let truncate_at = writer.bytes_written() - 20;
+        // 模拟断电：没有写出最终索引，最后一帧只写了一部分
+        drop(writer);
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(truncate_at).unwrap();
+
+        assert_eq!(repair_avi(&path).unwrap(), Some(2));
+        assert_eq!(repair_avi(&path).unwrap(), None);
+
+        let mut reader = AviReader::new(BufReader::new(std::fs::File::open(&path).unwrap())).unwrap();
+        assert_eq!(reader.frame_count(), 2);
+        assert_eq!(reader.read_frame(0).unwrap(), frames[0]);
+        assert_eq!(reader.read_frame(1).unwrap(), frames[1]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
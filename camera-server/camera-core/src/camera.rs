@@ -4,6 +4,8 @@
 //! 支持Linux(V4L2)和macOS(AVFoundation)平台。
 
 use crate::{Error, Result, config::CameraConfig};
+use crate::snapshot::{encode_image, SnapshotFormat, SnapshotOptions};
+use crate::stack::{stack_frames, Rgb16Image, StackOptions};
 use log::{info, error};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
@@ -613,6 +615,16 @@ impl Camera {
 
     /// 捕获一帧图像并直接保存为JPEG格式
     pub fn capture_jpeg(&mut self, quality: u8) -> Result<Vec<u8>> {
+        let options = SnapshotOptions {
+            quality,
+            ..SnapshotOptions::default()
+        };
+
+        self.capture_encoded(SnapshotFormat::Jpeg, &options)
+    }
+
+    /// 捕获一帧图像并按指定格式编码
+    pub fn capture_encoded(&mut self, format: SnapshotFormat, options: &SnapshotOptions) -> Result<Vec<u8>> {
         if !self.initialized {
             return Err(Error::CameraDevice("摄像头未初始化".to_string()));
         }
@@ -621,30 +633,35 @@ impl Camera {
             return Err(Error::CameraDevice("摄像头未开始采集".to_string()));
         }
 
-        // 捕获RGB图像
-        let rgb_image = self.capture_frame()?;
-
-        // 创建一个缓冲区来存储JPEG数据
-        let mut jpeg_buffer = Vec::new();
-
-        // 使用image库的JPEG编码器
-        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_buffer, quality);
-        match encoder.encode(
-            rgb_image.as_raw(),
-            rgb_image.width(),
-            rgb_image.height(),
-            image::ColorType::Rgb8
-        ) {
-            Ok(_) => {
-                info!("成功生成JPEG图像，大小: {} 字节", jpeg_buffer.len());
-                Ok(jpeg_buffer)
+        let image = match &options.stack {
+            Some(stack) => image::DynamicImage::ImageRgb16(self.capture_stacked(stack)?),
+            None => image::DynamicImage::ImageRgb8(self.capture_frame()?),
+        };
+
+        match encode_image(&image, format, options) {
+            Ok(data) => {
+                info!("成功生成{:?}图像，大小: {} 字节", format, data.len());
+                Ok(data)
             },
             Err(e) => {
-                error!("JPEG编码失败: {}", e);
-                Err(Error::CameraDevice(format!("JPEG编码失败: {}", e)))
+                error!("图像编码失败: {}", e);
+                Err(e)
             }
         }
     }
+
+    /// 连续捕获多帧并叠加降噪，输出16位图像
+    pub fn capture_stacked(&mut self, options: &StackOptions) -> Result<Rgb16Image> {
+        options.validate()?;
+
+        let mut frames = Vec::with_capacity(options.frames as usize);
+        for _ in 0..options.frames {
+            frames.push(self.capture_frame()?);
+        }
+
+        info!("叠加 {} 帧图像，方式: {:?}, 对齐: {}", frames.len(), options.mode, options.align);
+        stack_frames(&frames, options)
+    }
 }
 
 impl Drop for Camera {
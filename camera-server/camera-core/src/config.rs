@@ -1,6 +1,9 @@
 //! 配置模块
 
+use crate::Result;
+use crate::naming::{self, FilenameTemplate, TemplateKind};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// 摄像头配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +24,16 @@ pub struct CameraConfig {
     pub pixel_format: String,
 }
 
+impl CameraConfig {
+    /// 摄像头标识，取设备路径的文件名部分，如 "/dev/video0" 对应 "video0"
+    pub fn camera_id(&self) -> String {
+        Path::new(&self.device_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.device_path.clone())
+    }
+}
+
 impl Default for CameraConfig {
     fn default() -> Self {
         Self {
@@ -39,14 +52,69 @@ pub struct RecordingConfig {
     /// 输出目录
     pub output_dir: String,
     
-    /// 视频编码器，如 "h264", "h265"
+    /// 视频编码器，如 "mjpeg", "h264", "h265"
     pub encoder: String,
     
-    /// 视频容器格式，如 "mp4", "mkv"
+    /// 视频容器格式，如 "avi", "mp4", "mkv"，"y4m" 表示无损原始视频
     pub container: String,
     
-    /// 视频比特率，单位为 bps
+    /// 视频比特率，单位为 bps；CBR 时为固定码率，VBR 时为平均码率
     pub bitrate: u32,
+    
+    /// 码率控制方式，未设置时使用编码后端的默认方式
+    #[serde(default)]
+    pub rate_control: Option<RateControl>,
+    
+    /// 最大比特率（bps），VBR 和 CRF 模式下限制峰值码率
+    #[serde(default)]
+    pub max_bitrate: Option<u32>,
+    
+    /// CRF 模式下的质量参数，越小质量越高（H.264/H.265 为 0-51）
+    #[serde(default)]
+    pub crf: Option<u8>,
+    
+    /// 关键帧间隔（帧），回放时拖动进度需要较小的间隔
+    #[serde(default)]
+    pub keyframe_interval: Option<u32>,
+    
+    /// 连续 B 帧的最大数量，0 表示不使用 B 帧
+    #[serde(default)]
+    pub b_frames: Option<u32>,
+    
+    /// MJPEG 编码的 JPEG 质量 (1-100)
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+    
+    /// 录制帧率
+    #[serde(default = "default_recording_fps")]
+    pub fps: u32,
+    
+    /// 单个分段的最大时长（秒），达到后自动切换到新文件
+    #[serde(default)]
+    pub max_segment_duration: Option<u64>,
+    
+    /// 单个分段的最大大小（字节），达到后自动切换到新文件
+    #[serde(default)]
+    pub max_segment_bytes: Option<u64>,
+    
+    /// 文件名模板（不含扩展名），可用占位符见 `naming` 模块
+    #[serde(default = "default_recording_template")]
+    pub filename_template: String,
+    
+    /// 是否按日期分目录保存，如 `recordings/2025/05/13/`
+    #[serde(default)]
+    pub date_subdirs: bool,
+    
+    /// 封面图和缩略图条配置
+    #[serde(default)]
+    pub preview: PreviewConfig,
+}
+
+impl RecordingConfig {
+    /// 解析并校验文件名模板
+    pub fn filename_template(&self) -> Result<FilenameTemplate> {
+        FilenameTemplate::parse(&self.filename_template, TemplateKind::Recording)
+    }
 }
 
 impl Default for RecordingConfig {
@@ -56,24 +124,176 @@ impl Default for RecordingConfig {
             encoder: "h264".to_string(),
             container: "mp4".to_string(),
             bitrate: 4_000_000, // 4 Mbps
+            rate_control: None,
+            max_bitrate: None,
+            crf: None,
+            keyframe_interval: None,
+            b_frames: None,
+            jpeg_quality: default_jpeg_quality(),
+            fps: default_recording_fps(),
+            max_segment_duration: None,
+            max_segment_bytes: None,
+            filename_template: default_recording_template(),
+            date_subdirs: false,
+            preview: PreviewConfig::default(),
+        }
+    }
+}
+
+fn default_recording_fps() -> u32 {
+    30
+}
+
+fn default_jpeg_quality() -> u8 {
+    90
+}
+
+/// 录制预览图配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewConfig {
+    /// 录制结束时是否生成封面图和缩略图条
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// 封面图取录制开始后第几秒的画面，录制更短时取第一帧
+    #[serde(default = "default_poster_offset_secs")]
+    pub poster_offset_secs: f32,
+    
+    /// 缩略图间隔（秒）
+    #[serde(default = "default_thumbnail_interval_secs")]
+    pub thumbnail_interval_secs: u32,
+    
+    /// 缩略图宽度，高度按画面比例计算
+    #[serde(default = "default_thumbnail_width")]
+    pub thumbnail_width: u32,
+    
+    /// 单个缩略图条最多包含的缩略图数量
+    #[serde(default = "default_max_thumbnails")]
+    pub max_thumbnails: usize,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poster_offset_secs: default_poster_offset_secs(),
+            thumbnail_interval_secs: default_thumbnail_interval_secs(),
+            thumbnail_width: default_thumbnail_width(),
+            max_thumbnails: default_max_thumbnails(),
         }
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_poster_offset_secs() -> f32 {
+    1.0
+}
+
+fn default_thumbnail_interval_secs() -> u32 {
+    10
+}
+
+fn default_thumbnail_width() -> u32 {
+    160
+}
+
+fn default_max_thumbnails() -> usize {
+    120
+}
+
+/// 码率控制方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateControl {
+    /// 固定码率
+    Cbr,
+    /// 可变码率，按平均码率编码
+    Vbr,
+    /// 固定质量，MJPEG 使用 JPEG 质量，H.264/H.265 使用 CRF
+    Crf,
+}
+
+fn default_recording_template() -> String {
+    naming::DEFAULT_RECORDING_TEMPLATE.to_string()
+}
+
 /// 视频拆分配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SplitConfig {
     /// 输出目录
     pub output_dir: String,
     
-    /// 图像格式，如 "jpg", "png"
+    /// 图像格式：jpg、png、webp、tiff 或 bmp
     pub image_format: String,
     
-    /// 提取帧的频率，如每秒提取几帧
+    /// 提取帧的频率，如每秒提取几帧，仅 `rate` 抽帧方式使用
     pub frame_rate: f32,
     
+    /// 抽帧方式
+    #[serde(default)]
+    pub sampling: SamplingMode,
+    
+    /// 只拆分这些时间段，为空时拆分整个视频
+    #[serde(default)]
+    pub time_ranges: Vec<TimeRange>,
+    
+    /// 相似帧去重
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    
+    /// 输出前的裁剪、缩放和灰度转换
+    #[serde(default)]
+    pub transform: FrameTransform,
+    
     /// 图像质量 (1-100)
     pub quality: u8,
+    
+    /// 输出文件夹名模板
+    #[serde(default = "default_frame_dir_template")]
+    pub folder_template: String,
+    
+    /// 帧图片文件名模板（不含扩展名），必须包含 `{frame}`
+    #[serde(default = "default_frame_template")]
+    pub frame_template: String,
+    
+    /// 是否按日期分目录保存输出文件夹
+    #[serde(default)]
+    pub date_subdirs: bool,
+    
+    /// 同时运行的拆分任务数
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    
+    /// 拆分队列持久化文件，默认为输出目录下的 `.split_queue.json`
+    #[serde(default)]
+    pub queue_file: Option<String>,
+    
+    /// 保留的历史任务数
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+}
+
+impl SplitConfig {
+    /// 解析并校验输出文件夹名模板
+    pub fn folder_template(&self) -> Result<FilenameTemplate> {
+        FilenameTemplate::parse(&self.folder_template, TemplateKind::FrameDir)
+    }
+    
+    /// 解析并校验帧图片文件名模板
+    pub fn frame_template(&self) -> Result<FilenameTemplate> {
+        FilenameTemplate::parse(&self.frame_template, TemplateKind::Frame)
+    }
+    
+    /// 拆分队列持久化文件路径
+    pub fn queue_path(&self) -> PathBuf {
+        match &self.queue_file {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.output_dir).join(".split_queue.json"),
+        }
+    }
 }
 
 impl Default for SplitConfig {
@@ -82,7 +302,408 @@ impl Default for SplitConfig {
             output_dir: "./frames".to_string(),
             image_format: "jpg".to_string(),
             frame_rate: 1.0, // 每秒1帧
+            sampling: SamplingMode::default(),
+            time_ranges: Vec::new(),
+            dedup: DedupConfig::default(),
+            transform: FrameTransform::default(),
             quality: 90,
+            folder_template: default_frame_dir_template(),
+            frame_template: default_frame_template(),
+            date_subdirs: false,
+            max_concurrent: default_max_concurrent(),
+            queue_file: None,
+            history_limit: default_history_limit(),
+        }
+    }
+}
+
+/// 拆分抽帧方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SamplingMode {
+    /// 按 `frame_rate` 每秒提取固定帧数
+    #[default]
+    Rate,
+    /// 每隔 N 帧提取一帧，按原视频帧序号计算
+    Nth {
+        /// 间隔帧数，1 表示提取每一帧
+        n: u64,
+    },
+    /// 提取指定时间点（秒）最近的帧
+    Timestamps {
+        /// 时间点列表，不要求有序
+        timestamps: Vec<f64>,
+    },
+    /// 只提取关键帧，不适用于 Y4M、MJPEG AVI 等每一帧都是关键帧的视频
+    Keyframes,
+    /// 画面与上一张输出相比变化超过阈值时提取，用于从长时间静止的录像中挑选不同的画面
+    Scene {
+        /// 画面差异的计算方式
+        #[serde(default)]
+        metric: SceneMetric,
+        /// 差异阈值 (0.0 - 1.0)，超过该值视为场景变化
+        #[serde(default = "default_scene_threshold")]
+        threshold: f32,
+        /// 相邻两张输出之间的最小间隔（秒）
+        #[serde(default = "default_scene_min_gap")]
+        min_gap_secs: f64,
+    },
+}
+
+/// 场景变化的画面差异计算方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SceneMetric {
+    /// 亮度直方图差异，对镜头轻微晃动不敏感
+    #[default]
+    Histogram,
+    /// 缩小后逐像素的平均亮度差异，对画面中物体的移动更敏感
+    Pixel,
+}
+
+fn default_scene_threshold() -> f32 {
+    0.3
+}
+
+fn default_scene_min_gap() -> f64 {
+    1.0
+}
+
+/// 拆分时间段（秒）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeRange {
+    /// 开始时间
+    #[serde(default)]
+    pub start: f64,
+    /// 结束时间（不含），未设置时到视频结尾
+    #[serde(default)]
+    pub end: Option<f64>,
+}
+
+impl TimeRange {
+    /// 时间点是否在时间段内
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.start && self.end.is_none_or(|end| time < end)
+    }
+}
+
+/// 拆分输出图片的变换，依次执行裁剪、缩放和灰度转换
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameTransform {
+    /// 裁剪区域，坐标基于原视频画面
+    #[serde(default)]
+    pub crop: Option<CropRect>,
+    
+    /// 缩放方式
+    #[serde(default)]
+    pub resize: Option<ResizeMode>,
+    
+    /// 是否输出灰度图
+    #[serde(default)]
+    pub grayscale: bool,
+}
+
+impl FrameTransform {
+    /// 是否不做任何变换
+    pub fn is_identity(&self) -> bool {
+        self.crop.is_none() && self.resize.is_none() && !self.grayscale
+    }
+}
+
+/// 裁剪区域（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropRect {
+    /// 左上角横坐标
+    pub x: u32,
+    /// 左上角纵坐标
+    pub y: u32,
+    /// 宽度
+    pub width: u32,
+    /// 高度
+    pub height: u32,
+}
+
+/// 缩放方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// 等比缩放，使较长边不超过 `max`，不放大
+    Fit {
+        /// 最长边（像素）
+        max: u32,
+    },
+    /// 等比缩放到指定尺寸以内，空白部分用 `fill` 填充，输出尺寸固定
+    Letterbox {
+        /// 输出宽度
+        width: u32,
+        /// 输出高度
+        height: u32,
+        /// 填充颜色 (RGB)
+        #[serde(default)]
+        fill: [u8; 3],
+    },
+    /// 按比例缩放
+    Scale {
+        /// 缩放比例，如 0.5
+        factor: f32,
+    },
+}
+
+/// 相似帧去重配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupConfig {
+    /// 是否启用去重
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// 感知哈希算法
+    #[serde(default)]
+    pub algorithm: HashAlgorithm,
+    
+    /// 汉明距离阈值 (0-64)，与已保留帧的距离不超过该值视为重复
+    #[serde(default = "default_dedup_max_distance")]
+    pub max_distance: u32,
+    
+    /// 重复帧的处理方式
+    #[serde(default)]
+    pub action: DedupAction,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            algorithm: HashAlgorithm::default(),
+            max_distance: default_dedup_max_distance(),
+            action: DedupAction::default(),
+        }
+    }
+}
+
+/// 感知哈希算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// 均值哈希，最快，对亮度变化敏感
+    Ahash,
+    /// 差异哈希，速度和效果比较均衡
+    #[default]
+    Dhash,
+    /// DCT 哈希，对缩放和压缩最不敏感，计算量最大
+    Phash,
+}
+
+/// 重复帧的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupAction {
+    /// 不输出（拆分时）或删除（已有文件夹）重复帧
+    #[default]
+    Drop,
+    /// 保留重复帧，只在去重报告中标记
+    Flag,
+}
+
+fn default_dedup_max_distance() -> u32 {
+    6
+}
+
+fn default_max_concurrent() -> usize {
+    1
+}
+
+fn default_history_limit() -> usize {
+    100
+}
+
+fn default_frame_dir_template() -> String {
+    naming::DEFAULT_FRAME_DIR_TEMPLATE.to_string()
+}
+
+fn default_frame_template() -> String {
+    naming::DEFAULT_FRAME_TEMPLATE.to_string()
+}
+
+/// 联系表/雪碧图配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SheetConfig {
+    /// 生成的图片类型
+    #[serde(default)]
+    pub kind: SheetKind,
+    
+    /// 每行的缩略图数量
+    #[serde(default = "default_sheet_columns")]
+    pub columns: u32,
+    
+    /// 缩略图宽度，高度按画面比例计算
+    #[serde(default = "default_sheet_tile_width")]
+    pub tile_width: u32,
+    
+    /// 相邻缩略图之间的视频时长（秒），未设置时按时长和数量上限均匀分布
+    #[serde(default)]
+    pub interval_secs: Option<f64>,
+    
+    /// 缩略图数量上限
+    #[serde(default = "default_sheet_max_tiles")]
+    pub max_tiles: usize,
+    
+    /// 是否在缩略图上标注时间，仅联系表使用
+    #[serde(default = "default_true")]
+    pub labels: bool,
+    
+    /// 缩略图之间的间距（像素），仅联系表使用，雪碧图总是紧密排列
+    #[serde(default = "default_sheet_spacing")]
+    pub spacing: u32,
+    
+    /// 背景颜色 (RGB)
+    #[serde(default = "default_sheet_background")]
+    pub background: [u8; 3],
+    
+    /// 图像格式：jpg、png、webp、tiff 或 bmp
+    #[serde(default = "default_sheet_image_format")]
+    pub image_format: String,
+    
+    /// 图像质量 (1-100)
+    #[serde(default = "default_jpeg_quality")]
+    pub quality: u8,
+}
+
+impl Default for SheetConfig {
+    fn default() -> Self {
+        Self {
+            kind: SheetKind::default(),
+            columns: default_sheet_columns(),
+            tile_width: default_sheet_tile_width(),
+            interval_secs: None,
+            max_tiles: default_sheet_max_tiles(),
+            labels: true,
+            spacing: default_sheet_spacing(),
+            background: default_sheet_background(),
+            image_format: default_sheet_image_format(),
+            quality: default_jpeg_quality(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// 联系表/雪碧图类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SheetKind {
+    /// 联系表：带间距和时间标注的网格图，用于快速浏览长时间录像
+    #[default]
+    Contact,
+    /// 雪碧图：紧密排列的网格图，附带 WebVTT 和 JSON 索引，用于网页拖动预览
+    Sprite,
+}
+
+impl SheetKind {
+    /// 输出文件名中的类型名
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SheetKind::Contact => "contact",
+            SheetKind::Sprite => "sprite",
+        }
+    }
+}
+
+fn default_sheet_columns() -> u32 {
+    5
+}
+
+fn default_sheet_tile_width() -> u32 {
+    160
+}
+
+fn default_sheet_max_tiles() -> usize {
+    60
+}
+
+fn default_sheet_spacing() -> u32 {
+    4
+}
+
+fn default_sheet_background() -> [u8; 3] {
+    [24, 24, 24]
+}
+
+fn default_sheet_image_format() -> String {
+    "jpg".to_string()
+}
+
+/// 事件触发录制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecordingConfig {
+    /// 是否启用事件触发录制
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// 预录时长（秒），事件发生前缓存的画面会写入录制文件开头
+    #[serde(default = "default_pre_roll_secs")]
+    pub pre_roll_secs: f32,
+    
+    /// 后录时长（秒），最后一次事件之后继续录制的时间
+    #[serde(default = "default_post_roll_secs")]
+    pub post_roll_secs: f32,
+    
+    /// 触发文件路径，外部命令创建该文件即触发一次事件
+    #[serde(default)]
+    pub trigger_file: Option<String>,
+    
+    /// 运动检测配置
+    #[serde(default)]
+    pub motion: MotionConfig,
+}
+
+impl Default for EventRecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pre_roll_secs: default_pre_roll_secs(),
+            post_roll_secs: default_post_roll_secs(),
+            trigger_file: None,
+            motion: MotionConfig::default(),
+        }
+    }
+}
+
+fn default_pre_roll_secs() -> f32 {
+    3.0
+}
+
+fn default_post_roll_secs() -> f32 {
+    10.0
+}
+
+/// 运动检测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotionConfig {
+    /// 是否启用运动检测
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// 像素亮度变化阈值 (0-255)，超过该值的像素视为变化
+    #[serde(default = "default_motion_pixel_threshold")]
+    pub pixel_threshold: u8,
+    
+    /// 变化像素占画面的最小比例 (0.0 - 1.0)，超过该比例视为运动
+    #[serde(default = "default_motion_min_area")]
+    pub min_area: f32,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pixel_threshold: default_motion_pixel_threshold(),
+            min_area: default_motion_min_area(),
+        }
+    }
+}
+
+fn default_motion_pixel_threshold() -> u8 {
+    25
+}
+
+fn default_motion_min_area() -> f32 {
+    0.02
+}
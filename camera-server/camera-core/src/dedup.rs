@@ -0,0 +1,311 @@
+//! 相似帧去重模块
+//!
+//! 为每张图片计算 64 位感知哈希，与已保留图片的哈希比较汉明距离，
+//! 不超过阈值的视为重复。重复图片按保留的图片分组，去重报告列出每一组。
+//! 拆分时对抽出的帧去重，也可以对已有的帧文件夹去重。
+
+use crate::{Error, Result};
+use crate::config::{DedupAction, DedupConfig, HashAlgorithm};
+use crate::manifest::{is_frame_file, SplitManifest};
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 去重报告文件名
+pub const DEDUP_REPORT_FILE_NAME: &str = "dedup.json";
+
+/// 校验去重配置
+pub fn validate(config: &DedupConfig) -> Result<()> {
+    if config.max_distance > 64 {
+        return Err(Error::Config(format!("去重汉明距离阈值必须在 0-64 范围内: {}", config.max_distance)));
+    }
+    Ok(())
+}
+
+/// 计算图片的 64 位感知哈希
+pub fn image_hash(image: &DynamicImage, algorithm: HashAlgorithm) -> u64 {
+    let luma = image.to_luma8();
+
+    match algorithm {
+        HashAlgorithm::Ahash => {
+            let small = image::imageops::resize(&luma, 8, 8, FilterType::Triangle);
+            let mean = small.as_raw().iter().map(|&v| v as u32).sum::<u32>() / 64;
+            bits(small.as_raw().iter().map(|&v| v as u32 > mean))
+        }
+        HashAlgorithm::Dhash => {
+            let small = image::imageops::resize(&luma, 9, 8, FilterType::Triangle);
+            bits((0..8).flat_map(|y| {
+                let small = &small;
+                (0..8).map(move |x| small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0])
+            }))
+        }
+        HashAlgorithm::Phash => phash(&luma),
+    }
+}
+
+/// 两个哈希的汉明距离
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// 哈希的十六进制表示
+pub fn hash_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+/// 将最多 64 个比特按顺序组合为哈希
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.take(64).fold(0u64, |hash, bit| (hash << 1) | bit as u64)
+}
+
+/// DCT 哈希：缩小到 32x32 后取左上角 8x8 的低频系数，与中位数比较
+fn phash(luma: &GrayImage) -> u64 {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let small = image::imageops::resize(luma, SIZE as u32, SIZE as u32, FilterType::Triangle);
+    let pixels: Vec<f64> = small.as_raw().iter().map(|&v| v as f64).collect();
+
+    let cos: Vec<f64> = (0..LOW)
+        .flat_map(|u| (0..SIZE).map(move |x| {
+            ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * SIZE) as f64).cos()
+        }))
+        .collect();
+
+    let mut coefficients = [0f64; LOW * LOW];
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += pixels[y * SIZE + x] * cos[u * SIZE + x] * cos[v * SIZE + y];
+                }
+            }
+            coefficients[v * LOW + u] = sum;
+        }
+    }
+
+    // 直流分量只反映整体亮度，不参与中位数计算
+    let mut sorted: Vec<f64> = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    bits(coefficients.iter().map(|&c| c > median))
+}
+
+/// 被判定为重复的图片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupMember {
+    /// 文件名
+    pub file: String,
+    /// 原视频中的帧序号，对已有文件夹去重时为 None
+    pub frame_index: Option<u64>,
+    /// 感知哈希
+    pub hash: String,
+    /// 与保留图片的汉明距离
+    pub distance: u32,
+    /// 是否已丢弃（拆分时未输出或已删除）
+    pub removed: bool,
+}
+
+/// 一组相似图片，保留第一张
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupCluster {
+    /// 保留的图片文件名
+    pub keep: String,
+    /// 保留图片在原视频中的帧序号
+    pub frame_index: Option<u64>,
+    /// 保留图片的感知哈希
+    pub hash: String,
+    /// 与其相似的重复图片
+    pub duplicates: Vec<DedupMember>,
+}
+
+/// 去重报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupReport {
+    /// 哈希算法
+    pub algorithm: HashAlgorithm,
+    /// 汉明距离阈值
+    pub max_distance: u32,
+    /// 重复图片的处理方式
+    pub action: DedupAction,
+    /// 检查的图片总数
+    pub total: usize,
+    /// 保留的不重复图片数
+    pub unique: usize,
+    /// 重复图片数
+    pub duplicates: usize,
+    /// 含有重复图片的分组
+    pub clusters: Vec<DedupCluster>,
+}
+
+impl DedupReport {
+    /// 写入文件夹
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::VideoProcessing(format!("序列化去重报告失败: {}", e)))?;
+        std::fs::write(dir.join(DEDUP_REPORT_FILE_NAME), data)?;
+        Ok(())
+    }
+}
+
+/// 单张图片的去重结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DedupDecision {
+    /// 不重复，已作为新的保留图片
+    Unique,
+    /// 与已保留的图片重复
+    Duplicate {
+        /// 保留图片的文件名
+        of: String,
+        /// 汉明距离
+        distance: u32,
+    },
+}
+
+/// 去重器，依次检查图片并记录分组
+pub struct Deduplicator {
+    /// 去重配置
+    config: DedupConfig,
+    /// 每组保留图片的哈希，与 `clusters` 一一对应
+    kept: Vec<u64>,
+    /// 所有分组，包括没有重复图片的
+    clusters: Vec<DedupCluster>,
+    /// 重复图片数
+    duplicates: usize,
+}
+
+impl Deduplicator {
+    /// 创建去重器
+    pub fn new(config: DedupConfig) -> Self {
+        Self {
+            config,
+            kept: Vec::new(),
+            clusters: Vec::new(),
+            duplicates: 0,
+        }
+    }
+
+    /// 检查一张图片，与距离最近的保留图片比较
+    ///
+    /// `file` 为图片文件名，拆分时丢弃的重复帧不会写出，但仍按将要使用的文件名记录。
+    pub fn check(&mut self, image: &DynamicImage, file: &str, frame_index: Option<u64>) -> DedupDecision {
+        let hash = image_hash(image, self.config.algorithm);
+
+        let nearest = self.kept.iter()
+            .enumerate()
+            .map(|(index, &kept)| (index, hamming_distance(hash, kept)))
+            .min_by_key(|&(_, distance)| distance)
+            .filter(|&(_, distance)| distance <= self.config.max_distance);
+
+        match nearest {
+            Some((index, distance)) => {
+                let cluster = &mut self.clusters[index];
+                cluster.duplicates.push(DedupMember {
+                    file: file.to_string(),
+                    frame_index,
+                    hash: hash_hex(hash),
+                    distance,
+                    removed: self.config.action == DedupAction::Drop,
+                });
+                self.duplicates += 1;
+
+                DedupDecision::Duplicate {
+                    of: cluster.keep.clone(),
+                    distance,
+                }
+            }
+            None => {
+                self.kept.push(hash);
+                self.clusters.push(DedupCluster {
+                    keep: file.to_string(),
+                    frame_index,
+                    hash: hash_hex(hash),
+                    duplicates: Vec::new(),
+                });
+                DedupDecision::Unique
+            }
+        }
+    }
+
+    /// 生成去重报告，只列出含有重复图片的分组
+    pub fn report(&self) -> DedupReport {
+        DedupReport {
+            algorithm: self.config.algorithm,
+            max_distance: self.config.max_distance,
+            action: self.config.action,
+            total: self.kept.len() + self.duplicates,
+            unique: self.kept.len(),
+            duplicates: self.duplicates,
+            clusters: self.clusters.iter()
+                .filter(|cluster| !cluster.duplicates.is_empty())
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// 对已有的帧文件夹去重，按文件名顺序检查，写出去重报告
+///
+/// 处理方式为 `drop` 时删除重复的图片。文件夹中有拆分清单时同步更新清单：
+/// 删除的图片从清单中移除，标记的图片记录与之相似的保留图片。
+pub fn dedup_dir(dir: &Path, config: &DedupConfig) -> Result<DedupReport> {
+    validate(config)?;
+
+    let mut manifest = SplitManifest::load(dir)?;
+
+    let mut files: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_frame_file(path))
+        .collect();
+    files.sort();
+
+    let mut deduplicator = Deduplicator::new(config.clone());
+    for path in &files {
+        let image = image::open(path)
+            .map_err(|e| Error::Image(format!("无法读取图片 {}: {}", path.display(), e)))?;
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let frame_index = manifest.as_ref()
+            .and_then(|m| m.frame(&name))
+            .map(|frame| frame.frame_index);
+
+        let of = match deduplicator.check(&image, &name, frame_index) {
+            DedupDecision::Duplicate { of, .. } => of,
+            DedupDecision::Unique => continue,
+        };
+
+        if config.action == DedupAction::Drop {
+            std::fs::remove_file(path)?;
+        }
+
+        if let Some(manifest) = &mut manifest {
+            match config.action {
+                DedupAction::Drop => manifest.frames.retain(|frame| frame.file != name),
+                DedupAction::Flag => {
+                    if let Some(frame) = manifest.frames.iter_mut().find(|frame| frame.file == name) {
+                        frame.duplicate_of = Some(of);
+                    }
+                }
+            }
+        }
+    }
+
+    let report = deduplicator.report();
+    report.write(dir)?;
+
+    if let Some(mut manifest) = manifest {
+        manifest.frames_duplicate = report.duplicates as u64;
+        if config.action == DedupAction::Drop {
+            manifest.frames_written = manifest.frames.len() as u64;
+        }
+        manifest.write(dir)?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,403 @@
+//! 视频编码后端模块
+//!
+//! `VideoRecorder` 通过 `Encoder` 接口写入帧，具体的编码和封装由后端实现。
+//! 后端按 (编码器, 容器) 组合注册到 `EncoderRegistry`，录制配置中的
+//! `encoder` 和 `container` 字段在这里解析。内置后端为纯 Rust 实现的
+//! MJPEG/AVI、原始 Y4M 以及调用系统 ffmpeg 的 H.264/H.265 后端，
+//! 其他后端（如 Rockchip MPP 硬件编码）可以通过 `EncoderRegistry::register` 接入。
+
+use crate::{Error, Result, config::{RateControl, RecordingConfig}};
+use crate::avi::AviWriter;
+use crate::ffmpeg::{FfmpegBackend, FFMPEG_PROGRAM};
+use crate::y4m::{Y4mHeader, Y4mWriter};
+use image::ImageEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+/// MJPEG/AVI 录制写入索引检查点的间隔（秒）
+const AVI_CHECKPOINT_SECS: u64 = 2;
+
+/// 视频流参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamParams {
+    /// 帧宽度
+    pub width: u32,
+    /// 帧高度
+    pub height: u32,
+    /// 帧率
+    pub fps: u32,
+}
+
+/// 编码器实际使用的参数
+///
+/// 由后端根据录制配置和自身默认值得出，写入录制元数据，
+/// 未设置的项表示该编码方式没有这一参数。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncoderSettings {
+    /// 编码后端名称
+    pub backend: String,
+    /// 编码器
+    pub codec: String,
+    /// 码率控制方式
+    pub rate_control: Option<RateControl>,
+    /// 比特率（bps）
+    pub bitrate: Option<u32>,
+    /// 最大比特率（bps）
+    pub max_bitrate: Option<u32>,
+    /// CRF 质量参数
+    pub crf: Option<u8>,
+    /// 关键帧间隔（帧）
+    pub keyframe_interval: Option<u32>,
+    /// 连续 B 帧的最大数量
+    pub b_frames: Option<u32>,
+    /// JPEG 质量
+    pub jpeg_quality: Option<u8>,
+}
+
+/// 视频编码器
+///
+/// 一个编码器实例对应一个输出文件，负责编码和封装。
+pub trait Encoder: Send {
+    /// 写入一帧图像
+    fn write_frame(&mut self, frame: &image::RgbImage) -> Result<()>;
+
+    /// 已写入帧数
+    fn frame_count(&self) -> u64;
+
+    /// 已写入字节数
+    fn bytes_written(&self) -> u64;
+
+    /// 是否已接近容器格式的大小上限，录制会在写入下一帧前切换到新文件
+    fn near_size_limit(&self) -> bool {
+        false
+    }
+
+    /// 完成编码并关闭输出文件
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// 编码后端
+pub trait EncoderBackend: Send + Sync {
+    /// 后端名称
+    fn name(&self) -> &'static str;
+
+    /// 支持的 (编码器, 容器) 组合
+    fn formats(&self) -> Vec<(&'static str, &'static str)>;
+
+    /// 后端在当前系统上是否可用
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// 校验录制配置中与该后端相关的参数
+    fn validate(&self, _config: &RecordingConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// 根据录制配置得出实际使用的编码参数，写入录制元数据
+    ///
+    /// 默认直接取录制配置中的值，后端有自己的默认值时应覆盖该方法。
+    fn settings(&self, config: &RecordingConfig, _stream: &StreamParams) -> EncoderSettings {
+        EncoderSettings {
+            backend: self.name().to_string(),
+            codec: config.encoder.clone(),
+            rate_control: config.rate_control,
+            bitrate: Some(config.bitrate),
+            max_bitrate: config.max_bitrate,
+            crf: config.crf,
+            keyframe_interval: config.keyframe_interval,
+            b_frames: config.b_frames,
+            jpeg_quality: None,
+        }
+    }
+
+    /// 创建写入指定文件的编码器
+    fn create(
+        &self,
+        path: &Path,
+        config: &RecordingConfig,
+        stream: &StreamParams,
+    ) -> Result<Box<dyn Encoder>>;
+
+    /// 是否支持指定的编码器和容器组合
+    fn supports(&self, encoder: &str, container: &str) -> bool {
+        self.formats().iter().any(|(e, c)| {
+            e.eq_ignore_ascii_case(encoder) && c.eq_ignore_ascii_case(container)
+        })
+    }
+}
+
+/// 编码后端注册表
+pub struct EncoderRegistry {
+    /// 已注册的后端，先注册的优先
+    backends: Vec<Arc<dyn EncoderBackend>>,
+}
+
+impl EncoderRegistry {
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        Self {
+            backends: Vec::new(),
+        }
+    }
+
+    /// 创建包含内置后端的注册表
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(MjpegAviBackend));
+        registry.register(Arc::new(Y4mBackend));
+        registry.register(Arc::new(FfmpegBackend::new(FFMPEG_PROGRAM)));
+        registry
+    }
+
+    /// 注册后端
+    pub fn register(&mut self, backend: Arc<dyn EncoderBackend>) {
+        self.backends.push(backend);
+    }
+
+    /// 列出当前可用的 (编码器, 容器) 组合
+    pub fn supported_formats(&self) -> Vec<(&'static str, &'static str)> {
+        self.backends.iter()
+            .filter(|b| b.is_available())
+            .flat_map(|b| b.formats())
+            .collect()
+    }
+
+    /// 查找支持指定组合且可用的后端
+    pub fn find(&self, encoder: &str, container: &str) -> Result<Arc<dyn EncoderBackend>> {
+        self.backends.iter()
+            .find(|b| b.supports(encoder, container) && b.is_available())
+            .cloned()
+            .ok_or_else(|| {
+                let supported: Vec<String> = self.supported_formats().iter()
+                    .map(|(e, c)| format!("{}/{}", e, c))
+                    .collect();
+                Error::Config(format!(
+                    "不支持的编码器和容器组合: {}/{}，可用组合: {}",
+                    encoder, container, supported.join(", ")
+                ))
+            })
+    }
+
+    /// 校验录制配置，返回对应的后端
+    pub fn validate(&self, config: &RecordingConfig) -> Result<Arc<dyn EncoderBackend>> {
+        let backend = self.find(&config.encoder, &config.container)?;
+        backend.validate(config)?;
+        Ok(backend)
+    }
+}
+
+impl Default for EncoderRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// MJPEG/AVI 后端
+pub struct MjpegAviBackend;
+
+impl EncoderBackend for MjpegAviBackend {
+    fn name(&self) -> &'static str {
+        "mjpeg-avi"
+    }
+
+    fn formats(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("mjpeg", "avi")]
+    }
+
+    /// MJPEG 每帧都是关键帧，只能通过 JPEG 质量控制码率
+    fn validate(&self, config: &RecordingConfig) -> Result<()> {
+        validate_jpeg_quality(config.jpeg_quality)?;
+
+        if matches!(config.rate_control, Some(RateControl::Cbr | RateControl::Vbr)) {
+            return Err(Error::Config(
+                "MJPEG/AVI 只支持固定质量 (crf)，请通过 jpeg_quality 调整码率".to_string()
+            ));
+        }
+
+        validate_intra_only(config)
+    }
+
+    fn settings(&self, config: &RecordingConfig, _stream: &StreamParams) -> EncoderSettings {
+        EncoderSettings {
+            backend: self.name().to_string(),
+            codec: "mjpeg".to_string(),
+            rate_control: Some(RateControl::Crf),
+            bitrate: None,
+            max_bitrate: None,
+            crf: None,
+            keyframe_interval: Some(1),
+            b_frames: Some(0),
+            jpeg_quality: Some(config.jpeg_quality),
+        }
+    }
+
+    fn create(
+        &self,
+        path: &Path,
+        config: &RecordingConfig,
+        stream: &StreamParams,
+    ) -> Result<Box<dyn Encoder>> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer = AviWriter::new(file, stream.width, stream.height, stream.fps)?;
+
+        Ok(Box::new(MjpegAviEncoder {
+            writer,
+            quality: config.jpeg_quality,
+            buffer: Vec::new(),
+            checkpoint_interval: stream.fps.max(1) as u64 * AVI_CHECKPOINT_SECS,
+        }))
+    }
+}
+
+/// MJPEG/AVI 编码器
+struct MjpegAviEncoder {
+    /// AVI 写入器
+    writer: AviWriter<BufWriter<File>>,
+    /// JPEG 质量
+    quality: u8,
+    /// JPEG 编码缓冲区，在帧之间复用
+    buffer: Vec<u8>,
+    /// 每隔多少帧写入一次索引检查点
+    checkpoint_interval: u64,
+}
+
+impl Encoder for MjpegAviEncoder {
+    fn write_frame(&mut self, frame: &image::RgbImage) -> Result<()> {
+        self.buffer.clear();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut self.buffer, self.quality)
+            .write_image(frame.as_raw(), frame.width(), frame.height(), image::ColorType::Rgb8)
+            .map_err(|e| Error::Image(format!("JPEG编码失败: {}", e)))?;
+
+        self.writer.write_frame(&self.buffer)?;
+
+        // 定期写入索引并同步到磁盘，断电时最多丢失一个间隔内的帧
+        if self.writer.frame_count().is_multiple_of(self.checkpoint_interval) {
+            self.writer.checkpoint()?;
+            self.writer.get_ref().get_ref().sync_data()?;
+        }
+
+        Ok(())
+    }
+
+    fn frame_count(&self) -> u64 {
+        self.writer.frame_count()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.writer.bytes_written()
+    }
+
+    fn near_size_limit(&self) -> bool {
+        self.writer.near_size_limit()
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+/// 原始 Y4M 后端
+pub struct Y4mBackend;
+
+impl EncoderBackend for Y4mBackend {
+    fn name(&self) -> &'static str {
+        "y4m"
+    }
+
+    fn formats(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("raw", "y4m"), ("rawvideo", "y4m")]
+    }
+
+    /// 原始视频不压缩，不接受码率控制参数，每帧都是关键帧
+    fn validate(&self, config: &RecordingConfig) -> Result<()> {
+        if config.rate_control.is_some() || config.crf.is_some() || config.max_bitrate.is_some() {
+            return Err(Error::Config(
+                "Y4M 为不压缩的原始视频，不支持 rate_control、crf 和 max_bitrate".to_string()
+            ));
+        }
+
+        validate_intra_only(config)
+    }
+
+    /// 原始视频不压缩，码率相关参数没有意义
+    fn settings(&self, _config: &RecordingConfig, _stream: &StreamParams) -> EncoderSettings {
+        EncoderSettings {
+            backend: self.name().to_string(),
+            codec: "rawvideo".to_string(),
+            rate_control: None,
+            bitrate: None,
+            max_bitrate: None,
+            crf: None,
+            keyframe_interval: Some(1),
+            b_frames: Some(0),
+            jpeg_quality: None,
+        }
+    }
+
+    fn create(
+        &self,
+        path: &Path,
+        _config: &RecordingConfig,
+        stream: &StreamParams,
+    ) -> Result<Box<dyn Encoder>> {
+        let file = BufWriter::new(File::create(path)?);
+        let header = Y4mHeader::new(stream.width, stream.height, stream.fps);
+
+        Ok(Box::new(Y4mEncoder {
+            writer: Y4mWriter::new(file, header)?,
+        }))
+    }
+}
+
+/// 原始 Y4M 编码器
+struct Y4mEncoder {
+    /// Y4M 写入器
+    writer: Y4mWriter<BufWriter<File>>,
+}
+
+impl Encoder for Y4mEncoder {
+    fn write_frame(&mut self, frame: &image::RgbImage) -> Result<()> {
+        self.writer.write_frame(frame)
+    }
+
+    fn frame_count(&self) -> u64 {
+        self.writer.frame_count()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.writer.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+/// 校验 JPEG 质量
+pub(crate) fn validate_jpeg_quality(quality: u8) -> Result<()> {
+    if !(1..=100).contains(&quality) {
+        return Err(Error::Config(format!("JPEG质量必须在 1-100 范围内: {}", quality)));
+    }
+    Ok(())
+}
+
+/// 校验只有关键帧的编码方式（如 MJPEG）的 GOP 参数
+pub(crate) fn validate_intra_only(config: &RecordingConfig) -> Result<()> {
+    if config.keyframe_interval.is_some_and(|interval| interval != 1) {
+        return Err(Error::Config(format!(
+            "{} 每帧都是关键帧，关键帧间隔只能为1", config.encoder
+        )));
+    }
+
+    if config.b_frames.is_some_and(|frames| frames > 0) {
+        return Err(Error::Config(format!("{} 不支持B帧", config.encoder)));
+    }
+
+    Ok(())
+}
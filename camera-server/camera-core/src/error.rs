@@ -26,6 +26,9 @@ pub enum Error {
     #[error("图像处理错误: {0}")]
     Image(String),
 
+    #[error("存储空间不足: {0}")]
+    StorageFull(String),
+
     #[error("其他错误: {0}")]
     Other(String),
 }
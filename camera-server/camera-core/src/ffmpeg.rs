@@ -0,0 +1,674 @@
+//! FFmpeg 子进程编码后端和帧源
+//!
+//! 不链接 libav，而是启动系统中的 `ffmpeg` 可执行文件，通过标准输入
+//! 传入 RGB24 原始帧，由 FFmpeg 完成 H.264/H.265 编码和封装。
+//! 子进程的 stderr 输出会转发到日志中，异常退出时以 `Error::FFmpeg` 报告。
+//! MP4/MOV 使用分片写入，录制中断电时已写入的分片仍然可以播放。
+//!
+//! 拆分和生成联系表时，这些容器同样通过子进程解码：先用 `ffprobe` 读取
+//! 画面尺寸、帧率和每个数据包的时间戳与关键帧标记，再由 `ffmpeg` 从标准输出
+//! 逐帧输出 RGB24 原始数据。
+//!
+//! 码率控制参数会显式传给 FFmpeg，未配置的项使用 x264/x265 的默认值，
+//! 保证录制元数据中记录的参数与实际编码一致。
+
+use crate::{Error, Result, config::{RateControl, RecordingConfig}};
+use crate::encoder::{validate_intra_only, validate_jpeg_quality, Encoder, EncoderBackend, EncoderSettings, StreamParams};
+use crate::source::{DecodedFrame, FrameSource, VideoFormat, VideoInfo};
+use log::{info, warn, error};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+
+/// 默认的 ffmpeg 可执行文件
+pub const FFMPEG_PROGRAM: &str = "ffmpeg";
+
+/// 默认的 ffprobe 可执行文件
+pub const FFPROBE_PROGRAM: &str = "ffprobe";
+
+/// 保留的 stderr 末尾行数，用于错误信息
+const STDERR_TAIL_LINES: usize = 20;
+
+/// x264/x265 默认的关键帧间隔
+const DEFAULT_KEYFRAME_INTERVAL: u32 = 250;
+
+/// 允许的最大连续 B 帧数
+const MAX_B_FRAMES: u32 = 16;
+
+/// FFmpeg 子进程后端
+pub struct FfmpegBackend {
+    /// ffmpeg 可执行文件路径
+    program: String,
+    /// 可用性检测结果
+    available: OnceLock<bool>,
+}
+
+impl FfmpegBackend {
+    /// 创建后端，`program` 为 ffmpeg 可执行文件路径或命令名
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            available: OnceLock::new(),
+        }
+    }
+
+    /// 将录制配置中的编码器名称映射为 FFmpeg 编码器
+    fn codec(encoder: &str) -> Option<&'static str> {
+        match encoder.to_ascii_lowercase().as_str() {
+            "h264" => Some("libx264"),
+            "h265" | "hevc" => Some("libx265"),
+            "mjpeg" => Some("mjpeg"),
+            _ => None,
+        }
+    }
+
+    /// 编码器默认的 CRF 和 B 帧数
+    fn codec_defaults(codec: &str) -> (u8, u32) {
+        match codec {
+            "libx265" => (28, 4),
+            _ => (23, 3),
+        }
+    }
+
+    /// MJPEG 的 JPEG 质量 (1-100) 换算为 FFmpeg 的 `-q:v` (2-31，越小质量越高)
+    fn mjpeg_qscale(quality: u8) -> u32 {
+        2 + (100 - quality.clamp(1, 100) as u32) * 29 / 99
+    }
+}
+
+impl EncoderBackend for FfmpegBackend {
+    fn name(&self) -> &'static str {
+        "ffmpeg"
+    }
+
+    fn formats(&self) -> Vec<(&'static str, &'static str)> {
+        let mut formats = Vec::new();
+        for encoder in ["h264", "h265", "hevc"] {
+            for container in ["mp4", "mkv", "mov", "ts"] {
+                formats.push((encoder, container));
+            }
+        }
+        formats.push(("mjpeg", "mkv"));
+        formats.push(("mjpeg", "mov"));
+        formats
+    }
+
+    fn is_available(&self) -> bool {
+        *self.available.get_or_init(|| {
+            let available = Command::new(&self.program)
+                .arg("-version")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false);
+
+            if !available {
+                warn!("未找到可用的ffmpeg程序: {}", self.program);
+            }
+            available
+        })
+    }
+
+    fn validate(&self, config: &RecordingConfig) -> Result<()> {
+        let codec = Self::codec(&config.encoder)
+            .ok_or_else(|| Error::Config(format!("不支持的编码器: {}", config.encoder)))?;
+        let rate_control = config.rate_control.unwrap_or(RateControl::Vbr);
+
+        if rate_control != RateControl::Crf && config.bitrate == 0 {
+            return Err(Error::Config("视频比特率不能为0".to_string()));
+        }
+
+        if let Some(max_bitrate) = config.max_bitrate {
+            if rate_control == RateControl::Cbr {
+                return Err(Error::Config("CBR 模式不能设置最大比特率，请只设置 bitrate".to_string()));
+            }
+            if max_bitrate == 0 || (rate_control == RateControl::Vbr && max_bitrate < config.bitrate) {
+                return Err(Error::Config(format!(
+                    "最大比特率 {} 不能小于平均比特率 {}", max_bitrate, config.bitrate
+                )));
+            }
+        }
+
+        if config.crf.is_some() && rate_control != RateControl::Crf {
+            return Err(Error::Config("只有 crf 码率控制方式可以设置 crf".to_string()));
+        }
+
+        if codec == "mjpeg" {
+            if config.crf.is_some() {
+                return Err(Error::Config("MJPEG 的固定质量请通过 jpeg_quality 设置".to_string()));
+            }
+            validate_jpeg_quality(config.jpeg_quality)?;
+            return validate_intra_only(config);
+        }
+
+        if let Some(crf) = config.crf {
+            if crf > 51 {
+                return Err(Error::Config(format!("CRF 必须在 0-51 范围内: {}", crf)));
+            }
+        }
+
+        if config.keyframe_interval == Some(0) {
+            return Err(Error::Config("关键帧间隔不能为0".to_string()));
+        }
+
+        if let Some(b_frames) = config.b_frames {
+            if b_frames > MAX_B_FRAMES {
+                return Err(Error::Config(format!("B帧数量不能超过 {}: {}", MAX_B_FRAMES, b_frames)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn settings(&self, config: &RecordingConfig, _stream: &StreamParams) -> EncoderSettings {
+        let codec = Self::codec(&config.encoder).unwrap_or("unknown");
+        let rate_control = config.rate_control.unwrap_or(RateControl::Vbr);
+
+        if codec == "mjpeg" {
+            let constant_quality = rate_control == RateControl::Crf;
+            return EncoderSettings {
+                backend: self.name().to_string(),
+                codec: codec.to_string(),
+                rate_control: Some(rate_control),
+                bitrate: (!constant_quality).then_some(config.bitrate),
+                max_bitrate: config.max_bitrate,
+                crf: None,
+                keyframe_interval: Some(1),
+                b_frames: Some(0),
+                jpeg_quality: constant_quality.then_some(config.jpeg_quality),
+            };
+        }
+
+        let (default_crf, default_b_frames) = Self::codec_defaults(codec);
+        EncoderSettings {
+            backend: self.name().to_string(),
+            codec: codec.to_string(),
+            rate_control: Some(rate_control),
+            bitrate: (rate_control != RateControl::Crf).then_some(config.bitrate),
+            max_bitrate: match rate_control {
+                RateControl::Cbr => Some(config.bitrate),
+                _ => config.max_bitrate,
+            },
+            crf: (rate_control == RateControl::Crf).then(|| config.crf.unwrap_or(default_crf)),
+            keyframe_interval: Some(config.keyframe_interval.unwrap_or(DEFAULT_KEYFRAME_INTERVAL)),
+            b_frames: Some(config.b_frames.unwrap_or(default_b_frames)),
+            jpeg_quality: None,
+        }
+    }
+
+    fn create(
+        &self,
+        path: &Path,
+        config: &RecordingConfig,
+        stream: &StreamParams,
+    ) -> Result<Box<dyn Encoder>> {
+        let codec = Self::codec(&config.encoder)
+            .ok_or_else(|| Error::Config(format!("不支持的编码器: {}", config.encoder)))?;
+        let settings = self.settings(config, stream);
+
+        let mut command = Command::new(&self.program);
+        command
+            .args(["-hide_banner", "-loglevel", "warning", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{}x{}", stream.width, stream.height)])
+            .args(["-framerate", &stream.fps.to_string()])
+            .args(["-i", "pipe:0"])
+            .args(["-c:v", codec]);
+
+        // 码率控制
+        match settings.rate_control {
+            Some(RateControl::Cbr) => {
+                let bitrate = config.bitrate.to_string();
+                command
+                    .args(["-b:v", &bitrate])
+                    .args(["-minrate", &bitrate])
+                    .args(["-maxrate", &bitrate])
+                    .args(["-bufsize", &bitrate]);
+            }
+            Some(RateControl::Crf) if codec == "mjpeg" => {
+                command.args(["-q:v", &Self::mjpeg_qscale(config.jpeg_quality).to_string()]);
+            }
+            Some(RateControl::Crf) => {
+                if let Some(crf) = settings.crf {
+                    command.args(["-crf", &crf.to_string()]);
+                }
+            }
+            _ => {
+                command.args(["-b:v", &config.bitrate.to_string()]);
+            }
+        }
+
+        if settings.rate_control != Some(RateControl::Cbr) {
+            if let Some(max_bitrate) = settings.max_bitrate {
+                // 缓冲区取两倍最大码率，允许短时波动
+                command
+                    .args(["-maxrate", &max_bitrate.to_string()])
+                    .args(["-bufsize", &(max_bitrate as u64 * 2).to_string()]);
+            }
+        }
+
+        if codec != "mjpeg" {
+            command.args(["-pix_fmt", "yuv420p"]);
+
+            // GOP 设置，固定关键帧间隔便于回放时拖动进度
+            if let Some(interval) = settings.keyframe_interval {
+                command.args(["-g", &interval.to_string()]);
+            }
+            if let Some(b_frames) = settings.b_frames {
+                command.args(["-bf", &b_frames.to_string()]);
+            }
+        }
+
+        // MP4/MOV 默认在结束时才写出 moov，改为分片写入，进程中断时文件仍可播放
+        if matches!(config.container.to_ascii_lowercase().as_str(), "mp4" | "mov") {
+            command.args(["-movflags", "+frag_keyframe+empty_moov+default_base_moof"]);
+        }
+
+        command
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        info!("启动ffmpeg编码进程: {:?}", command);
+
+        let mut child = command.spawn()
+            .map_err(|e| Error::FFmpeg(format!("启动ffmpeg失败: {}", e)))?;
+
+        let stdin = child.stdin.take()
+            .ok_or_else(|| Error::FFmpeg("无法获取ffmpeg标准输入".to_string()))?;
+
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_TAIL_LINES)));
+        let stderr_thread = child.stderr.take().map(|stderr| forward_stderr(stderr, stderr_tail.clone()));
+
+        Ok(Box::new(FfmpegEncoder {
+            child,
+            stdin: Some(stdin),
+            stderr_thread,
+            stderr_tail,
+            path: path.to_path_buf(),
+            stream: *stream,
+            frame_count: 0,
+            bytes_written: 0,
+        }))
+    }
+}
+
+/// 后台线程转发 stderr 到日志，并保留最后几行用于错误报告
+fn forward_stderr(stderr: ChildStderr, tail: Arc<Mutex<VecDeque<String>>>) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(|l| l.ok()) {
+            warn!("[ffmpeg] {}", line);
+            let mut tail = tail.lock().unwrap();
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    })
+}
+
+/// FFmpeg 子进程编码器
+struct FfmpegEncoder {
+    /// ffmpeg 子进程
+    child: Child,
+    /// 子进程标准输入，结束编码时关闭
+    stdin: Option<ChildStdin>,
+    /// stderr 转发线程
+    stderr_thread: Option<JoinHandle<()>>,
+    /// stderr 末尾若干行
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    /// 输出文件路径
+    path: PathBuf,
+    /// 视频流参数
+    stream: StreamParams,
+    /// 已写入帧数
+    frame_count: u64,
+    /// 输出文件大小，每秒视频刷新一次，避免每帧查询文件系统
+    bytes_written: u64,
+}
+
+impl FfmpegEncoder {
+    /// 拼接 stderr 末尾输出
+    fn stderr_summary(&self) -> String {
+        let tail = self.stderr_tail.lock().unwrap();
+        tail.iter().cloned().collect::<Vec<_>>().join("; ")
+    }
+
+    /// 等待 stderr 转发线程结束
+    fn join_stderr(&mut self) {
+        if let Some(thread) = self.stderr_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Encoder for FfmpegEncoder {
+    fn write_frame(&mut self, frame: &image::RgbImage) -> Result<()> {
+        if frame.width() != self.stream.width || frame.height() != self.stream.height {
+            return Err(Error::FFmpeg(format!(
+                "帧尺寸 {}x{} 与编码参数 {}x{} 不一致",
+                frame.width(), frame.height(), self.stream.width, self.stream.height
+            )));
+        }
+
+        let stdin = self.stdin.as_mut()
+            .ok_or_else(|| Error::FFmpeg("ffmpeg输入已关闭".to_string()))?;
+
+        if let Err(e) = stdin.write_all(frame.as_raw()) {
+            // 写入失败通常意味着子进程已经退出
+            let status = self.child.try_wait().ok().flatten();
+            self.stdin = None;
+            self.join_stderr();
+            error!("向ffmpeg写入帧失败: {}, 退出状态: {:?}", e, status);
+            return Err(Error::FFmpeg(format!(
+                "ffmpeg进程异常退出 ({:?}): {}", status, self.stderr_summary()
+            )));
+        }
+
+        self.frame_count += 1;
+        if self.frame_count.is_multiple_of(self.stream.fps.max(1) as u64) {
+            self.bytes_written = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(self.bytes_written);
+        }
+        Ok(())
+    }
+
+    fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        // 关闭标准输入，通知 ffmpeg 输入结束
+        self.stdin = None;
+
+        let status = self.child.wait()
+            .map_err(|e| Error::FFmpeg(format!("等待ffmpeg退出失败: {}", e)))?;
+        self.join_stderr();
+
+        if !status.success() {
+            return Err(Error::FFmpeg(format!(
+                "ffmpeg编码失败 ({}): {}", status, self.stderr_summary()
+            )));
+        }
+
+        info!("ffmpeg编码完成: {}, 共 {} 帧", self.path.display(), self.frame_count);
+        Ok(())
+    }
+}
+
+impl Drop for FfmpegEncoder {
+    fn drop(&mut self) {
+        // 未正常结束时确保子进程不会遗留
+        if let Ok(None) = self.child.try_wait() {
+            self.stdin = None;
+            if let Err(e) = self.child.kill() {
+                error!("结束ffmpeg进程失败: {}", e);
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// ffprobe 的 JSON 输出
+#[derive(Debug, Deserialize)]
+struct ProbeOutput {
+    /// 视频流，只选择了第一路
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    /// 视频流的数据包，按解码顺序排列
+    #[serde(default)]
+    packets: Vec<ProbePacket>,
+}
+
+/// ffprobe 输出的视频流信息
+#[derive(Debug, Deserialize)]
+struct ProbeStream {
+    /// 帧宽度
+    width: u32,
+    /// 帧高度
+    height: u32,
+    /// 平均帧率，如 "30/1"
+    #[serde(default)]
+    avg_frame_rate: String,
+    /// 基准帧率，平均帧率无效时使用
+    #[serde(default)]
+    r_frame_rate: String,
+}
+
+impl ProbeStream {
+    /// 帧率，平均帧率无效时使用基准帧率
+    fn fps(&self) -> f64 {
+        parse_rate(&self.avg_frame_rate)
+            .or_else(|| parse_rate(&self.r_frame_rate))
+            .unwrap_or(0.0)
+    }
+}
+
+/// 运行 ffprobe 读取第一路视频流的指定信息
+fn run_ffprobe(path: &Path, entries: &str) -> Result<ProbeOutput> {
+    let output = Command::new(FFPROBE_PROGRAM)
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", entries])
+        .args(["-of", "json"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| Error::FFmpeg(format!(
+            "启动ffprobe失败，解码 {} 需要安装 FFmpeg: {}", path.display(), e
+        )))?;
+
+    if !output.status.success() {
+        return Err(Error::FFmpeg(format!(
+            "ffprobe无法读取 {} ({}): {}",
+            path.display(), output.status, String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| Error::FFmpeg(format!("解析ffprobe输出失败: {}", e)))
+}
+
+/// 取出有效的视频流
+fn video_stream<'a>(probe: &'a ProbeOutput, path: &Path) -> Result<&'a ProbeStream> {
+    probe.streams.first()
+        .filter(|s| s.width > 0 && s.height > 0)
+        .ok_or_else(|| Error::VideoProcessing(format!("视频文件中没有视频流: {}", path.display())))
+}
+
+/// ffprobe 输出的数据包信息
+#[derive(Debug, Deserialize)]
+struct ProbePacket {
+    /// 显示时间戳（秒）
+    #[serde(default)]
+    pts_time: Option<String>,
+    /// 标记，关键帧包含 `K`
+    #[serde(default)]
+    flags: String,
+}
+
+/// 解析 "30000/1001" 形式的帧率
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/').unwrap_or((rate, "1"));
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+/// 通过 ffmpeg 子进程解码的帧源，用于 MP4、MKV、MOV、TS 等容器
+pub struct FfmpegSource {
+    /// ffmpeg 子进程
+    child: Child,
+    /// 子进程标准输出，逐帧输出 RGB24 数据
+    stdout: BufReader<ChildStdout>,
+    /// stderr 转发线程
+    stderr_thread: Option<JoinHandle<()>>,
+    /// stderr 末尾若干行
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    /// 视频信息
+    info: VideoInfo,
+    /// 按显示顺序排列的每帧时间戳（从0开始）和关键帧标记
+    frames: Vec<(f64, bool)>,
+    /// 下一帧序号
+    next_index: u64,
+    /// 是否已读到输出末尾
+    finished: bool,
+}
+
+impl FfmpegSource {
+    /// 只读取视频流信息，不扫描数据包也不启动解码进程
+    ///
+    /// 用于加入拆分队列前的校验。没有扫描数据包时无法确定总帧数和是否只有帧内编码，
+    /// 分别为 `None` 和 `false`，打开视频开始解码时才会得到准确的值。
+    pub fn probe(path: &Path) -> Result<VideoInfo> {
+        let probe = run_ffprobe(path, "stream=width,height,avg_frame_rate,r_frame_rate")?;
+        let stream = video_stream(&probe, path)?;
+
+        Ok(VideoInfo {
+            format: VideoFormat::Ffmpeg,
+            width: stream.width,
+            height: stream.height,
+            fps: stream.fps(),
+            frame_count: None,
+            intra_only: false,
+        })
+    }
+
+    /// 探测视频信息并启动解码进程
+    pub fn open(path: &Path) -> Result<Self> {
+        let probe = run_ffprobe(path, "stream=width,height,avg_frame_rate,r_frame_rate:packet=pts_time,flags")?;
+        let stream = video_stream(&probe, path)?;
+
+        // 数据包按解码顺序排列，按时间戳排序后与解码输出的帧一一对应
+        let mut frames: Vec<(f64, bool)> = probe.packets.iter()
+            .filter_map(|p| {
+                let pts = p.pts_time.as_deref()?.parse::<f64>().ok()?;
+                Some((pts, p.flags.contains('K')))
+            })
+            .collect();
+        frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(&(start, _)) = frames.first() {
+            for frame in &mut frames {
+                frame.0 -= start;
+            }
+        }
+
+        let info = VideoInfo {
+            format: VideoFormat::Ffmpeg,
+            width: stream.width,
+            height: stream.height,
+            fps: stream.fps(),
+            frame_count: (!frames.is_empty()).then_some(frames.len() as u64),
+            intra_only: !frames.is_empty() && frames.iter().all(|&(_, keyframe)| keyframe),
+        };
+
+        // 不自动旋转，保证输出尺寸与 ffprobe 报告的一致
+        let mut command = Command::new(FFMPEG_PROGRAM);
+        command
+            .args(["-hide_banner", "-loglevel", "error", "-nostdin", "-noautorotate"])
+            .arg("-i")
+            .arg(path)
+            .args(["-map", "0:v:0", "-an", "-vsync", "passthrough"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24", "pipe:1"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        info!("启动ffmpeg解码进程: {:?}", command);
+
+        let mut child = command.spawn()
+            .map_err(|e| Error::FFmpeg(format!("启动ffmpeg失败: {}", e)))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| Error::FFmpeg("无法获取ffmpeg标准输出".to_string()))?;
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_TAIL_LINES)));
+        let stderr_thread = child.stderr.take().map(|stderr| forward_stderr(stderr, stderr_tail.clone()));
+
+        Ok(Self {
+            child,
+            stdout: BufReader::new(stdout),
+            stderr_thread,
+            stderr_tail,
+            info,
+            frames,
+            next_index: 0,
+            finished: false,
+        })
+    }
+
+    /// 输出结束后等待进程退出，异常退出时报告 stderr 末尾输出
+    fn finish(&mut self) -> Result<()> {
+        self.finished = true;
+        let status = self.child.wait()
+            .map_err(|e| Error::FFmpeg(format!("等待ffmpeg退出失败: {}", e)))?;
+        if let Some(thread) = self.stderr_thread.take() {
+            let _ = thread.join();
+        }
+
+        if !status.success() {
+            let tail = self.stderr_tail.lock().unwrap();
+            return Err(Error::FFmpeg(format!(
+                "ffmpeg解码失败 ({}): {}", status, tail.iter().cloned().collect::<Vec<_>>().join("; ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl FrameSource for FfmpegSource {
+    fn info(&self) -> &VideoInfo {
+        &self.info
+    }
+
+    fn next_frame(&mut self) -> Result<Option<DecodedFrame>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut data = vec![0u8; self.info.width as usize * self.info.height as usize * 3];
+        if let Err(e) = self.stdout.read_exact(&mut data) {
+            // 输出结束，末尾不完整的帧丢弃
+            if e.kind() != std::io::ErrorKind::UnexpectedEof {
+                return Err(e.into());
+            }
+            self.finish()?;
+            return Ok(None);
+        }
+
+        let image = image::RgbImage::from_raw(self.info.width, self.info.height, data)
+            .ok_or_else(|| Error::FFmpeg("ffmpeg输出的帧大小不正确".to_string()))?;
+
+        let index = self.next_index;
+        self.next_index += 1;
+
+        let (pts, keyframe) = match self.frames.get(index as usize) {
+            Some(&frame) => frame,
+            None if self.info.fps > 0.0 => (index as f64 / self.info.fps, false),
+            None => (0.0, false),
+        };
+
+        Ok(Some(DecodedFrame {
+            index,
+            pts,
+            keyframe,
+            image,
+        }))
+    }
+}
+
+impl Drop for FfmpegSource {
+    fn drop(&mut self) {
+        // 未读完就放弃时结束解码进程
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! 存储空间保护模块
+//!
+//! 录制、拆分和打包都会持续写入磁盘，开发板上的 SD 卡写满后系统和数据库都可能损坏。
+//! 写入方在开始前和写入过程中通过 `StorageGuard` 检查剩余空间，
+//! 空间不足时返回 `Error::StorageFull`，由写入方结束当前文件并进入失败状态。
+//! 具体的检查策略由存储模块实现。
+
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 写入磁盘的任务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageJob {
+    /// 视频录制
+    Recording,
+    /// 视频拆分
+    Split,
+    /// 文件打包
+    Package,
+}
+
+impl StorageJob {
+    /// 任务名称，用于日志
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageJob::Recording => "recording",
+            StorageJob::Split => "split",
+            StorageJob::Package => "package",
+        }
+    }
+}
+
+/// 存储空间不足事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageEvent {
+    /// 被拒绝或中止的任务
+    pub job: StorageJob,
+    /// 写入路径
+    pub path: PathBuf,
+    /// 磁盘使用率 (0.0 - 1.0)
+    pub usage_ratio: f32,
+    /// 可用空间（字节）
+    pub available: u64,
+    /// 说明
+    pub message: String,
+    /// 发生时间（RFC 3339）
+    pub occurred_at: String,
+}
+
+/// 存储空间检查
+pub trait StorageGuard: Send + Sync {
+    /// 检查向 `path` 所在磁盘再写入 `required` 字节后是否仍在限制内
+    ///
+    /// 空间不足时返回 `Error::StorageFull`。`path` 可以是尚未创建的路径，
+    /// 实现应检查其最近的已存在上级目录。
+    fn check(&self, job: StorageJob, path: &Path, required: u64) -> Result<()>;
+}
@@ -5,6 +5,25 @@
 
 pub mod camera;
 pub mod video;
+pub mod y4m;
+pub mod avi;
+pub mod encoder;
+pub mod ffmpeg;
+pub mod source;
+pub mod split;
+pub mod scene;
+pub mod dedup;
+pub mod transform;
+pub mod sheet;
+pub mod recovery;
+pub mod metadata;
+pub mod manifest;
+pub mod preview;
+pub mod naming;
+pub mod trigger;
+pub mod snapshot;
+pub mod stack;
+pub mod guard;
 pub mod error;
 pub mod config;
 
@@ -0,0 +1,179 @@
+//! 拆分输出清单模块
+//!
+//! 每个拆分输出文件夹中写入一个 `manifest.json`，记录源视频、本次拆分使用的参数，
+//! 以及每张图片对应的原视频帧序号和显示时间戳，便于之后追溯图片的来源。
+//! 同时写出内容相同的 `manifest.csv`，方便直接导入表格或标注工具。
+//! 清单在拆分结束时写出，失败的拆分也会写出已输出的部分。
+
+use crate::{Error, Result};
+use crate::config::{DedupConfig, FrameTransform, SamplingMode, SplitConfig, TimeRange};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 清单文件名
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// CSV 格式的清单文件名
+pub const MANIFEST_CSV_FILE_NAME: &str = "manifest.csv";
+
+/// 拆分输出的图片扩展名
+const FRAME_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "tif", "tiff", "bmp"];
+
+/// 清单格式版本
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// 拆分参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitParams {
+    /// 图像格式
+    pub image_format: String,
+    /// 图像质量
+    pub quality: u8,
+    /// 抽帧方式
+    pub sampling: SamplingMode,
+    /// 每秒提取帧数，仅 `rate` 抽帧方式有值
+    #[serde(default)]
+    pub frame_rate: Option<f32>,
+    /// 拆分的时间段，为空表示整个视频
+    #[serde(default)]
+    pub time_ranges: Vec<TimeRange>,
+    /// 相似帧去重配置，未启用时为 None
+    #[serde(default)]
+    pub dedup: Option<DedupConfig>,
+    /// 输出前的裁剪、缩放和灰度转换，未设置时为 None
+    #[serde(default)]
+    pub transform: Option<FrameTransform>,
+}
+
+impl SplitParams {
+    /// 从拆分配置中提取参数
+    pub fn from_config(config: &SplitConfig) -> Self {
+        Self {
+            image_format: config.image_format.clone(),
+            quality: config.quality,
+            frame_rate: (config.sampling == SamplingMode::Rate).then_some(config.frame_rate),
+            sampling: config.sampling.clone(),
+            time_ranges: config.time_ranges.clone(),
+            dedup: config.dedup.enabled.then(|| config.dedup.clone()),
+            transform: (!config.transform.is_identity()).then(|| config.transform.clone()),
+        }
+    }
+}
+
+/// 清单中的一张图片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameEntry {
+    /// 文件名
+    pub file: String,
+    /// 原视频中的帧序号（从0开始）
+    pub frame_index: u64,
+    /// 原视频中的显示时间戳（秒）
+    pub pts: f64,
+    /// 是否为关键帧
+    pub keyframe: bool,
+    /// 去重标记为重复时，与之相似的保留图片文件名
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+}
+
+/// 拆分输出清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitManifest {
+    /// 清单格式版本
+    pub version: u32,
+    /// 拆分任务ID
+    pub task_id: String,
+    /// 源视频路径
+    pub video: PathBuf,
+    /// 源视频帧率
+    pub video_fps: f64,
+    /// 拆分参数
+    pub params: SplitParams,
+    /// 已解码帧数
+    pub frames_decoded: u64,
+    /// 输出图片数
+    pub frames_written: u64,
+    /// 去重时判定为重复的帧数
+    #[serde(default)]
+    pub frames_duplicate: u64,
+    /// 拆分是否完整结束
+    pub complete: bool,
+    /// 生成时间（RFC 3339）
+    pub created_at: String,
+    /// 输出的图片，按帧序号排列
+    #[serde(default)]
+    pub frames: Vec<FrameEntry>,
+}
+
+impl SplitManifest {
+    /// 清单文件路径
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(MANIFEST_FILE_NAME)
+    }
+
+    /// 读取文件夹中的清单，没有清单时返回 None
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = Self::path(dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = std::fs::read(&path)?;
+        let manifest = serde_json::from_slice(&data)
+            .map_err(|e| Error::VideoProcessing(format!("清单文件损坏 {}: {}", path.display(), e)))?;
+        Ok(Some(manifest))
+    }
+
+    /// 按文件名查找图片
+    pub fn frame(&self, file: &str) -> Option<&FrameEntry> {
+        self.frames.iter().find(|frame| frame.file == file)
+    }
+
+    /// 写入文件夹，同时写出 JSON 和 CSV 两种格式
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::VideoProcessing(format!("序列化清单失败: {}", e)))?;
+        std::fs::write(Self::path(dir), data)?;
+        std::fs::write(dir.join(MANIFEST_CSV_FILE_NAME), self.to_csv())?;
+        Ok(())
+    }
+
+    /// 生成 CSV 格式的清单，每张图片一行，拆分参数以 JSON 写在 `params` 列
+    pub fn to_csv(&self) -> String {
+        let params = serde_json::to_string(&self.params).unwrap_or_default();
+        let video = self.video.to_string_lossy();
+
+        let mut csv = String::from("file,video,frame_index,pts,keyframe,duplicate_of,params\n");
+        for frame in &self.frames {
+            let row = [
+                csv_field(&frame.file),
+                csv_field(&video),
+                frame.frame_index.to_string(),
+                format!("{:.6}", frame.pts),
+                frame.keyframe.to_string(),
+                csv_field(frame.duplicate_of.as_deref().unwrap_or("")),
+                csv_field(&params),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// 是否为拆分输出的图片文件
+pub fn is_frame_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| FRAME_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// 按 RFC 4180 转义 CSV 字段
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,101 @@
+//! 录制元数据模块
+//!
+//! 每个录制文件旁边都有一个同名的 JSON 附属文件（`<文件名>.json`），
+//! 记录录制时的摄像头和编码配置、起止时间、帧数、丢帧数等信息，
+//! 不再需要从 `video_%Y%m%d_%H%M%S` 这样的文件名中推断。
+
+use crate::{Error, Result, config::{CameraConfig, RecordingConfig}};
+use crate::encoder::EncoderSettings;
+use crate::preview::PreviewInfo;
+use crate::trigger::TriggerSource;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 附属元数据文件的后缀
+pub const METADATA_SUFFIX: &str = ".json";
+
+/// 录制元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingMetadata {
+    /// 写入该文件的软件版本
+    pub software_version: String,
+    /// 摄像头标识
+    pub camera_id: String,
+    /// 摄像头配置
+    pub camera: Option<CameraConfig>,
+    /// 录制配置
+    pub recording: RecordingConfig,
+    /// 编码器实际使用的参数，旧版本录制的文件没有该项
+    #[serde(default)]
+    pub encoding: Option<EncoderSettings>,
+    /// 录制文件名
+    pub file_name: String,
+    /// 分段序号（从1开始）
+    pub segment: u32,
+    /// 帧宽度
+    pub width: u32,
+    /// 帧高度
+    pub height: u32,
+    /// 开始时间（RFC 3339）
+    pub start_time: String,
+    /// 结束时间（RFC 3339），录制未正常结束时为 None
+    pub end_time: Option<String>,
+    /// 实际录制时长（秒）
+    pub duration_secs: f64,
+    /// 写入的帧数
+    pub frame_count: u64,
+    /// 根据帧间隔推算的丢帧数
+    pub dropped_frames: u64,
+    /// 实际帧率
+    pub effective_fps: f64,
+    /// 触发录制的事件来源，手动录制时为空
+    #[serde(default)]
+    pub triggers: Vec<TriggerSource>,
+    /// 封面图和缩略图条，录制结束后才有
+    #[serde(default)]
+    pub preview: Option<PreviewInfo>,
+}
+
+impl RecordingMetadata {
+    /// 当前软件版本
+    pub fn software_version() -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    /// 读取录制文件的附属元数据，不存在时返回 None
+    pub fn load_for(video: &Path) -> Result<Option<Self>> {
+        let path = sidecar_path(video);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = std::fs::read(&path)?;
+        let metadata = serde_json::from_slice(&data)
+            .map_err(|e| Error::Other(format!("解析元数据文件失败: {}: {}", path.display(), e)))?;
+        Ok(Some(metadata))
+    }
+
+    /// 写入录制文件的附属元数据
+    ///
+    /// 先写临时文件再重命名，避免断电时留下半个 JSON 文件。
+    pub fn save_for(&self, video: &Path) -> Result<()> {
+        let path = sidecar_path(video);
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Other(format!("序列化元数据失败: {}", e)))?;
+
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &path)?;
+
+        Ok(())
+    }
+}
+
+/// 录制文件对应的附属元数据文件路径
+pub fn sidecar_path(video: &Path) -> PathBuf {
+    let mut name = video.as_os_str().to_os_string();
+    name.push(METADATA_SUFFIX);
+    PathBuf::from(name)
+}
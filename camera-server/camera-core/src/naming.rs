@@ -0,0 +1,314 @@
+//! 文件命名模板模块
+//!
+//! 录制文件、拆分输出文件夹和帧图片的文件名都由模板生成，模板中可以使用以下占位符：
+//!
+//! | 占位符 | 含义 | 示例 |
+//! |--------|------|------|
+//! | `{camera}` | 摄像头标识 | `video0` |
+//! | `{date}` | 日期 | `20250513` |
+//! | `{time}` | 时间 | `120000` |
+//! | `{seq}` / `{seq:N}` | 分段序号，默认补齐到 3 位 | `001` |
+//! | `{trigger}` | 录制触发来源，手动录制为 `manual` | `motion` |
+//! | `{hostname}` | 主机名 | `camera-box` |
+//! | `{video}` | 原视频文件名（不含扩展名） | `video_20250513_120000` |
+//! | `{frame}` / `{frame:N}` | 帧序号，默认补齐到 6 位 | `000042` |
+//!
+//! 模板只描述文件名本身，不能包含路径分隔符；按日期分目录由配置中的
+//! `date_subdirs` 开关控制，生成 `YYYY/MM/DD` 形式的子目录。
+
+use crate::{Error, Result};
+use crate::trigger::TriggerSource;
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
+
+/// 默认的录制文件名模板，与早期版本的 `video_{时间戳}` 保持一致
+pub const DEFAULT_RECORDING_TEMPLATE: &str = "video_{date}_{time}";
+
+/// 默认的拆分输出文件夹模板
+pub const DEFAULT_FRAME_DIR_TEMPLATE: &str = "frames_{video}";
+
+/// 默认的帧图片文件名模板
+pub const DEFAULT_FRAME_TEMPLATE: &str = "{video}_{frame}";
+
+/// `{seq}` 的默认补齐位数
+const DEFAULT_SEQ_WIDTH: usize = 3;
+
+/// `{frame}` 的默认补齐位数
+const DEFAULT_FRAME_WIDTH: usize = 6;
+
+/// 补齐位数上限
+const MAX_WIDTH: usize = 12;
+
+/// 模板用途，决定可用的占位符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    /// 录制文件名
+    Recording,
+    /// 拆分输出文件夹名
+    FrameDir,
+    /// 帧图片文件名
+    Frame,
+}
+
+impl TemplateKind {
+    /// 该用途下允许使用的占位符
+    fn allows(self, placeholder: Placeholder) -> bool {
+        use Placeholder::*;
+        match self {
+            TemplateKind::Recording => matches!(
+                placeholder, Camera | Date | Time | Seq(_) | Trigger | Hostname
+            ),
+            TemplateKind::FrameDir => matches!(
+                placeholder, Date | Time | Hostname | Video
+            ),
+            TemplateKind::Frame => matches!(
+                placeholder, Date | Time | Hostname | Video | Frame(_)
+            ),
+        }
+    }
+
+    /// 用于错误信息的名称
+    fn name(self) -> &'static str {
+        match self {
+            TemplateKind::Recording => "录制文件名模板",
+            TemplateKind::FrameDir => "拆分文件夹模板",
+            TemplateKind::Frame => "帧文件名模板",
+        }
+    }
+}
+
+/// 模板占位符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Camera,
+    Date,
+    Time,
+    Seq(usize),
+    Trigger,
+    Hostname,
+    Video,
+    Frame(usize),
+}
+
+impl Placeholder {
+    /// 解析花括号内的占位符，如 `seq:4`
+    fn parse(text: &str) -> Option<Self> {
+        let (name, width) = match text.split_once(':') {
+            Some((name, width)) => {
+                let width = width.parse::<usize>().ok()
+                    .filter(|w| (1..=MAX_WIDTH).contains(w))?;
+                (name, Some(width))
+            }
+            None => (text, None),
+        };
+
+        let placeholder = match name {
+            "seq" => Placeholder::Seq(width.unwrap_or(DEFAULT_SEQ_WIDTH)),
+            "frame" => Placeholder::Frame(width.unwrap_or(DEFAULT_FRAME_WIDTH)),
+            _ if width.is_some() => return None,
+            "camera" => Placeholder::Camera,
+            "date" => Placeholder::Date,
+            "time" => Placeholder::Time,
+            "trigger" => Placeholder::Trigger,
+            "hostname" => Placeholder::Hostname,
+            "video" => Placeholder::Video,
+            _ => return None,
+        };
+
+        Some(placeholder)
+    }
+}
+
+/// 模板片段
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// 生成文件名所需的信息，未提供的字段对应的占位符渲染为 `unknown`
+#[derive(Debug, Clone)]
+pub struct NamingContext<'a> {
+    /// 摄像头标识
+    pub camera: Option<&'a str>,
+    /// 文件对应的时间，同时决定日期子目录
+    pub time: DateTime<Local>,
+    /// 分段序号
+    pub seq: Option<u32>,
+    /// 录制触发来源，`None` 表示手动录制
+    pub trigger: Option<TriggerSource>,
+    /// 原视频文件名（不含扩展名）
+    pub video: Option<&'a str>,
+    /// 帧序号
+    pub frame: Option<u64>,
+}
+
+impl<'a> NamingContext<'a> {
+    /// 以指定时间创建空的命名信息
+    pub fn new(time: DateTime<Local>) -> Self {
+        Self {
+            camera: None,
+            time,
+            seq: None,
+            trigger: None,
+            video: None,
+            frame: None,
+        }
+    }
+}
+
+/// 已校验的文件名模板
+#[derive(Debug, Clone)]
+pub struct FilenameTemplate {
+    /// 原始模板
+    template: String,
+    /// 解析后的片段
+    parts: Vec<Part>,
+}
+
+impl FilenameTemplate {
+    /// 解析并校验模板
+    pub fn parse(template: &str, kind: TemplateKind) -> Result<Self> {
+        let invalid = |reason: String| Error::Config(format!(
+            "{}无效 \"{}\": {}", kind.name(), template, reason
+        ));
+
+        if template.trim().is_empty() {
+            return Err(invalid("模板不能为空".to_string()));
+        }
+
+        if template.contains(['/', '\\']) || template == "." || template == ".." {
+            return Err(invalid("模板不能包含路径，按日期分目录请使用 date_subdirs".to_string()));
+        }
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+
+        while let Some(pos) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..pos]);
+            if rest[pos..].starts_with('}') {
+                return Err(invalid("多余的 '}'".to_string()));
+            }
+
+            let end = rest[pos..].find('}')
+                .ok_or_else(|| invalid("缺少 '}'".to_string()))?;
+            let name = &rest[pos + 1..pos + end];
+            let placeholder = Placeholder::parse(name)
+                .ok_or_else(|| invalid(format!("未知的占位符 {{{}}}", name)))?;
+            if !kind.allows(placeholder) {
+                return Err(invalid(format!("不支持占位符 {{{}}}", name)));
+            }
+
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Placeholder(placeholder));
+            rest = &rest[pos + end + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if kind == TemplateKind::Frame && !parts.iter().any(|p| matches!(p, Part::Placeholder(Placeholder::Frame(_)))) {
+            return Err(invalid("帧文件名必须包含 {frame}，否则会相互覆盖".to_string()));
+        }
+
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// 原始模板
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// 模板是否包含分段序号
+    pub fn has_seq(&self) -> bool {
+        self.parts.iter().any(|p| matches!(p, Part::Placeholder(Placeholder::Seq(_))))
+    }
+
+    /// 根据命名信息生成文件名（不含扩展名）
+    pub fn render(&self, context: &NamingContext) -> String {
+        let mut name = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Placeholder(placeholder) => {
+                    let value = match placeholder {
+                        Placeholder::Camera => context.camera.unwrap_or("unknown").to_string(),
+                        Placeholder::Date => context.time.format("%Y%m%d").to_string(),
+                        Placeholder::Time => context.time.format("%H%M%S").to_string(),
+                        Placeholder::Seq(width) => format!("{:0width$}", context.seq.unwrap_or(1), width = *width),
+                        Placeholder::Trigger => context.trigger
+                            .map(|t| t.as_str())
+                            .unwrap_or("manual")
+                            .to_string(),
+                        Placeholder::Hostname => hostname(),
+                        Placeholder::Video => context.video.unwrap_or("unknown").to_string(),
+                        Placeholder::Frame(width) => format!("{:0width$}", context.frame.unwrap_or(0), width = *width),
+                    };
+                    name.push_str(&sanitize(&value));
+                }
+            }
+        }
+
+        name
+    }
+}
+
+/// 按日期生成 `YYYY/MM/DD` 子目录，未启用时直接返回根目录
+pub fn dated_dir(root: &Path, time: &DateTime<Local>, date_subdirs: bool) -> PathBuf {
+    if date_subdirs {
+        root.join(time.format("%Y").to_string())
+            .join(time.format("%m").to_string())
+            .join(time.format("%d").to_string())
+    } else {
+        root.to_path_buf()
+    }
+}
+
+/// 若文件已存在，在文件名后追加 `_1`、`_2`… 直到不冲突
+///
+/// 模板不含时间或序号时，同一秒内的多次录制会生成相同的文件名，
+/// 这里保证不会覆盖已有文件。
+pub fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file").to_string();
+    let extension = path.extension().and_then(|s| s.to_str()).map(|s| s.to_string());
+
+    (1..)
+        .map(|n| {
+            let name = match &extension {
+                Some(ext) => format!("{}_{}.{}", stem, n, ext),
+                None => format!("{}_{}", stem, n),
+            };
+            path.with_file_name(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("可用文件名")
+}
+
+/// 本机主机名，读取失败时为 `localhost`
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// 替换占位符值中不能出现在文件名里的字符
+fn sanitize(value: &str) -> String {
+    value.chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect()
+}
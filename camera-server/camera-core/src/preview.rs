@@ -0,0 +1,184 @@
+//! 录制预览图模块
+//!
+//! 录制过程中从写入的画面里挑选封面帧和缩略图，分段结束时在视频旁边写出：
+//!
+//! - `<文件名>.poster.jpg`：封面图，取录制开始后指定时间的一帧，原始分辨率
+//! - `<文件名>.thumbs.jpg`：缩略图条，按固定间隔取帧缩小后横向拼接
+//!
+//! 画面在写入时直接采集，不需要事后解码视频，所有编码后端都适用。
+//! 缩略图数量超过上限时丢弃一半并加倍间隔，长时间录制的缩略图条宽度不会无限增长。
+
+use crate::{Error, Result, config::PreviewConfig};
+use image::{ImageEncoder, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 封面图文件后缀
+pub const POSTER_SUFFIX: &str = ".poster.jpg";
+
+/// 缩略图条文件后缀
+pub const THUMBNAILS_SUFFIX: &str = ".thumbs.jpg";
+
+/// 封面图的 JPEG 质量
+const POSTER_QUALITY: u8 = 85;
+
+/// 缩略图条的 JPEG 质量
+const THUMBNAIL_QUALITY: u8 = 75;
+
+/// 写入录制元数据的预览图信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewInfo {
+    /// 封面图文件名
+    pub poster: Option<String>,
+    /// 缩略图条文件名
+    pub thumbnails: Option<String>,
+    /// 缩略图数量
+    pub thumbnail_count: usize,
+    /// 相邻缩略图之间的视频时长（秒）
+    pub thumbnail_interval_secs: f64,
+    /// 单个缩略图宽度
+    pub thumbnail_width: u32,
+    /// 单个缩略图高度
+    pub thumbnail_height: u32,
+}
+
+/// 封面图路径
+pub fn poster_path(video: &Path) -> PathBuf {
+    with_suffix(video, POSTER_SUFFIX)
+}
+
+/// 缩略图条路径
+pub fn thumbnails_path(video: &Path) -> PathBuf {
+    with_suffix(video, THUMBNAILS_SUFFIX)
+}
+
+fn with_suffix(video: &Path, suffix: &str) -> PathBuf {
+    let mut name = video.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 校验预览图配置
+pub fn validate(config: &PreviewConfig) -> Result<()> {
+    if !config.poster_offset_secs.is_finite() || config.poster_offset_secs < 0.0 {
+        return Err(Error::Config(format!("封面图时间必须为非负数: {}", config.poster_offset_secs)));
+    }
+
+    if config.thumbnail_interval_secs == 0 {
+        return Err(Error::Config("缩略图间隔不能为0".to_string()));
+    }
+
+    if !(16..=640).contains(&config.thumbnail_width) {
+        return Err(Error::Config(format!("缩略图宽度必须在 16-640 范围内: {}", config.thumbnail_width)));
+    }
+
+    if config.max_thumbnails < 2 {
+        return Err(Error::Config(format!("缩略图数量上限不能小于2: {}", config.max_thumbnails)));
+    }
+
+    Ok(())
+}
+
+/// 预览图采集器，每个录制分段一个
+pub struct PreviewCollector {
+    /// 配置
+    config: PreviewConfig,
+    /// 帧率，用于将帧序号换算为视频时间
+    fps: u32,
+    /// 已收到的帧数
+    frames: u64,
+    /// 封面图 JPEG 数据
+    poster: Option<Vec<u8>>,
+    /// 已采集的缩略图
+    thumbnails: Vec<RgbImage>,
+    /// 当前缩略图间隔（帧）
+    interval_frames: u64,
+}
+
+impl PreviewCollector {
+    /// 创建采集器
+    pub fn new(config: PreviewConfig, fps: u32) -> Self {
+        let fps = fps.max(1);
+        Self {
+            interval_frames: config.thumbnail_interval_secs as u64 * fps as u64,
+            config,
+            fps,
+            frames: 0,
+            poster: None,
+            thumbnails: Vec::new(),
+        }
+    }
+
+    /// 处理一帧写入的画面
+    pub fn push(&mut self, frame: &RgbImage) -> Result<()> {
+        let index = self.frames;
+        self.frames += 1;
+
+        // 到达封面时间之前先用第一帧，保证短录制也有封面
+        let poster_frame = (self.config.poster_offset_secs * self.fps as f32) as u64;
+        if self.poster.is_none() || index == poster_frame {
+            self.poster = Some(encode_jpeg(frame, POSTER_QUALITY)?);
+        }
+
+        if index.is_multiple_of(self.interval_frames) {
+            let width = self.config.thumbnail_width.min(frame.width());
+            let height = (frame.height() as u64 * width as u64 / frame.width().max(1) as u64).max(1) as u32;
+            self.thumbnails.push(image::imageops::thumbnail(frame, width, height));
+
+            // 超过上限时隔一个丢一个，间隔加倍，已保留的缩略图仍然等间隔
+            if self.thumbnails.len() > self.config.max_thumbnails {
+                let kept = std::mem::take(&mut self.thumbnails)
+                    .into_iter()
+                    .step_by(2)
+                    .collect();
+                self.thumbnails = kept;
+                self.interval_frames *= 2;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 在视频旁边写出封面图和缩略图条，没有收到画面时不写出
+    pub fn write(&self, video: &Path) -> Result<Option<PreviewInfo>> {
+        let poster = match &self.poster {
+            Some(poster) => poster,
+            None => return Ok(None),
+        };
+
+        let poster_path = poster_path(video);
+        std::fs::write(&poster_path, poster)?;
+
+        let (width, height) = self.thumbnails.first()
+            .map(|t| t.dimensions())
+            .unwrap_or((0, 0));
+        let thumbnails_path = thumbnails_path(video);
+        if !self.thumbnails.is_empty() {
+            let mut strip = RgbImage::new(width * self.thumbnails.len() as u32, height);
+            for (i, thumbnail) in self.thumbnails.iter().enumerate() {
+                image::imageops::replace(&mut strip, thumbnail, i as i64 * width as i64, 0);
+            }
+            std::fs::write(&thumbnails_path, encode_jpeg(&strip, THUMBNAIL_QUALITY)?)?;
+        }
+
+        let file_name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string());
+
+        Ok(Some(PreviewInfo {
+            poster: file_name(&poster_path),
+            thumbnails: (!self.thumbnails.is_empty()).then(|| file_name(&thumbnails_path)).flatten(),
+            thumbnail_count: self.thumbnails.len(),
+            thumbnail_interval_secs: self.interval_frames as f64 / self.fps as f64,
+            thumbnail_width: width,
+            thumbnail_height: height,
+        }))
+    }
+}
+
+/// 将画面编码为 JPEG
+fn encode_jpeg(frame: &RgbImage, quality: u8) -> Result<Vec<u8>> {
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .write_image(frame.as_raw(), frame.width(), frame.height(), image::ColorType::Rgb8)
+        .map_err(|e| Error::Image(format!("预览图编码失败: {}", e)))?;
+    Ok(jpeg)
+}
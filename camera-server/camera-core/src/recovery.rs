@@ -0,0 +1,51 @@
+//! 录制文件恢复模块
+//!
+//! 录制过程中断电或进程崩溃时，文件末尾可能残留写了一半的数据，
+//! AVI 的索引和文件头也没有回填。启动时对录制目录中的文件调用
+//! `recover_video`，按格式修复后文件即可正常播放和拆分。
+
+use crate::Result;
+use crate::avi::repair_avi;
+use crate::y4m::repair_y4m;
+use log::{info, warn};
+use std::path::Path;
+
+/// 恢复结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    /// 文件完整，无需修复
+    Intact,
+    /// 已修复，包含修复后保留的帧数
+    Repaired {
+        /// 保留的帧数
+        frames: u64,
+    },
+    /// 该格式不需要或不支持修复（如分片 MP4、MKV）
+    Unsupported,
+}
+
+/// 检查并修复录制文件
+pub fn recover_video(path: &Path) -> Result<RecoveryOutcome> {
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let repaired = match extension.as_str() {
+        "avi" => repair_avi(path)?,
+        "y4m" => repair_y4m(path)?,
+        _ => return Ok(RecoveryOutcome::Unsupported),
+    };
+
+    Ok(match repaired {
+        Some(frames) => {
+            if frames == 0 {
+                warn!("录制文件中没有完整的帧: {}", path.display());
+            } else {
+                info!("已修复录制文件: {}, 保留 {} 帧", path.display(), frames);
+            }
+            RecoveryOutcome::Repaired { frames }
+        }
+        None => RecoveryOutcome::Intact,
+    })
+}
@@ -0,0 +1,92 @@
+//! 场景变化检测模块
+//!
+//! 拆分时用于从长时间静止的录像中挑选画面不同的帧。每帧缩小为亮度图后
+//! 与上一张输出的帧比较，差异超过阈值视为新场景。与上一张输出而不是上一帧比较，
+//! 缓慢的变化累积到一定程度后同样会被输出。
+
+use crate::config::SceneMetric;
+use image::{GrayImage, RgbImage};
+
+/// 比较前将画面缩小到的宽度
+const SCENE_WIDTH: u32 = 64;
+
+/// 直方图的分组数
+const HISTOGRAM_BINS: usize = 64;
+
+/// 基于画面差异的场景变化检测器
+pub struct SceneDetector {
+    /// 差异计算方式
+    metric: SceneMetric,
+    /// 差异阈值 (0.0 - 1.0)
+    threshold: f32,
+    /// 上一张输出的帧缩小后的亮度图
+    reference: Option<GrayImage>,
+}
+
+impl SceneDetector {
+    /// 创建场景变化检测器
+    pub fn new(metric: SceneMetric, threshold: f32) -> Self {
+        Self {
+            metric,
+            threshold,
+            reference: None,
+        }
+    }
+
+    /// 与上一张输出的帧比较，变化超过阈值时记为新的参考帧并返回 true，第一帧总是返回 true
+    pub fn detect(&mut self, frame: &RgbImage) -> bool {
+        let luma = downscale(frame);
+
+        let changed = match &self.reference {
+            Some(reference) if reference.dimensions() == luma.dimensions() => {
+                self.difference(reference, &luma) >= self.threshold
+            }
+            _ => true,
+        };
+
+        if changed {
+            self.reference = Some(luma);
+        }
+        changed
+    }
+
+    /// 两张亮度图的差异 (0.0 - 1.0)
+    fn difference(&self, a: &GrayImage, b: &GrayImage) -> f32 {
+        match self.metric {
+            SceneMetric::Histogram => {
+                let (ha, hb) = (histogram(a), histogram(b));
+                // 归一化直方图的 L1 距离最大为 2
+                ha.iter().zip(&hb).map(|(x, y)| (x - y).abs()).sum::<f32>() / 2.0
+            }
+            SceneMetric::Pixel => {
+                let total: u64 = a.as_raw().iter()
+                    .zip(b.as_raw())
+                    .map(|(x, y)| x.abs_diff(*y) as u64)
+                    .sum();
+                total as f32 / (a.as_raw().len().max(1) as f32 * 255.0)
+            }
+        }
+    }
+}
+
+/// 缩小并转换为亮度图
+fn downscale(frame: &RgbImage) -> GrayImage {
+    let width = SCENE_WIDTH.min(frame.width()).max(1);
+    let height = (frame.height() as u64 * width as u64 / frame.width().max(1) as u64).max(1) as u32;
+    let small = image::imageops::thumbnail(frame, width, height);
+    image::DynamicImage::ImageRgb8(small).to_luma8()
+}
+
+/// 归一化的亮度直方图
+fn histogram(image: &GrayImage) -> [f32; HISTOGRAM_BINS] {
+    let mut bins = [0f32; HISTOGRAM_BINS];
+    for &value in image.as_raw() {
+        bins[value as usize * HISTOGRAM_BINS / 256] += 1.0;
+    }
+
+    let total = image.as_raw().len().max(1) as f32;
+    for bin in &mut bins {
+        *bin /= total;
+    }
+    bins
+}
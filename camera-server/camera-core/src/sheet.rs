@@ -0,0 +1,471 @@
+//! 联系表和雪碧图模块
+//!
+//! 从录像或帧文件夹中挑选画面，缩小后排成网格：
+//!
+//! - `<名称>.contact.jpg`：联系表，缩略图之间留有间距并标注时间，用于快速浏览长时间录像
+//! - `<名称>.sprite.jpg`：雪碧图，缩略图紧密排列，同时写出 `<名称>.sprite.vtt` 和
+//!   `<名称>.sprite.json` 索引，网页客户端拖动进度条时据此显示对应的画面
+//!
+//! 录像的输出写在视频旁边，`<名称>` 为视频文件名；帧文件夹的输出写在文件夹旁边，
+//! `<名称>` 为文件夹名。录像按固定间隔取帧，帧文件夹按顺序均匀挑选图片，
+//! 有拆分清单时按清单中的帧序号排序并使用其中的时间戳。
+
+use crate::{Error, Result};
+use crate::config::{SheetConfig, SheetKind};
+use crate::manifest::{is_frame_file, SplitManifest};
+use crate::source::open_video;
+use crate::snapshot::{encode_image, SnapshotFormat, SnapshotOptions};
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 网格图尺寸上限（像素）
+const MAX_SHEET_SIZE: u32 = 16384;
+
+/// 无法确定视频时长且未设置间隔时使用的取帧间隔（秒）
+const DEFAULT_INTERVAL_SECS: f64 = 10.0;
+
+/// 雪碧图 WebVTT 索引文件后缀
+pub const SPRITE_VTT_SUFFIX: &str = ".sprite.vtt";
+
+/// 雪碧图 JSON 索引文件后缀
+pub const SPRITE_INDEX_SUFFIX: &str = ".sprite.json";
+
+/// 网格图中的一个缩略图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetTile {
+    /// 在网格图中的横坐标
+    pub x: u32,
+    /// 在网格图中的纵坐标
+    pub y: u32,
+    /// 宽度
+    pub width: u32,
+    /// 高度
+    pub height: u32,
+    /// 画面在视频中的时间（秒），帧文件夹没有清单时为 None
+    pub time: Option<f64>,
+    /// 原视频中的帧序号
+    pub frame_index: Option<u64>,
+    /// 来源图片文件名，仅帧文件夹有值
+    pub file: Option<String>,
+}
+
+/// 网格图索引
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetIndex {
+    /// 网格图类型
+    pub kind: SheetKind,
+    /// 网格图文件名，与索引文件在同一目录
+    pub image: String,
+    /// 来源录像或帧文件夹
+    pub source: PathBuf,
+    /// 列数
+    pub columns: u32,
+    /// 行数
+    pub rows: u32,
+    /// 单个缩略图宽度
+    pub tile_width: u32,
+    /// 单个缩略图高度
+    pub tile_height: u32,
+    /// 视频时长（秒），无法确定时为 None
+    pub duration_secs: Option<f64>,
+    /// 生成时间（RFC 3339）
+    pub created_at: String,
+    /// 缩略图，按时间顺序排列
+    pub tiles: Vec<SheetTile>,
+}
+
+impl SheetIndex {
+    /// 生成 WebVTT 索引，每个缩略图一条，显示到下一个缩略图的时间为止
+    ///
+    /// 缩略图没有时间时按每张 1 秒排列。
+    pub fn to_webvtt(&self) -> String {
+        let times: Vec<f64> = self.tiles.iter()
+            .enumerate()
+            .map(|(i, tile)| tile.time.unwrap_or(i as f64))
+            .collect();
+
+        // 最后一个缩略图显示到视频结束，时长未知时按平均间隔
+        let step = match times.len() {
+            0 | 1 => 1.0,
+            n => ((times[n - 1] - times[0]) / (n - 1) as f64).max(0.001),
+        };
+
+        let mut vtt = String::from("WEBVTT\n");
+        for (i, tile) in self.tiles.iter().enumerate() {
+            let start = times[i];
+            let end = times.get(i + 1).copied()
+                .or(self.duration_secs.filter(|&d| d > start))
+                .unwrap_or(start + step);
+
+            vtt.push_str(&format!(
+                "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+                vtt_time(start), vtt_time(end), self.image, tile.x, tile.y, tile.width, tile.height
+            ));
+        }
+        vtt
+    }
+}
+
+/// 挑选出的画面
+struct Picked {
+    /// 缩小后的画面
+    image: RgbImage,
+    /// 画面在视频中的时间（秒）
+    time: Option<f64>,
+    /// 原视频中的帧序号
+    frame_index: Option<u64>,
+    /// 来源图片文件名
+    file: Option<String>,
+}
+
+/// 网格图路径，`base` 为视频文件或帧文件夹
+pub fn sheet_path(base: &Path, kind: SheetKind, format: SnapshotFormat) -> PathBuf {
+    with_suffix(base, &format!(".{}.{}", kind.as_str(), format.extension()))
+}
+
+/// 雪碧图 WebVTT 索引路径
+pub fn sprite_vtt_path(base: &Path) -> PathBuf {
+    with_suffix(base, SPRITE_VTT_SUFFIX)
+}
+
+/// 雪碧图 JSON 索引路径
+pub fn sprite_index_path(base: &Path) -> PathBuf {
+    with_suffix(base, SPRITE_INDEX_SUFFIX)
+}
+
+/// 视频文件或帧文件夹所有可能的网格图输出文件，与其一起删除和重命名
+pub fn sheet_files(base: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for kind in [SheetKind::Contact, SheetKind::Sprite] {
+        for format in SnapshotFormat::IMAGE_FORMATS {
+            files.push(sheet_path(base, kind, format));
+        }
+    }
+    files.push(sprite_vtt_path(base));
+    files.push(sprite_index_path(base));
+    files
+}
+
+fn with_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 校验网格图配置
+pub fn validate(config: &SheetConfig) -> Result<()> {
+    if !(1..=50).contains(&config.columns) {
+        return Err(Error::Config(format!("网格列数必须在 1-50 范围内: {}", config.columns)));
+    }
+
+    if !(16..=640).contains(&config.tile_width) {
+        return Err(Error::Config(format!("缩略图宽度必须在 16-640 范围内: {}", config.tile_width)));
+    }
+
+    if !(1..=1000).contains(&config.max_tiles) {
+        return Err(Error::Config(format!("缩略图数量上限必须在 1-1000 范围内: {}", config.max_tiles)));
+    }
+
+    if let Some(interval) = config.interval_secs {
+        if !interval.is_finite() || interval <= 0.0 {
+            return Err(Error::Config(format!("缩略图间隔必须为正数: {}", interval)));
+        }
+    }
+
+    if config.spacing > 64 {
+        return Err(Error::Config(format!("缩略图间距不能超过 64: {}", config.spacing)));
+    }
+
+    if config.columns * (config.tile_width + config.spacing) + config.spacing > MAX_SHEET_SIZE {
+        return Err(Error::Config(format!(
+            "网格图宽度超过 {} 像素，请减少列数或缩略图宽度", MAX_SHEET_SIZE
+        )));
+    }
+
+    if config.quality == 0 || config.quality > 100 {
+        return Err(Error::Config(format!("图像质量必须在 1-100 范围内: {}", config.quality)));
+    }
+
+    SnapshotFormat::image_from_name(&config.image_format)?;
+    Ok(())
+}
+
+/// 为录像或帧文件夹生成网格图，根据路径是否为目录选择
+pub fn build(source: &Path, config: &SheetConfig) -> Result<SheetIndex> {
+    if source.is_dir() {
+        build_for_dir(source, config)
+    } else {
+        build_for_video(source, config)
+    }
+}
+
+/// 解码录像，按间隔取帧生成网格图，写在视频旁边
+///
+/// 未设置间隔时按视频时长和数量上限均匀分布，无法确定时长时每 10 秒取一帧。
+pub fn build_for_video(video: &Path, config: &SheetConfig) -> Result<SheetIndex> {
+    validate(config)?;
+
+    let mut source = open_video(video)?;
+    let info = source.info().clone();
+    let duration = info.frame_count
+        .filter(|_| info.fps > 0.0)
+        .map(|count| count as f64 / info.fps);
+    let interval = config.interval_secs
+        .or(duration.map(|d| d / config.max_tiles as f64))
+        .filter(|&interval| interval > 0.0)
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    let (tile_width, tile_height) = tile_size(config.tile_width, info.width, info.height);
+    // 帧时间戳允许半帧误差，避免浮点误差跳过本应取到的帧
+    let tolerance = if info.fps > 0.0 { 0.5 / info.fps } else { 0.0 };
+
+    let mut picked = Vec::new();
+    let mut next_time = 0.0;
+    while picked.len() < config.max_tiles {
+        let frame = match source.next_frame()? {
+            Some(frame) => frame,
+            None => break,
+        };
+
+        let pts = frame.pts + tolerance;
+        if pts < next_time {
+            continue;
+        }
+        while next_time <= pts {
+            next_time += interval;
+        }
+
+        picked.push(Picked {
+            image: image::imageops::thumbnail(&frame.image, tile_width, tile_height),
+            time: Some(frame.pts),
+            frame_index: Some(frame.index),
+            file: None,
+        });
+    }
+
+    if picked.is_empty() {
+        return Err(Error::VideoProcessing(format!("视频中没有可用的画面: {}", video.display())));
+    }
+
+    write_sheet(video, picked, duration, config)
+}
+
+/// 从帧文件夹中按顺序均匀挑选图片生成网格图，写在文件夹旁边
+pub fn build_for_dir(dir: &Path, config: &SheetConfig) -> Result<SheetIndex> {
+    validate(config)?;
+
+    // 去掉末尾的路径分隔符，输出文件才会写在文件夹旁边而不是里面
+    let dir: PathBuf = dir.components().collect();
+    let dir = dir.as_path();
+    let manifest = SplitManifest::load(dir)?;
+    let frame = |name: &str| manifest.as_ref().and_then(|m| m.frame(name));
+
+    let mut files: Vec<(String, PathBuf)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_frame_file(path))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            Some((name, path))
+        })
+        .collect();
+    // 清单中的图片按帧序号排在前面，其余按文件名
+    files.sort_by(|(a, _), (b, _)| {
+        let key = |name: &str| frame(name).map_or(u64::MAX, |f| f.frame_index);
+        key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+    });
+
+    if files.is_empty() {
+        return Err(Error::VideoProcessing(format!("帧文件夹中没有图片: {}", dir.display())));
+    }
+
+    let count = files.len().min(config.max_tiles);
+    let mut picked = Vec::with_capacity(count);
+    let mut tile = None;
+    for i in 0..count {
+        let (name, path) = &files[i * files.len() / count];
+        let image = image::open(path)
+            .map_err(|e| Error::Image(format!("无法读取图片 {}: {}", path.display(), e)))?
+            .to_rgb8();
+
+        // 缩略图尺寸以第一张图片为准，尺寸不同的图片拉伸到相同大小
+        let (tile_width, tile_height) = *tile.get_or_insert_with(|| {
+            tile_size(config.tile_width, image.width(), image.height())
+        });
+
+        let entry = frame(name);
+        picked.push(Picked {
+            image: image::imageops::thumbnail(&image, tile_width, tile_height),
+            time: entry.map(|f| f.pts),
+            frame_index: entry.map(|f| f.frame_index),
+            file: Some(name.clone()),
+        });
+    }
+
+    write_sheet(dir, picked, None, config)
+}
+
+/// 缩略图尺寸，不放大
+fn tile_size(tile_width: u32, width: u32, height: u32) -> (u32, u32) {
+    let width_out = tile_width.min(width).max(1);
+    let height_out = (height as u64 * width_out as u64 / width.max(1) as u64).max(1) as u32;
+    (width_out, height_out)
+}
+
+/// 拼接网格图并写在 `base` 旁边，雪碧图同时写出索引
+fn write_sheet(
+    base: &Path,
+    picked: Vec<Picked>,
+    duration_secs: Option<f64>,
+    config: &SheetConfig,
+) -> Result<SheetIndex> {
+    let format = SnapshotFormat::image_from_name(&config.image_format)?;
+    let contact = config.kind == SheetKind::Contact;
+    let spacing = if contact { config.spacing } else { 0 };
+
+    let (tile_width, tile_height) = picked[0].image.dimensions();
+    let columns = config.columns.min(picked.len() as u32);
+    let rows = (picked.len() as u32).div_ceil(columns);
+    let width = columns * (tile_width + spacing) + spacing;
+    let height = rows as u64 * (tile_height + spacing) as u64 + spacing as u64;
+    if height > MAX_SHEET_SIZE as u64 {
+        return Err(Error::Config(format!(
+            "网格图高度 {} 超过 {} 像素，请增加列数或减少缩略图数量", height, MAX_SHEET_SIZE
+        )));
+    }
+
+    let mut canvas = RgbImage::from_pixel(width, height as u32, Rgb(config.background));
+    let mut tiles = Vec::with_capacity(picked.len());
+    for (i, item) in picked.into_iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let x = spacing + column * (tile_width + spacing);
+        let y = spacing + row * (tile_height + spacing);
+        image::imageops::replace(&mut canvas, &item.image, x as i64, y as i64);
+
+        if contact && config.labels {
+            let label = match (item.time, item.frame_index) {
+                (Some(time), _) => format_time(time),
+                (None, Some(index)) => format!("#{}", index),
+                (None, None) => format!("#{}", i + 1),
+            };
+            draw_label(&mut canvas, x, y, tile_width, tile_height, &label);
+        }
+
+        tiles.push(SheetTile {
+            x,
+            y,
+            width: tile_width,
+            height: tile_height,
+            time: item.time,
+            frame_index: item.frame_index,
+            file: item.file,
+        });
+    }
+
+    let image_path = sheet_path(base, config.kind, format);
+    let options = SnapshotOptions {
+        quality: config.quality,
+        ..Default::default()
+    };
+    let data = encode_image(&DynamicImage::ImageRgb8(canvas), format, &options)?;
+    std::fs::write(&image_path, data)?;
+
+    let index = SheetIndex {
+        kind: config.kind,
+        image: image_path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        source: base.to_path_buf(),
+        columns,
+        rows,
+        tile_width,
+        tile_height,
+        duration_secs,
+        created_at: chrono::Local::now().to_rfc3339(),
+        tiles,
+    };
+
+    if config.kind == SheetKind::Sprite {
+        let json = serde_json::to_vec_pretty(&index)
+            .map_err(|e| Error::VideoProcessing(format!("序列化雪碧图索引失败: {}", e)))?;
+        std::fs::write(sprite_index_path(base), json)?;
+        std::fs::write(sprite_vtt_path(base), index.to_webvtt())?;
+    }
+
+    Ok(index)
+}
+
+/// 时间标注格式 `HH:MM:SS`
+fn format_time(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+}
+
+/// WebVTT 时间格式 `HH:MM:SS.mmm`
+fn vtt_time(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000
+    )
+}
+
+/// 3x5 点阵字形，每行低 3 位从左到右
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0; 5],
+    }
+}
+
+/// 在缩略图左下角绘制白字黑底的标注，`left`、`top` 为缩略图左上角
+///
+/// 字形按缩略图高度放大，超出缩略图的部分截断。
+fn draw_label(canvas: &mut RgbImage, left: u32, top: u32, tile_width: u32, tile_height: u32, text: &str) {
+    let scale = (tile_height / 60).clamp(1, 4);
+    let padding = scale;
+    let advance = 4 * scale;
+    let box_width = text.chars().count() as u32 * advance + padding * 2 - scale;
+    let box_height = (5 * scale + padding * 2).min(tile_height);
+    let right = (left + box_width).min(left + tile_width);
+    let bottom = top + tile_height;
+    let top = bottom - box_height;
+
+    for y in top..bottom {
+        for x in left..right {
+            canvas.put_pixel(x, y, Rgb([0, 0, 0]));
+        }
+    }
+
+    for (i, c) in text.chars().enumerate() {
+        let origin_x = left + padding + i as u32 * advance;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3u32 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = origin_x + column * scale + dx;
+                        let y = top + padding + row as u32 * scale + dy;
+                        if x < right && y < bottom {
+                            canvas.put_pixel(x, y, Rgb([255, 255, 255]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
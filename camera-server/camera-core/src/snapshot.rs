@@ -0,0 +1,220 @@
+//! 快照编码模块
+//!
+//! 将采集到的图像编码为 JPEG、PNG、WebP、TIFF、BMP 或原始 RGB/YUV 数据，
+//! 快照和视频拆分共用同一套格式定义。
+
+use crate::{Error, Result};
+use crate::stack::StackOptions;
+use crate::y4m::{rgb_to_yuv, Y4mColorspace};
+use image::{DynamicImage, ImageEncoder};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// 快照图像格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotFormat {
+    /// JPEG（有损）
+    Jpeg,
+    /// PNG（无损，支持16位）
+    Png,
+    /// WebP（无损）
+    WebP,
+    /// TIFF（无损，支持16位）
+    Tiff,
+    /// BMP（无压缩）
+    Bmp,
+    /// 原始 RGB24 数据，逐像素 R、G、B 排列
+    RawRgb,
+    /// 原始 YUV 4:2:0 平面数据（I420，BT.601 TV 范围）
+    RawYuv,
+}
+
+impl SnapshotFormat {
+    /// 可以保存为图片文件的格式
+    pub const IMAGE_FORMATS: [SnapshotFormat; 5] = [
+        Self::Jpeg, Self::Png, Self::WebP, Self::Tiff, Self::Bmp,
+    ];
+
+    /// 根据格式名称或扩展名解析格式，如 "jpg"、"png"、"tiff"
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            "webp" => Ok(Self::WebP),
+            "tif" | "tiff" => Ok(Self::Tiff),
+            "bmp" => Ok(Self::Bmp),
+            "rgb" | "raw" | "rawrgb" => Ok(Self::RawRgb),
+            "yuv" | "rawyuv" => Ok(Self::RawYuv),
+            other => Err(Error::Config(format!("不支持的图像格式: {}", other))),
+        }
+    }
+
+    /// 解析可以保存为图片文件的格式，用于拆分和网格图输出
+    ///
+    /// 原始 RGB/YUV 数据不带尺寸信息，无法单独查看，不能作为图片文件输出。
+    pub fn image_from_name(name: &str) -> Result<Self> {
+        match Self::from_name(name) {
+            Ok(format) if !format.is_raw() => Ok(format),
+            _ => Err(Error::Config(format!(
+                "不支持的图像格式: {}，可选 jpg、png、webp、tiff、bmp", name
+            ))),
+        }
+    }
+
+    /// 是否为原始像素数据
+    pub fn is_raw(&self) -> bool {
+        matches!(self, Self::RawRgb | Self::RawYuv)
+    }
+
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::WebP => "webp",
+            Self::Tiff => "tiff",
+            Self::Bmp => "bmp",
+            Self::RawRgb => "rgb",
+            Self::RawYuv => "yuv",
+        }
+    }
+
+    /// MIME类型
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::WebP => "image/webp",
+            Self::Tiff => "image/tiff",
+            Self::Bmp => "image/bmp",
+            Self::RawRgb | Self::RawYuv => "application/octet-stream",
+        }
+    }
+}
+
+/// 快照编码选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotOptions {
+    /// JPEG 质量 (1-100)
+    pub quality: u8,
+
+    /// PNG 压缩级别 (0-9)，数值越大文件越小、编码越慢
+    pub png_compression: u8,
+
+    /// TIFF/PNG 是否输出16位色深
+    pub high_bit_depth: bool,
+
+    /// 多帧叠加降噪选项，为 None 时只采集单帧
+    #[serde(default)]
+    pub stack: Option<StackOptions>,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            png_compression: 6,
+            high_bit_depth: false,
+            stack: None,
+        }
+    }
+}
+
+/// 按指定格式编码图像
+///
+/// 8位和16位图像都可以传入；不支持16位的格式会先转换为8位，
+/// 开启 `high_bit_depth` 时 TIFF 和 PNG 会以16位输出。8位灰度图保持单通道输出。
+pub fn encode_image(
+    image: &DynamicImage,
+    format: SnapshotFormat,
+    options: &SnapshotOptions,
+) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+
+    match format {
+        SnapshotFormat::Jpeg => {
+            let (samples, color) = to_8bit(image);
+            let quality = options.quality.clamp(1, 100);
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality)
+                .write_image(&samples, image.width(), image.height(), color)
+                .map_err(|e| Error::Image(format!("JPEG编码失败: {}", e)))?;
+        }
+        SnapshotFormat::Png => {
+            use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+
+            let compression = match options.png_compression {
+                0..=2 => CompressionType::Fast,
+                3..=6 => CompressionType::Default,
+                _ => CompressionType::Best,
+            };
+            let encoder = PngEncoder::new_with_quality(&mut buffer, compression, FilterType::Adaptive);
+
+            if options.high_bit_depth {
+                let rgb = image.to_rgb16();
+                encoder.write_image(
+                    &to_ne_bytes(rgb.as_raw()),
+                    rgb.width(),
+                    rgb.height(),
+                    image::ColorType::Rgb16,
+                )
+            } else {
+                let (samples, color) = to_8bit(image);
+                encoder.write_image(&samples, image.width(), image.height(), color)
+            }
+            .map_err(|e| Error::Image(format!("PNG编码失败: {}", e)))?;
+        }
+        SnapshotFormat::WebP => {
+            let (samples, color) = to_8bit(image);
+            image::codecs::webp::WebPEncoder::new_lossless(&mut buffer)
+                .write_image(&samples, image.width(), image.height(), color)
+                .map_err(|e| Error::Image(format!("WebP编码失败: {}", e)))?;
+        }
+        SnapshotFormat::Tiff => {
+            // TIFF 编码器需要可定位的输出
+            let mut cursor = Cursor::new(&mut buffer);
+            let encoder = image::codecs::tiff::TiffEncoder::new(&mut cursor);
+
+            if options.high_bit_depth {
+                let rgb = image.to_rgb16();
+                encoder.write_image(
+                    &to_ne_bytes(rgb.as_raw()),
+                    rgb.width(),
+                    rgb.height(),
+                    image::ColorType::Rgb16,
+                )
+            } else {
+                let (samples, color) = to_8bit(image);
+                encoder.write_image(&samples, image.width(), image.height(), color)
+            }
+            .map_err(|e| Error::Image(format!("TIFF编码失败: {}", e)))?;
+        }
+        SnapshotFormat::Bmp => {
+            let (samples, color) = to_8bit(image);
+            image::codecs::bmp::BmpEncoder::new(&mut buffer)
+                .write_image(&samples, image.width(), image.height(), color)
+                .map_err(|e| Error::Image(format!("BMP编码失败: {}", e)))?;
+        }
+        SnapshotFormat::RawRgb => {
+            buffer = image.to_rgb8().into_raw();
+        }
+        SnapshotFormat::RawYuv => {
+            buffer = rgb_to_yuv(&image.to_rgb8(), Y4mColorspace::C420, false);
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// 取8位样本，灰度图保持单通道，其他图像转换为 RGB
+fn to_8bit(image: &DynamicImage) -> (Vec<u8>, image::ColorType) {
+    match image {
+        DynamicImage::ImageLuma8(gray) => (gray.as_raw().clone(), image::ColorType::L8),
+        other => (other.to_rgb8().into_raw(), image::ColorType::Rgb8),
+    }
+}
+
+/// 将16位样本按本机字节序展开为字节
+fn to_ne_bytes(samples: &[u16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_ne_bytes()).collect()
+}
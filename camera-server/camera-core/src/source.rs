@@ -0,0 +1,236 @@
+//! 视频帧源模块
+//!
+//! 为视频拆分等功能提供统一的逐帧读取接口，根据文件扩展名
+//! 选择对应的解复用/解码实现。
+
+use crate::{Error, Result};
+use crate::avi::AviReader;
+use crate::ffmpeg::FfmpegSource;
+use crate::y4m::Y4mReader;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// 视频文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// YUV4MPEG2 原始视频
+    Y4m,
+    /// AVI 封装的 MJPEG 视频
+    AviMjpeg,
+    /// 通过 ffmpeg 子进程解码的其他容器（MP4、MKV、MOV、TS）
+    Ffmpeg,
+}
+
+/// 视频基本信息
+#[derive(Debug, Clone)]
+pub struct VideoInfo {
+    /// 文件格式
+    pub format: VideoFormat,
+    /// 帧宽度
+    pub width: u32,
+    /// 帧高度
+    pub height: u32,
+    /// 帧率
+    pub fps: f64,
+    /// 总帧数（无法确定时为 None）
+    pub frame_count: Option<u64>,
+    /// 是否每一帧都是关键帧，如 Y4M 和 MJPEG 这类只有帧内编码的格式
+    pub intra_only: bool,
+}
+
+/// 解码后的视频帧
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    /// 帧序号（从0开始）
+    pub index: u64,
+    /// 显示时间戳（秒）
+    pub pts: f64,
+    /// 是否为关键帧
+    pub keyframe: bool,
+    /// 帧图像
+    pub image: image::RgbImage,
+}
+
+/// 视频帧源
+pub trait FrameSource: Send {
+    /// 获取视频信息
+    fn info(&self) -> &VideoInfo;
+
+    /// 读取下一帧，到达文件末尾时返回 None
+    fn next_frame(&mut self) -> Result<Option<DecodedFrame>>;
+}
+
+/// 打开视频文件，根据扩展名选择帧源实现
+pub fn open_video(path: &Path) -> Result<Box<dyn FrameSource>> {
+    match video_format(path)? {
+        VideoFormat::Y4m => Ok(Box::new(Y4mSource::open(path)?)),
+        VideoFormat::AviMjpeg => Ok(Box::new(AviSource::open(path)?)),
+        VideoFormat::Ffmpeg => Ok(Box::new(FfmpegSource::open(path)?)),
+    }
+}
+
+/// 只读取视频信息，不开始解码
+///
+/// Y4M 和 AVI 只读取文件头和索引；其他容器只读取视频流信息，
+/// 不扫描数据包，总帧数和是否只有帧内编码要等 [`open_video`] 时才能确定。
+pub fn probe_video(path: &Path) -> Result<VideoInfo> {
+    match video_format(path)? {
+        VideoFormat::Y4m => Ok(Y4mSource::open(path)?.info),
+        VideoFormat::AviMjpeg => Ok(AviSource::open(path)?.info),
+        VideoFormat::Ffmpeg => FfmpegSource::probe(path),
+    }
+}
+
+/// 检查文件是否存在并按扩展名确定格式
+fn video_format(path: &Path) -> Result<VideoFormat> {
+    if !path.exists() {
+        return Err(Error::VideoProcessing(format!(
+            "视频文件不存在: {}", path.display()
+        )));
+    }
+
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "y4m" => Ok(VideoFormat::Y4m),
+        "avi" => Ok(VideoFormat::AviMjpeg),
+        "mp4" | "mkv" | "mov" | "ts" => Ok(VideoFormat::Ffmpeg),
+        _ => Err(Error::VideoProcessing(format!(
+            "不支持的视频格式: {}", path.display()
+        ))),
+    }
+}
+
+/// Y4M 帧源
+struct Y4mSource {
+    /// Y4M 读取器
+    reader: Y4mReader<BufReader<File>>,
+    /// 视频信息
+    info: VideoInfo,
+    /// 下一帧序号
+    next_index: u64,
+}
+
+impl Y4mSource {
+    /// 打开 Y4M 文件
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let reader = Y4mReader::new(BufReader::new(file))?;
+
+        let header = reader.header();
+        let info = VideoInfo {
+            format: VideoFormat::Y4m,
+            width: header.width,
+            height: header.height,
+            fps: header.fps(),
+            frame_count: Some(reader.estimate_frame_count(file_size)),
+            intra_only: true,
+        };
+
+        Ok(Self {
+            reader,
+            info,
+            next_index: 0,
+        })
+    }
+}
+
+impl FrameSource for Y4mSource {
+    fn info(&self) -> &VideoInfo {
+        &self.info
+    }
+
+    fn next_frame(&mut self) -> Result<Option<DecodedFrame>> {
+        let image = match self.reader.next_frame()? {
+            Some(image) => image,
+            None => return Ok(None),
+        };
+
+        let index = self.next_index;
+        self.next_index += 1;
+
+        let pts = if self.info.fps > 0.0 { index as f64 / self.info.fps } else { 0.0 };
+
+        Ok(Some(DecodedFrame {
+            index,
+            pts,
+            // 原始视频每一帧都是独立的
+            keyframe: true,
+            image,
+        }))
+    }
+}
+
+/// AVI (MJPEG) 帧源
+struct AviSource {
+    /// AVI 读取器
+    reader: AviReader<BufReader<File>>,
+    /// 视频信息
+    info: VideoInfo,
+    /// 下一帧序号
+    next_index: u64,
+}
+
+impl AviSource {
+    /// 打开 AVI 文件
+    fn open(path: &Path) -> Result<Self> {
+        let reader = AviReader::new(BufReader::new(File::open(path)?))?;
+
+        let compression = reader.compression();
+        if !matches!(compression.as_str(), "MJPG" | "mjpg" | "AVRn" | "LJPG") {
+            return Err(Error::VideoProcessing(format!(
+                "不支持的AVI视频编码: {}", compression
+            )));
+        }
+
+        let info = VideoInfo {
+            format: VideoFormat::AviMjpeg,
+            width: reader.width(),
+            height: reader.height(),
+            fps: reader.fps(),
+            frame_count: Some(reader.frame_count()),
+            intra_only: true,
+        };
+
+        Ok(Self {
+            reader,
+            info,
+            next_index: 0,
+        })
+    }
+}
+
+impl FrameSource for AviSource {
+    fn info(&self) -> &VideoInfo {
+        &self.info
+    }
+
+    fn next_frame(&mut self) -> Result<Option<DecodedFrame>> {
+        let index = self.next_index;
+        if index >= self.reader.frame_count() {
+            return Ok(None);
+        }
+
+        let keyframe = self.reader.index()[index as usize].keyframe;
+        let data = self.reader.read_frame(index as usize)?;
+        let image = image::load_from_memory_with_format(&data, image::ImageFormat::Jpeg)
+            .map_err(|e| Error::Image(format!("解码第 {} 帧失败: {}", index, e)))?
+            .to_rgb8();
+
+        self.next_index += 1;
+
+        let pts = if self.info.fps > 0.0 { index as f64 / self.info.fps } else { 0.0 };
+
+        Ok(Some(DecodedFrame {
+            index,
+            pts,
+            keyframe,
+            image,
+        }))
+    }
+}
@@ -0,0 +1,820 @@
+//! 视频拆分任务模块
+//!
+//! 拆分任务在后台线程中运行：逐帧解码视频，按 `SplitConfig.sampling` 指定的方式抽取画面，
+//! 编码为 `image_format` 指定的图片格式写入输出目录，结束时写出拆分清单。任务状态通过共享的
+//! `SplitTaskStatus` 对外报告，取消通过标志位协作完成，工作线程在每帧之间检查。
+//!
+//! 排队中和运行中的任务以及历史记录保存在 `SplitQueueFile` 中，
+//! 服务重启后未完成的任务重新排队。服务关闭时运行中的任务被中断而不是取消，
+//! 已输出的图片保留在输出目录中。
+
+use crate::{Error, Result, config::{DedupAction, FrameTransform, SamplingMode, SplitConfig, TimeRange}};
+use crate::dedup::{self, DedupDecision, Deduplicator};
+use crate::guard::{StorageGuard, StorageJob};
+use crate::manifest::{FrameEntry, SplitManifest, SplitParams, MANIFEST_VERSION};
+use crate::naming::{FilenameTemplate, NamingContext};
+use crate::scene::SceneDetector;
+use crate::snapshot::{encode_image, SnapshotFormat, SnapshotOptions};
+use crate::source::{DecodedFrame, FrameSource, VideoInfo};
+use crate::transform;
+use chrono::{DateTime, Local};
+use image::DynamicImage;
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// 拆分过程中每写入多少字节检查一次存储空间
+const STORAGE_CHECK_BYTES: u64 = 16 * 1024 * 1024;
+
+/// 拆分任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitState {
+    /// 排队等待
+    Queued,
+    /// 正在拆分
+    Running,
+    /// 拆分完成
+    Completed,
+    /// 已取消，已输出的图片会被删除
+    Cancelled,
+    /// 拆分失败，已输出的图片保留
+    Failed,
+}
+
+impl SplitState {
+    /// 任务是否已结束
+    pub fn is_finished(self) -> bool {
+        matches!(self, SplitState::Completed | SplitState::Cancelled | SplitState::Failed)
+    }
+}
+
+/// 拆分任务状态信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitTaskStatus {
+    /// 任务ID
+    pub task_id: String,
+    /// 源视频路径
+    pub video_path: PathBuf,
+    /// 输出目录
+    pub output_dir: PathBuf,
+    /// 任务状态
+    pub state: SplitState,
+    /// 进度 (0.0 - 1.0)
+    pub progress: f32,
+    /// 已解码帧数
+    pub frames_decoded: u64,
+    /// 已输出图片数
+    pub frames_written: u64,
+    /// 视频总帧数（无法确定时为 None）
+    pub total_frames: Option<u64>,
+    /// 已输出图片总大小（字节）
+    pub bytes_written: u64,
+    /// 去重时判定为重复的帧数
+    #[serde(default)]
+    pub frames_duplicate: u64,
+    /// 失败原因
+    pub error: Option<String>,
+    /// 加入队列时间（RFC 3339）
+    pub queued_at: String,
+    /// 开始时间（RFC 3339）
+    #[serde(default)]
+    pub started_at: Option<String>,
+    /// 结束时间（RFC 3339）
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    /// 运行时长（秒）
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+    /// 该任务使用的拆分配置，未设置时使用拆分器的当前配置
+    #[serde(default)]
+    pub config: Option<SplitConfig>,
+}
+
+impl SplitTaskStatus {
+    /// 创建排队中的任务
+    pub fn queued(task_id: String, video_path: &Path, output_dir: PathBuf) -> Self {
+        Self {
+            task_id,
+            video_path: video_path.to_path_buf(),
+            output_dir,
+            state: SplitState::Queued,
+            progress: 0.0,
+            frames_decoded: 0,
+            frames_written: 0,
+            total_frames: None,
+            bytes_written: 0,
+            frames_duplicate: 0,
+            error: None,
+            queued_at: Local::now().to_rfc3339(),
+            started_at: None,
+            finished_at: None,
+            duration_secs: None,
+            config: None,
+        }
+    }
+
+    /// 标记任务结束并计算运行时长
+    pub fn finish(&mut self, state: SplitState, error: Option<String>) {
+        let now = Local::now();
+        self.state = state;
+        self.error = error;
+        self.finished_at = Some(now.to_rfc3339());
+        self.duration_secs = self.started_at.as_deref()
+            .and_then(|started| DateTime::parse_from_rfc3339(started).ok())
+            .map(|started| (now - started.with_timezone(&Local)).num_milliseconds() as f64 / 1000.0);
+    }
+
+    /// 重启后将未完成的任务重置为排队状态
+    fn requeue(&mut self) {
+        self.state = SplitState::Queued;
+        self.progress = 0.0;
+        self.frames_decoded = 0;
+        self.frames_written = 0;
+        self.bytes_written = 0;
+        self.frames_duplicate = 0;
+        self.started_at = None;
+    }
+}
+
+/// 拆分队列持久化文件内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SplitQueueFile {
+    /// 未完成的任务（排队中和运行中），按加入顺序
+    #[serde(default)]
+    pub pending: Vec<SplitTaskStatus>,
+    /// 已结束的任务，最新的在前
+    #[serde(default)]
+    pub history: Vec<SplitTaskStatus>,
+}
+
+impl SplitQueueFile {
+    /// 读取队列文件，文件不存在时返回空队列
+    ///
+    /// 上次退出时仍在运行的任务会重新排队。
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = std::fs::read(path)?;
+        let mut queue: Self = serde_json::from_slice(&data)
+            .map_err(|e| Error::VideoProcessing(format!("拆分队列文件损坏 {}: {}", path.display(), e)))?;
+
+        for job in &mut queue.pending {
+            if job.state != SplitState::Queued {
+                info!("重新排队未完成的拆分任务: {} ({})", job.task_id, job.video_path.display());
+                job.requeue();
+            }
+        }
+
+        Ok(queue)
+    }
+
+    /// 写入队列文件，先写临时文件再替换，避免写入中断导致文件损坏
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::VideoProcessing(format!("序列化拆分队列失败: {}", e)))?;
+        let mut temp = path.as_os_str().to_os_string();
+        temp.push(".tmp");
+        std::fs::write(&temp, data)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// 校验拆分配置
+pub fn validate(config: &SplitConfig) -> Result<()> {
+    SnapshotFormat::image_from_name(&config.image_format)?;
+
+    match &config.sampling {
+        SamplingMode::Rate => {
+            if !config.frame_rate.is_finite() || config.frame_rate <= 0.0 {
+                return Err(Error::Config(format!("提取帧率必须大于0: {}", config.frame_rate)));
+            }
+        }
+        SamplingMode::Nth { n } => {
+            if *n == 0 {
+                return Err(Error::Config("抽帧间隔不能为0".to_string()));
+            }
+        }
+        SamplingMode::Timestamps { timestamps } => {
+            if timestamps.is_empty() {
+                return Err(Error::Config("按时间点抽帧时至少需要一个时间点".to_string()));
+            }
+            if let Some(t) = timestamps.iter().find(|t| !t.is_finite() || **t < 0.0) {
+                return Err(Error::Config(format!("抽帧时间点必须为非负数: {}", t)));
+            }
+        }
+        SamplingMode::Keyframes => {}
+        SamplingMode::Scene { threshold, min_gap_secs, .. } => {
+            if !threshold.is_finite() || *threshold <= 0.0 || *threshold > 1.0 {
+                return Err(Error::Config(format!("场景变化阈值必须在 0-1 范围内: {}", threshold)));
+            }
+            if !min_gap_secs.is_finite() || *min_gap_secs < 0.0 {
+                return Err(Error::Config(format!("场景变化最小间隔必须为非负数: {}", min_gap_secs)));
+            }
+        }
+    }
+
+    for range in &config.time_ranges {
+        if !range.start.is_finite() || range.start < 0.0 {
+            return Err(Error::Config(format!("拆分开始时间必须为非负数: {}", range.start)));
+        }
+        if let Some(end) = range.end {
+            if !end.is_finite() || end <= range.start {
+                return Err(Error::Config(format!(
+                    "拆分结束时间必须晚于开始时间: {} - {}", range.start, end
+                )));
+            }
+        }
+    }
+
+    if !(1..=100).contains(&config.quality) {
+        return Err(Error::Config(format!("图像质量必须在 1-100 范围内: {}", config.quality)));
+    }
+
+    if !(1..=8).contains(&config.max_concurrent) {
+        return Err(Error::Config(format!("拆分并发数必须在 1-8 范围内: {}", config.max_concurrent)));
+    }
+
+    if config.dedup.enabled {
+        dedup::validate(&config.dedup)?;
+    }
+
+    transform::validate(&config.transform)?;
+
+    config.folder_template()?;
+    config.frame_template()?;
+    Ok(())
+}
+
+/// 检查拆分配置是否适用于该视频
+///
+/// 裁剪区域是否超出画面需要知道视频尺寸；只有帧内编码的视频每一帧都是关键帧，
+/// 按关键帧抽帧等于输出所有帧，直接拒绝。加入队列时只读取了视频流信息，
+/// 是否只有帧内编码可能还不确定，任务开始时会用完整的视频信息再检查一次。
+pub fn check_source(config: &SplitConfig, info: &VideoInfo) -> Result<()> {
+    transform::output_size(&config.transform, info.width, info.height)?;
+
+    if matches!(config.sampling, SamplingMode::Keyframes) && info.intra_only {
+        return Err(Error::Config(format!(
+            "{:?} 格式的视频每一帧都是关键帧，不能按关键帧抽帧，请使用 rate 或 nth 方式",
+            info.format
+        )));
+    }
+
+    Ok(())
+}
+
+/// 运行中的拆分任务
+pub struct SplitTask {
+    /// 共享的任务状态
+    status: Arc<Mutex<SplitTaskStatus>>,
+    /// 取消标志
+    cancel: Arc<AtomicBool>,
+    /// 中断标志，中断时保留已输出的图片
+    interrupt: Arc<AtomicBool>,
+    /// 工作线程
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SplitTask {
+    /// 在后台线程中开始拆分排队中的任务
+    pub fn spawn(
+        mut job: SplitTaskStatus,
+        source: Box<dyn FrameSource>,
+        config: &SplitConfig,
+        storage_guard: Option<Arc<dyn StorageGuard>>,
+    ) -> Result<Self> {
+        check_source(config, source.info())?;
+
+        let task_id = job.task_id.clone();
+        let output_dir = job.output_dir.clone();
+        let video_name = job.video_path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        job.state = SplitState::Running;
+        job.started_at = Some(Local::now().to_rfc3339());
+        job.total_frames = source.info().frame_count;
+        let status = Arc::new(Mutex::new(job));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let interrupt = Arc::new(AtomicBool::new(false));
+
+        let worker = Worker {
+            source,
+            output_dir,
+            video_name,
+            started_at: Local::now(),
+            template: config.frame_template()?,
+            format: SnapshotFormat::image_from_name(&config.image_format)?,
+            options: SnapshotOptions {
+                quality: config.quality,
+                ..Default::default()
+            },
+            params: SplitParams::from_config(config),
+            dedup: config.dedup.enabled.then(|| Deduplicator::new(config.dedup.clone())),
+            frames: Vec::new(),
+            transform: config.transform.clone(),
+            storage_guard,
+            status: status.clone(),
+            cancel: cancel.clone(),
+            interrupt: interrupt.clone(),
+        };
+
+        let handle = std::thread::Builder::new()
+            .name(format!("split-{}", task_id))
+            .spawn(move || worker.run())
+            .map_err(|e| Error::VideoProcessing(format!("无法启动拆分线程: {}", e)))?;
+
+        Ok(Self {
+            status,
+            cancel,
+            interrupt,
+            handle: Some(handle),
+        })
+    }
+
+    /// 当前任务状态
+    pub fn status(&self) -> SplitTaskStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// 任务ID
+    pub fn task_id(&self) -> String {
+        self.status.lock().unwrap().task_id.clone()
+    }
+
+    /// 任务是否仍在运行
+    pub fn is_running(&self) -> bool {
+        !self.status.lock().unwrap().state.is_finished()
+    }
+
+    /// 请求取消任务，工作线程会在处理完当前帧后停止
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// 服务关闭时中断任务，工作线程处理完当前帧后停止
+    ///
+    /// 与取消不同，已输出的图片和不完整的清单保留在输出目录中，任务状态仍为运行中，
+    /// 队列文件中的记录不变，下次启动时重新拆分。
+    pub fn interrupt(&self) {
+        self.interrupt.store(true, Ordering::SeqCst);
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// 等待工作线程退出
+    pub fn join(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                let mut status = self.status.lock().unwrap();
+                status.finish(SplitState::Failed, Some("拆分线程异常退出".to_string()));
+            }
+        }
+    }
+}
+
+impl Drop for SplitTask {
+    fn drop(&mut self) {
+        // 随拆分器一起释放时视为服务关闭，不删除已输出的图片
+        self.interrupt();
+        self.join();
+    }
+}
+
+/// 拆分工作线程
+struct Worker {
+    /// 帧源
+    source: Box<dyn FrameSource>,
+    /// 输出目录
+    output_dir: PathBuf,
+    /// 原视频文件名（不含扩展名）
+    video_name: String,
+    /// 任务开始时间，用于帧文件名中的日期和时间
+    started_at: DateTime<Local>,
+    /// 帧文件名模板
+    template: FilenameTemplate,
+    /// 图片格式
+    format: SnapshotFormat,
+    /// 图片编码选项，只使用其中的 JPEG 质量
+    options: SnapshotOptions,
+    /// 拆分参数，决定抽帧方式并写入清单
+    params: SplitParams,
+    /// 相似帧去重，未启用时为 None
+    dedup: Option<Deduplicator>,
+    /// 已输出的图片，写入清单
+    frames: Vec<FrameEntry>,
+    /// 输出前的图片变换
+    transform: FrameTransform,
+    /// 存储空间检查
+    storage_guard: Option<Arc<dyn StorageGuard>>,
+    /// 共享的任务状态
+    status: Arc<Mutex<SplitTaskStatus>>,
+    /// 取消标志
+    cancel: Arc<AtomicBool>,
+    /// 中断标志
+    interrupt: Arc<AtomicBool>,
+}
+
+impl Worker {
+    /// 运行拆分并记录最终状态
+    fn run(mut self) {
+        let result = self.extract();
+        let interrupted = matches!(result, Ok(false)) && self.interrupt.load(Ordering::SeqCst);
+
+        // 写文件期间不持有状态锁，避免查询任务状态时被阻塞
+        let snapshot = self.status.lock().unwrap().clone();
+
+        match &result {
+            Ok(false) if interrupted => {
+                info!("拆分已中断，保留已输出的 {} 张图片: {}",
+                    snapshot.frames_written, snapshot.output_dir.display());
+            }
+            Ok(true) => {
+                info!("拆分完成: {} -> {}，共输出 {} 张图片",
+                    snapshot.video_path.display(), snapshot.output_dir.display(), snapshot.frames_written);
+            }
+            Ok(false) => info!("拆分已取消: {}", snapshot.task_id),
+            Err(e) => error!("拆分失败 {}: {}", snapshot.video_path.display(), e),
+        }
+
+        if matches!(result, Ok(false)) && !interrupted {
+            // 取消时删除输出目录，不需要清单和去重报告
+            if let Err(e) = std::fs::remove_dir_all(&self.output_dir) {
+                warn!("删除未完成的拆分输出失败 {}: {}", self.output_dir.display(), e);
+            }
+        } else {
+            if let Err(e) = self.write_manifest(&snapshot, matches!(result, Ok(true))) {
+                warn!("写入拆分清单失败 {}: {}", self.output_dir.display(), e);
+            }
+
+            if let Some(dedup) = &self.dedup {
+                if let Err(e) = dedup.report().write(&self.output_dir) {
+                    warn!("写入去重报告失败 {}: {}", self.output_dir.display(), e);
+                }
+            }
+        }
+
+        let mut status = self.status.lock().unwrap();
+        match result {
+            // 中断的任务保持运行中状态，下次启动时重新拆分
+            Ok(false) if interrupted => {}
+            Ok(true) => {
+                status.finish(SplitState::Completed, None);
+                status.progress = 1.0;
+            }
+            Ok(false) => status.finish(SplitState::Cancelled, None),
+            Err(e) => status.finish(SplitState::Failed, Some(e.to_string())),
+        }
+    }
+
+    /// 逐帧解码并输出图片，被取消时返回 `Ok(false)`
+    fn extract(&mut self) -> Result<bool> {
+        let fps = self.source.info().fps;
+        let mut sampler = Sampler::new(&self.params, fps);
+
+        // 只拆分到某个时间点时，进度按需要解码的帧数计算
+        let total_frames = match (self.source.info().frame_count, sampler.end_time()) {
+            (Some(total), Some(end)) if fps > 0.0 => Some(total.min((end * fps).ceil() as u64)),
+            (total, _) => total,
+        };
+        let mut unchecked_bytes = 0u64;
+
+        while let Some(frame) = self.source.next_frame()? {
+            if self.cancel.load(Ordering::SeqCst) {
+                return Ok(false);
+            }
+
+            if sampler.is_done(frame.pts) {
+                break;
+            }
+
+            let mut written = None;
+            let mut duplicate = None;
+            if sampler.accept(&frame) {
+                let output = if self.transform.is_identity() {
+                    DynamicImage::ImageRgb8(frame.image)
+                } else {
+                    transform::apply(&frame.image, &self.transform)
+                };
+
+                let context = NamingContext {
+                    video: Some(&self.video_name),
+                    frame: Some(frame.index),
+                    ..NamingContext::new(self.started_at)
+                };
+                let name = format!("{}.{}", self.template.render(&context), self.format.extension());
+
+                if let Some(dedup) = &mut self.dedup {
+                    // 按实际输出的画面去重，裁剪后的区域才是需要比较的内容
+                    if let DedupDecision::Duplicate { of, .. } = dedup.check(&output, &name, Some(frame.index)) {
+                        duplicate = Some(of);
+                    }
+                }
+
+                let dropped = duplicate.is_some()
+                    && self.params.dedup.as_ref().is_some_and(|d| d.action == DedupAction::Drop);
+                if !dropped {
+                    let data = encode_image(&output, self.format, &self.options)?;
+
+                    unchecked_bytes += data.len() as u64;
+                    if unchecked_bytes >= STORAGE_CHECK_BYTES {
+                        self.check_storage(unchecked_bytes)?;
+                        unchecked_bytes = 0;
+                    }
+
+                    std::fs::write(self.output_dir.join(&name), &data)?;
+                    written = Some(data.len() as u64);
+
+                    self.frames.push(FrameEntry {
+                        file: name,
+                        frame_index: frame.index,
+                        pts: frame.pts,
+                        keyframe: frame.keyframe,
+                        duplicate_of: duplicate.clone(),
+                    });
+                }
+            }
+
+            let mut status = self.status.lock().unwrap();
+            status.frames_decoded += 1;
+            if duplicate.is_some() {
+                status.frames_duplicate += 1;
+            }
+            if let Some(bytes) = written {
+                status.frames_written += 1;
+                status.bytes_written += bytes;
+            }
+            if let Some(total) = total_frames.filter(|&t| t > 0) {
+                status.progress = (status.frames_decoded as f32 / total as f32).min(0.99);
+            }
+        }
+
+        Ok(!self.cancel.load(Ordering::SeqCst))
+    }
+
+    /// 检查剩余空间是否足够继续写入
+    fn check_storage(&self, required: u64) -> Result<()> {
+        match &self.storage_guard {
+            Some(guard) => guard.check(StorageJob::Split, &self.output_dir, required),
+            None => Ok(()),
+        }
+    }
+
+    /// 在输出目录写出拆分清单
+    fn write_manifest(&self, status: &SplitTaskStatus, complete: bool) -> Result<()> {
+        let manifest = SplitManifest {
+            version: MANIFEST_VERSION,
+            task_id: status.task_id.clone(),
+            video: status.video_path.clone(),
+            video_fps: self.source.info().fps,
+            params: self.params.clone(),
+            frames_decoded: status.frames_decoded,
+            frames_written: status.frames_written,
+            frames_duplicate: status.frames_duplicate,
+            complete,
+            created_at: Local::now().to_rfc3339(),
+            frames: self.frames.clone(),
+        };
+        manifest.write(&self.output_dir)
+    }
+}
+
+/// 按抽帧方式和时间段决定输出哪些帧
+struct Sampler {
+    /// 抽帧方式
+    mode: SamplingMode,
+    /// 拆分的时间段，为空表示整个视频
+    ranges: Vec<TimeRange>,
+    /// 时间误差，取半帧，避免时间戳的舍入误差导致漏帧
+    tolerance: f64,
+    /// `rate` 方式的采样间隔（秒）
+    step: f64,
+    /// `rate` 方式的下一个采样时间
+    next_sample: f64,
+    /// `timestamps` 方式尚未输出的时间点，升序
+    timestamps: VecDeque<f64>,
+    /// `scene` 方式的场景变化检测器
+    scene: Option<SceneDetector>,
+    /// 上一张输出的时间
+    last_output: Option<f64>,
+}
+
+impl Sampler {
+    /// 根据拆分参数创建
+    fn new(params: &SplitParams, fps: f64) -> Self {
+        // 不在时间段内的时间点不会输出，提前丢弃，否则会在时间段的第一帧被当作命中
+        let timestamps = match &params.sampling {
+            SamplingMode::Timestamps { timestamps } => {
+                let mut sorted: Vec<f64> = timestamps.iter()
+                    .copied()
+                    .filter(|&t| params.time_ranges.is_empty()
+                        || params.time_ranges.iter().any(|range| range.contains(t)))
+                    .collect();
+                sorted.sort_by(f64::total_cmp);
+                sorted.into()
+            }
+            _ => VecDeque::new(),
+        };
+
+        Self {
+            mode: params.sampling.clone(),
+            ranges: params.time_ranges.clone(),
+            tolerance: if fps > 0.0 { 0.5 / fps } else { 0.0 },
+            step: 1.0 / params.frame_rate.unwrap_or(1.0) as f64,
+            next_sample: 0.0,
+            timestamps,
+            scene: match &params.sampling {
+                SamplingMode::Scene { metric, threshold, .. } => Some(SceneDetector::new(*metric, *threshold)),
+                _ => None,
+            },
+            last_output: None,
+        }
+    }
+
+    /// 需要解码到的时间点，整个视频都需要时返回 None
+    fn end_time(&self) -> Option<f64> {
+        let range_end = if self.ranges.is_empty() {
+            None
+        } else {
+            self.ranges.iter()
+                .map(|range| range.end)
+                .try_fold(0.0f64, |end, range_end| range_end.map(|e| end.max(e)))
+        };
+
+        let last_timestamp = self.timestamps.back().map(|t| t + self.tolerance);
+
+        match (range_end, last_timestamp) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// 之后的帧都不会再输出，可以提前结束解码
+    fn is_done(&self, pts: f64) -> bool {
+        if matches!(self.mode, SamplingMode::Timestamps { .. }) && self.timestamps.is_empty() {
+            return true;
+        }
+
+        self.end_time().is_some_and(|end| pts >= end)
+    }
+
+    /// 是否输出该帧
+    fn accept(&mut self, frame: &DecodedFrame) -> bool {
+        let pts = frame.pts + self.tolerance;
+        if !self.ranges.is_empty() && !self.ranges.iter().any(|range| range.contains(pts)) {
+            return false;
+        }
+
+        let accepted = self.sample(frame, pts);
+        if accepted {
+            self.last_output = Some(frame.pts);
+        }
+        accepted
+    }
+
+    /// 按抽帧方式判断，`pts` 已加上时间误差
+    fn sample(&mut self, frame: &DecodedFrame, pts: f64) -> bool {
+        match &self.mode {
+            SamplingMode::Rate => {
+                if pts < self.next_sample {
+                    return false;
+                }
+
+                // 跳过已经落后的采样点，视频时间戳不连续时不会连续输出多帧
+                while self.next_sample <= pts {
+                    self.next_sample += self.step;
+                }
+                true
+            }
+            SamplingMode::Nth { n } => frame.index.is_multiple_of(*n),
+            SamplingMode::Timestamps { .. } => {
+                // 多个时间点落在同一帧上时只输出一次
+                let mut hit = false;
+                while self.timestamps.front().is_some_and(|&t| t <= pts) {
+                    self.timestamps.pop_front();
+                    hit = true;
+                }
+                hit
+            }
+            SamplingMode::Keyframes => frame.keyframe,
+            SamplingMode::Scene { min_gap_secs, .. } => {
+                // 间隔不足时不做比较，也不更新参考帧
+                if self.last_output.is_some_and(|last| pts - last < *min_gap_secs) {
+                    return false;
+                }
+                self.scene.as_mut().is_some_and(|scene| scene.detect(&frame.image))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::VideoFormat;
+
+    /// 按配置对 10fps 的 `count` 帧抽帧，返回输出的帧序号，每5帧一个关键帧
+    fn sample(config: SplitConfig, count: u64) -> Vec<u64> {
+        let mut sampler = Sampler::new(&SplitParams::from_config(&config), 10.0);
+
+        let mut accepted = Vec::new();
+        for index in 0..count {
+            let frame = DecodedFrame {
+                index,
+                pts: index as f64 / 10.0,
+                keyframe: index % 5 == 0,
+                image: image::RgbImage::new(1, 1),
+            };
+            if sampler.is_done(frame.pts) {
+                break;
+            }
+            if sampler.accept(&frame) {
+                accepted.push(index);
+            }
+        }
+        accepted
+    }
+
+    #[test]
+    fn rate_and_nth_sampling() {
+        let rate = SplitConfig {
+            sampling: SamplingMode::Rate,
+            frame_rate: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(sample(rate, 20), vec![0, 5, 10, 15]);
+
+        let nth = SplitConfig {
+            sampling: SamplingMode::Nth { n: 3 },
+            ..Default::default()
+        };
+        assert_eq!(sample(nth, 10), vec![0, 3, 6, 9]);
+
+        // 时间段内重新从第一帧开始计数
+        let nth_in_range = SplitConfig {
+            sampling: SamplingMode::Nth { n: 2 },
+            time_ranges: vec![TimeRange { start: 1.0, end: Some(1.5) }],
+            ..Default::default()
+        };
+        assert_eq!(sample(nth_in_range, 100), vec![10, 12, 14]);
+    }
+
+    #[test]
+    fn timestamps_hit_nearest_frame_once() {
+        let config = SplitConfig {
+            sampling: SamplingMode::Timestamps { timestamps: vec![1.0, 0.0, 1.02, 0.5] },
+            ..Default::default()
+        };
+        assert_eq!(sample(config, 100), vec![0, 5, 10]);
+
+        let in_range = SplitConfig {
+            sampling: SamplingMode::Timestamps { timestamps: vec![0.5, 2.0, 9.0] },
+            time_ranges: vec![TimeRange { start: 1.0, end: Some(3.0) }],
+            ..Default::default()
+        };
+        assert_eq!(sample(in_range, 100), vec![20]);
+    }
+
+    #[test]
+    fn keyframes_follow_source_flags() {
+        let config = SplitConfig {
+            sampling: SamplingMode::Keyframes,
+            ..Default::default()
+        };
+        assert_eq!(sample(config.clone(), 12), vec![0, 5, 10]);
+
+        let mut info = VideoInfo {
+            format: VideoFormat::Y4m,
+            width: 64,
+            height: 48,
+            fps: 10.0,
+            frame_count: Some(100),
+            intra_only: true,
+        };
+        assert!(check_source(&config, &info).is_err());
+        assert!(check_source(&SplitConfig::default(), &info).is_ok());
+        info.intra_only = false;
+        assert!(check_source(&config, &info).is_ok());
+    }
+
+    #[test]
+    fn raw_image_formats_rejected() {
+        let config = SplitConfig {
+            image_format: "yuv".to_string(),
+            ..Default::default()
+        };
+        assert!(validate(&config).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! 多帧叠加降噪模块
+//!
+//! 对连续采集的多帧图像做均值或中值叠加，得到低噪声的单张静态图像，
+//! 可选对各帧做简单的全局平移对齐。结果以16位精度输出，保留叠加带来的
+//! 额外色阶。
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// 单次叠加允许的最大帧数
+pub const MAX_STACK_FRAMES: u32 = 64;
+
+/// 叠加方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackMode {
+    /// 均值叠加，降噪效果最好
+    Mean,
+    /// 中值叠加，可以去除偶发的亮点和运动物体
+    Median,
+}
+
+/// 叠加选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackOptions {
+    /// 叠加帧数 (2-64)
+    pub frames: u32,
+
+    /// 叠加方式
+    pub mode: StackMode,
+
+    /// 是否对齐各帧
+    pub align: bool,
+
+    /// 对齐时允许的最大平移像素数
+    pub max_shift: u32,
+}
+
+impl Default for StackOptions {
+    fn default() -> Self {
+        Self {
+            frames: 8,
+            mode: StackMode::Mean,
+            align: false,
+            max_shift: 16,
+        }
+    }
+}
+
+impl StackOptions {
+    /// 校验叠加选项
+    pub fn validate(&self) -> Result<()> {
+        if self.frames < 2 || self.frames > MAX_STACK_FRAMES {
+            return Err(Error::Config(format!(
+                "叠加帧数必须在 2-{} 之间: {}", MAX_STACK_FRAMES, self.frames
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// 16位RGB图像
+pub type Rgb16Image = image::ImageBuffer<image::Rgb<u16>, Vec<u16>>;
+
+/// 叠加多帧图像
+///
+/// 第一帧作为参考帧，所有帧尺寸必须一致。开启对齐时，各帧相对参考帧的
+/// 平移量由亮度的行列投影估计，平移后超出画面的区域只使用有效帧参与计算。
+pub fn stack_frames(frames: &[image::RgbImage], options: &StackOptions) -> Result<Rgb16Image> {
+    let reference = frames.first()
+        .ok_or_else(|| Error::Image("没有可叠加的帧".to_string()))?;
+
+    let width = reference.width();
+    let height = reference.height();
+
+    if frames.iter().any(|f| f.width() != width || f.height() != height) {
+        return Err(Error::Image("叠加的帧尺寸不一致".to_string()));
+    }
+
+    // 估计每帧相对参考帧的平移
+    let offsets: Vec<(i32, i32)> = if options.align {
+        let (ref_cols, ref_rows) = luma_projections(reference);
+        frames.iter()
+            .map(|frame| {
+                let (cols, rows) = luma_projections(frame);
+                (
+                    best_shift(&ref_cols, &cols, options.max_shift),
+                    best_shift(&ref_rows, &rows, options.max_shift),
+                )
+            })
+            .collect()
+    } else {
+        vec![(0, 0); frames.len()]
+    };
+
+    let mut output = Rgb16Image::new(width, height);
+    let mut samples: Vec<u8> = Vec::with_capacity(frames.len());
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut pixel = [0u16; 3];
+
+            for (channel, value) in pixel.iter_mut().enumerate() {
+                samples.clear();
+                for (frame, (dx, dy)) in frames.iter().zip(&offsets) {
+                    let sx = x as i32 + dx;
+                    let sy = y as i32 + dy;
+                    if sx >= 0 && sy >= 0 && (sx as u32) < width && (sy as u32) < height {
+                        samples.push(frame.get_pixel(sx as u32, sy as u32)[channel]);
+                    }
+                }
+
+                *value = match options.mode {
+                    StackMode::Mean => mean_to_u16(&samples),
+                    StackMode::Median => median_to_u16(&mut samples),
+                };
+            }
+
+            output.put_pixel(x, y, image::Rgb(pixel));
+        }
+    }
+
+    Ok(output)
+}
+
+/// 计算亮度的列投影和行投影（各列、各行的平均亮度）
+fn luma_projections(frame: &image::RgbImage) -> (Vec<f32>, Vec<f32>) {
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let mut cols = vec![0f32; width];
+    let mut rows = vec![0f32; height];
+
+    for (x, y, pixel) in frame.enumerate_pixels() {
+        let luma = 0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32;
+        cols[x as usize] += luma;
+        rows[y as usize] += luma;
+    }
+
+    cols.iter_mut().for_each(|v| *v /= height as f32);
+    rows.iter_mut().for_each(|v| *v /= width as f32);
+
+    (cols, rows)
+}
+
+/// 在 ±max_shift 范围内搜索使两条投影差异最小的平移量
+///
+/// 返回值表示目标帧中对应参考帧位置 i 的采样点为 i + shift。
+fn best_shift(reference: &[f32], target: &[f32], max_shift: u32) -> i32 {
+    let len = reference.len() as i32;
+    let max_shift = (max_shift as i32).min(len / 4);
+
+    let mut best = 0;
+    let mut best_cost = f32::MAX;
+
+    for shift in -max_shift..=max_shift {
+        let mut cost = 0.0;
+        let mut count = 0;
+        for i in 0..len {
+            let j = i + shift;
+            if j >= 0 && j < len {
+                cost += (reference[i as usize] - target[j as usize]).abs();
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            let cost = cost / count as f32;
+            if cost < best_cost {
+                best_cost = cost;
+                best = shift;
+            }
+        }
+    }
+
+    best
+}
+
+/// 8位样本的均值，映射到16位范围
+fn mean_to_u16(samples: &[u8]) -> u16 {
+    if samples.is_empty() {
+        return 0;
+    }
+
+    let sum: u32 = samples.iter().map(|&v| v as u32).sum();
+    let count = samples.len() as u32;
+    ((sum * 257 + count / 2) / count) as u16
+}
+
+/// 8位样本的中值，映射到16位范围
+fn median_to_u16(samples: &mut [u8]) -> u16 {
+    if samples.is_empty() {
+        return 0;
+    }
+
+    samples.sort_unstable();
+    let mid = samples.len() / 2;
+
+    if samples.len().is_multiple_of(2) {
+        let sum = samples[mid - 1] as u32 + samples[mid] as u32;
+        (sum * 257).div_ceil(2) as u16
+    } else {
+        samples[mid] as u16 * 257
+    }
+}
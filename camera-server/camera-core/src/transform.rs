@@ -0,0 +1,143 @@
+//! 拆分图片变换模块
+//!
+//! 拆分时在编码前对画面依次做裁剪、缩放和灰度转换，直接输出机器学习流程需要的
+//! 尺寸（如 640x640、224x224），不必再对大量原尺寸图片做第二遍处理。
+
+use crate::{Error, Result};
+use crate::config::{CropRect, FrameTransform, ResizeMode};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// 输出尺寸上限（像素）
+const MAX_OUTPUT_SIZE: u32 = 8192;
+
+/// 缩放使用的插值算法
+const RESIZE_FILTER: FilterType = FilterType::CatmullRom;
+
+/// 校验变换配置中与画面尺寸无关的部分
+pub fn validate(transform: &FrameTransform) -> Result<()> {
+    if let Some(crop) = &transform.crop {
+        if crop.width == 0 || crop.height == 0 {
+            return Err(Error::Config(format!("裁剪区域不能为空: {}x{}", crop.width, crop.height)));
+        }
+    }
+
+    match transform.resize {
+        Some(ResizeMode::Fit { max }) if !(1..=MAX_OUTPUT_SIZE).contains(&max) => {
+            return Err(Error::Config(format!("缩放最长边必须在 1-{} 范围内: {}", MAX_OUTPUT_SIZE, max)));
+        }
+        Some(ResizeMode::Letterbox { width, height, .. })
+            if !(1..=MAX_OUTPUT_SIZE).contains(&width) || !(1..=MAX_OUTPUT_SIZE).contains(&height) =>
+        {
+            return Err(Error::Config(format!(
+                "输出尺寸必须在 1-{} 范围内: {}x{}", MAX_OUTPUT_SIZE, width, height
+            )));
+        }
+        Some(ResizeMode::Scale { factor }) if !factor.is_finite() || factor <= 0.0 || factor > 4.0 => {
+            return Err(Error::Config(format!("缩放比例必须在 0-4 范围内: {}", factor)));
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// 检查变换能否用于指定尺寸的画面，返回输出尺寸
+pub fn output_size(transform: &FrameTransform, width: u32, height: u32) -> Result<(u32, u32)> {
+    let (width, height) = match &transform.crop {
+        Some(crop) => {
+            if crop.x as u64 + crop.width as u64 > width as u64 || crop.y as u64 + crop.height as u64 > height as u64 {
+                return Err(Error::Config(format!(
+                    "裁剪区域 {}x{}+{}+{} 超出画面 {}x{}",
+                    crop.width, crop.height, crop.x, crop.y, width, height
+                )));
+            }
+            (crop.width, crop.height)
+        }
+        None => (width, height),
+    };
+
+    let size = match transform.resize {
+        Some(ResizeMode::Fit { max }) => fit(width, height, max, max),
+        Some(ResizeMode::Letterbox { width, height, .. }) => (width, height),
+        Some(ResizeMode::Scale { factor }) => (
+            ((width as f32 * factor).round() as u32).max(1),
+            ((height as f32 * factor).round() as u32).max(1),
+        ),
+        None => (width, height),
+    };
+
+    if size.0 > MAX_OUTPUT_SIZE || size.1 > MAX_OUTPUT_SIZE {
+        return Err(Error::Config(format!("输出尺寸过大: {}x{}", size.0, size.1)));
+    }
+
+    Ok(size)
+}
+
+/// 对画面做变换，需先用 `output_size` 检查过画面尺寸
+pub fn apply(image: &RgbImage, transform: &FrameTransform) -> DynamicImage {
+    let cropped = match &transform.crop {
+        Some(CropRect { x, y, width, height }) => {
+            image::imageops::crop_imm(image, *x, *y, *width, *height).to_image()
+        }
+        None => image.clone(),
+    };
+
+    let resized = match transform.resize {
+        Some(ResizeMode::Fit { max }) => {
+            let (width, height) = fit(cropped.width(), cropped.height(), max, max);
+            resize(cropped, width, height)
+        }
+        Some(ResizeMode::Letterbox { width, height, fill }) => {
+            let (inner_width, inner_height) = scale_into(cropped.width(), cropped.height(), width, height);
+            let inner = resize(cropped, inner_width, inner_height);
+            let mut canvas = RgbImage::from_pixel(width, height, Rgb(fill));
+            image::imageops::replace(
+                &mut canvas,
+                &inner,
+                ((width - inner_width) / 2) as i64,
+                ((height - inner_height) / 2) as i64,
+            );
+            canvas
+        }
+        Some(ResizeMode::Scale { factor }) => {
+            let width = ((cropped.width() as f32 * factor).round() as u32).max(1);
+            let height = ((cropped.height() as f32 * factor).round() as u32).max(1);
+            resize(cropped, width, height)
+        }
+        None => cropped,
+    };
+
+    if transform.grayscale {
+        DynamicImage::ImageLuma8(DynamicImage::ImageRgb8(resized).to_luma8())
+    } else {
+        DynamicImage::ImageRgb8(resized)
+    }
+}
+
+/// 尺寸不变时不做缩放
+fn resize(image: RgbImage, width: u32, height: u32) -> RgbImage {
+    if image.dimensions() == (width, height) {
+        image
+    } else {
+        image::imageops::resize(&image, width, height, RESIZE_FILTER)
+    }
+}
+
+/// 等比缩放到 `max_width` x `max_height` 以内，不放大
+fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    if width <= max_width && height <= max_height {
+        (width, height)
+    } else {
+        scale_into(width, height, max_width, max_height)
+    }
+}
+
+/// 等比缩放到恰好放入 `max_width` x `max_height`，可能放大
+fn scale_into(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let ratio = (max_width as f64 / width.max(1) as f64).min(max_height as f64 / height.max(1) as f64);
+    (
+        ((width as f64 * ratio).round() as u32).clamp(1, max_width),
+        ((height as f64 * ratio).round() as u32).clamp(1, max_height),
+    )
+}
@@ -0,0 +1,326 @@
+//! 事件触发录制模块
+//!
+//! 平时只把画面缓存在预录缓冲区中，收到事件（运动检测、HTTP 请求、外部命令）
+//! 后开始录制，先写入缓冲的预录画面，再持续录制到最后一次事件之后的后录时间。
+//! 后录期间再次发生的事件只会延长当前录制，不会产生新的文件。
+//!
+//! 预录缓冲区中的画面以 JPEG 压缩保存，1080p 下每帧只占几百 KB，
+//! 避免在内存有限的开发板上缓存数秒原始 RGB 画面。
+
+use crate::{Error, Result, config::{EventRecordingConfig, MotionConfig}};
+use crate::video::VideoRecorder;
+use image::ImageEncoder;
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// 预录画面的 JPEG 质量
+const PRE_ROLL_QUALITY: u8 = 90;
+
+/// 运动检测前将画面缩小到的宽度
+const MOTION_WIDTH: u32 = 64;
+
+/// 检查触发文件的间隔
+const TRIGGER_FILE_POLL: Duration = Duration::from_millis(500);
+
+/// 录制触发来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerSource {
+    /// 运动检测
+    Motion,
+    /// HTTP 接口触发
+    Http,
+    /// 外部命令（触发文件）
+    Command,
+    /// 定时录制计划
+    Schedule,
+}
+
+impl TriggerSource {
+    /// 触发来源名称，用于文件名和日志
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerSource::Motion => "motion",
+            TriggerSource::Http => "http",
+            TriggerSource::Command => "command",
+            TriggerSource::Schedule => "schedule",
+        }
+    }
+}
+
+/// 触发句柄，可以在其他线程或任务中发送事件
+#[derive(Debug, Clone)]
+pub struct TriggerHandle {
+    sender: Sender<TriggerSource>,
+}
+
+impl TriggerHandle {
+    /// 发送一次触发事件
+    pub fn fire(&self, source: TriggerSource) {
+        // 接收端随录制任务结束时丢弃事件即可
+        let _ = self.sender.send(source);
+    }
+}
+
+/// 预录缓冲区
+struct PreRollBuffer {
+    /// 缓冲时长
+    duration: Duration,
+    /// 按时间顺序排列的 JPEG 画面
+    frames: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl PreRollBuffer {
+    fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// 压缩并缓存一帧，丢弃超出预录时长的旧画面
+    fn push(&mut self, frame: &image::RgbImage) -> Result<()> {
+        if self.duration.is_zero() {
+            return Ok(());
+        }
+
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, PRE_ROLL_QUALITY)
+            .write_image(frame.as_raw(), frame.width(), frame.height(), image::ColorType::Rgb8)
+            .map_err(|e| Error::Image(format!("预录画面编码失败: {}", e)))?;
+
+        let now = Instant::now();
+        self.frames.push_back((now, jpeg));
+        while let Some((time, _)) = self.frames.front() {
+            if now.duration_since(*time) > self.duration {
+                self.frames.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 取出全部缓存画面
+    fn drain(&mut self) -> Vec<Vec<u8>> {
+        self.frames.drain(..).map(|(_, jpeg)| jpeg).collect()
+    }
+}
+
+/// 基于帧差的运动检测器
+pub struct MotionDetector {
+    /// 检测配置
+    config: MotionConfig,
+    /// 上一帧缩小后的亮度图
+    previous: Option<image::GrayImage>,
+}
+
+impl MotionDetector {
+    /// 创建运动检测器
+    pub fn new(config: MotionConfig) -> Self {
+        Self {
+            config,
+            previous: None,
+        }
+    }
+
+    /// 与上一帧比较，返回是否检测到运动
+    pub fn detect(&mut self, frame: &image::RgbImage) -> bool {
+        let height = (frame.height() as u64 * MOTION_WIDTH as u64 / frame.width().max(1) as u64).max(1) as u32;
+        let small = image::imageops::thumbnail(frame, MOTION_WIDTH, height);
+        let luma = image::DynamicImage::ImageRgb8(small).to_luma8();
+
+        let motion = match &self.previous {
+            Some(previous) if previous.dimensions() == luma.dimensions() => {
+                let changed = previous.as_raw().iter()
+                    .zip(luma.as_raw())
+                    .filter(|(a, b)| a.abs_diff(**b) > self.config.pixel_threshold)
+                    .count();
+                changed as f32 / luma.as_raw().len() as f32 >= self.config.min_area
+            }
+            _ => false,
+        };
+
+        self.previous = Some(luma);
+        motion
+    }
+}
+
+/// 事件触发录制控制器
+///
+/// 由录制帧泵逐帧调用 `process_frame`，根据事件自动开始和结束录制。
+pub struct EventTrigger {
+    /// 配置
+    config: EventRecordingConfig,
+    /// 预录缓冲区
+    buffer: PreRollBuffer,
+    /// 运动检测器
+    motion: Option<MotionDetector>,
+    /// 事件发送端，用于创建触发句柄
+    sender: Sender<TriggerSource>,
+    /// 事件接收端
+    receiver: Receiver<TriggerSource>,
+    /// 当前录制是否由事件触发开始
+    active: bool,
+    /// 后录结束时间
+    deadline: Option<Instant>,
+    /// 上次检查触发文件的时间
+    last_file_poll: Option<Instant>,
+}
+
+impl EventTrigger {
+    /// 创建事件触发录制控制器
+    pub fn new(config: EventRecordingConfig) -> Result<Self> {
+        Self::validate(&config)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let motion = config.motion.enabled
+            .then(|| MotionDetector::new(config.motion.clone()));
+
+        Ok(Self {
+            buffer: PreRollBuffer::new(Duration::from_secs_f32(config.pre_roll_secs)),
+            motion,
+            sender,
+            receiver,
+            active: false,
+            deadline: None,
+            last_file_poll: None,
+            config,
+        })
+    }
+
+    /// 校验事件触发录制配置
+    pub fn validate(config: &EventRecordingConfig) -> Result<()> {
+        for (name, value) in [("预录时长", config.pre_roll_secs), ("后录时长", config.post_roll_secs)] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(Error::Config(format!("{}必须为非负数: {}", name, value)));
+            }
+        }
+
+        if !(config.motion.min_area > 0.0 && config.motion.min_area <= 1.0) {
+            return Err(Error::Config(format!(
+                "运动检测面积比例必须在 (0, 1] 范围内: {}", config.motion.min_area
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// 获取触发句柄
+    pub fn handle(&self) -> TriggerHandle {
+        TriggerHandle {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// 当前录制是否由事件触发
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// 收集本帧之前发生的事件
+    fn collect_events(&mut self, frame: &image::RgbImage) -> Vec<TriggerSource> {
+        let mut events: Vec<TriggerSource> = self.receiver.try_iter().collect();
+
+        if let Some(detector) = &mut self.motion {
+            if detector.detect(frame) {
+                events.push(TriggerSource::Motion);
+            }
+        }
+
+        if let Some(path) = &self.config.trigger_file {
+            let due = self.last_file_poll
+                .map(|t| t.elapsed() >= TRIGGER_FILE_POLL)
+                .unwrap_or(true);
+            if due {
+                self.last_file_poll = Some(Instant::now());
+                let path = PathBuf::from(path);
+                if path.exists() {
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        warn!("删除触发文件失败: {}: {}", path.display(), e);
+                    }
+                    events.push(TriggerSource::Command);
+                }
+            }
+        }
+
+        events
+    }
+
+    /// 处理一帧画面
+    ///
+    /// 没有录制时缓存为预录画面；有事件时开始或延长录制；
+    /// 后录时间结束后停止录制。手动或定时录制进行中时只写入画面并记录事件来源。
+    pub fn process_frame(&mut self, recorder: &mut VideoRecorder, frame: &image::RgbImage) -> Result<()> {
+        let events = self.collect_events(frame);
+        let now = Instant::now();
+
+        if !events.is_empty() {
+            if !self.active && !recorder.state().is_active() {
+                self.start(recorder, &events)?;
+            } else {
+                for source in &events {
+                    recorder.add_trigger(*source);
+                }
+            }
+
+            if self.active {
+                self.deadline = Some(now + Duration::from_secs_f32(self.config.post_roll_secs));
+            }
+        }
+
+        // 事件录制被外部停止（如手动停止或录制失败）
+        if self.active && !recorder.state().is_active() {
+            info!("事件录制已被外部结束");
+            self.active = false;
+            self.deadline = None;
+        }
+
+        if !recorder.state().is_active() {
+            return self.buffer.push(frame);
+        }
+
+        if recorder.is_recording() {
+            recorder.write_frame(frame)?;
+        }
+
+        if self.active && self.deadline.is_some_and(|deadline| now >= deadline) {
+            self.active = false;
+            self.deadline = None;
+            let summary = recorder.stop_recording()?;
+            info!("事件录制结束: {:?}, 共 {} 帧", summary.path, summary.frames);
+        }
+
+        Ok(())
+    }
+
+    /// 开始事件录制并写入预录画面
+    fn start(&mut self, recorder: &mut VideoRecorder, events: &[TriggerSource]) -> Result<()> {
+        let path = recorder.start_triggered(events[0])?;
+        for source in events {
+            recorder.add_trigger(*source);
+        }
+        self.active = true;
+
+        let pre_roll = self.buffer.drain();
+        info!("事件触发录制 {:?}: {}, 预录 {} 帧", events, path.display(), pre_roll.len());
+
+        for jpeg in pre_roll {
+            let image = match image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg) {
+                Ok(image) => image.to_rgb8(),
+                Err(e) => {
+                    error!("解码预录画面失败: {}", e);
+                    continue;
+                }
+            };
+            recorder.write_frame(&image)?;
+        }
+
+        Ok(())
+    }
+}
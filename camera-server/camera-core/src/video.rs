@@ -1,76 +1,677 @@
 //! 视频处理模块
 
-use crate::{Error, Result, config::{RecordingConfig, SplitConfig}};
-use log::{info, error};
+use crate::{Error, Result, config::{CameraConfig, RecordingConfig, SplitConfig}};
+use crate::encoder::{Encoder, EncoderRegistry, EncoderSettings, StreamParams};
+use crate::guard::{StorageGuard, StorageJob};
+use crate::metadata::RecordingMetadata;
+use crate::naming::{self, NamingContext};
+use crate::preview::{self, PreviewCollector};
+use crate::source::{open_video, probe_video};
+use crate::split::{self, SplitQueueFile, SplitState, SplitTask, SplitTaskStatus};
+use crate::trigger::TriggerSource;
+use chrono::{DateTime, Local};
+use log::{info, warn, error, debug};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 录制过程中检查存储空间的间隔
+const STORAGE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 录制器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecorderState {
+    /// 空闲
+    Idle,
+    /// 正在准备录制
+    Starting,
+    /// 录制中
+    Recording,
+    /// 已暂停，恢复后继续写入同一文件
+    Paused,
+    /// 正在结束录制并写出文件
+    Finalizing,
+    /// 录制失败，可以重新开始录制，或停止录制以清除失败状态
+    Failed,
+}
+
+impl RecorderState {
+    /// 是否允许切换到目标状态
+    pub fn can_transition_to(self, next: RecorderState) -> bool {
+        use RecorderState::*;
+        matches!(
+            (self, next),
+            (Idle, Starting)
+                | (Failed, Starting)
+                | (Starting, Recording)
+                | (Starting, Failed)
+                | (Recording, Paused)
+                | (Paused, Recording)
+                | (Recording, Finalizing)
+                | (Paused, Finalizing)
+                | (Recording, Failed)
+                | (Finalizing, Idle)
+                | (Finalizing, Failed)
+                | (Failed, Finalizing)
+        )
+    }
+
+    /// 是否有尚未结束的录制（录制中或已暂停）
+    pub fn is_active(self) -> bool {
+        matches!(self, RecorderState::Recording | RecorderState::Paused)
+    }
+}
+
+/// 录制结束后的汇总信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingSummary {
+    /// 最后一个录制文件的路径
+    pub path: Option<PathBuf>,
+    /// 本次录制产生的所有文件
+    pub segments: Vec<PathBuf>,
+    /// 录制时长（秒），不含暂停时间
+    pub duration_secs: f64,
+    /// 写入的帧数
+    pub frames: u64,
+    /// 检测到的丢帧数
+    pub dropped_frames: u64,
+}
+
+/// 录制器状态快照，供 API 返回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecorderStatus {
+    /// 当前状态
+    pub state: RecorderState,
+    /// 是否正在写入帧
+    pub recording: bool,
+    /// 当前录制文件
+    pub current_file: Option<PathBuf>,
+    /// 当前分段序号
+    pub segment: u32,
+    /// 本次录制已写入帧数
+    pub frame_count: u64,
+    /// 本次录制检测到的丢帧数
+    pub dropped_frames: u64,
+    /// 本次录制时长（秒），不含暂停时间
+    pub duration_secs: f64,
+    /// 最近一次失败的原因
+    pub last_error: Option<String>,
+}
+
+/// 当前分段的统计信息，用于生成附属元数据
+struct SegmentStats {
+    /// 分段开始的墙上时间
+    started_at: DateTime<Local>,
+    /// 分段开始的单调时间
+    started: Instant,
+    /// 视频流参数
+    stream: StreamParams,
+    /// 编码器实际使用的参数
+    encoding: EncoderSettings,
+    /// 分段内的丢帧数
+    dropped_frames: u64,
+    /// 分段内累计的暂停时长
+    paused: Duration,
+    /// 封面图和缩略图采集，未启用时为 None
+    preview: Option<PreviewCollector>,
+}
 
 /// 视频录制器
 pub struct VideoRecorder {
     /// 录制配置
     config: RecordingConfig,
 
-    /// 是否正在录制
-    recording: bool,
+    /// 编码后端注册表
+    registry: Arc<EncoderRegistry>,
+
+    /// 录制状态
+    state: RecorderState,
+
+    /// 最近一次失败的原因
+    last_error: Option<String>,
 
     /// 当前录制文件路径
     current_file: Option<PathBuf>,
 
-    // 这里将来会添加 FFmpeg 相关的字段
+    /// 当前文件的编码器，收到第一帧后根据帧尺寸创建
+    encoder: Option<Box<dyn Encoder>>,
+
+    /// 本次录制已写入帧数
+    frame_count: u64,
+
+    /// 本次录制的开始时间，用于生成分段文件名和日期子目录
+    session_time: DateTime<Local>,
+
+    /// 当前分段序号（从1开始）
+    segment_index: u32,
+
+    /// 本次录制中已完成的分段文件，停止录制后包含最后一个分段
+    finished_segments: Vec<PathBuf>,
+
+    /// 摄像头标识，写入元数据
+    camera_id: String,
+
+    /// 摄像头配置，写入元数据
+    camera_config: Option<CameraConfig>,
+
+    /// 当前分段的统计信息
+    segment_stats: Option<SegmentStats>,
+
+    /// 上一帧的写入时间，用于检测丢帧
+    last_frame_at: Option<Instant>,
+
+    /// 本次录制的丢帧数
+    dropped_frames: u64,
+
+    /// 本次录制的触发来源
+    triggers: Vec<TriggerSource>,
+
+    /// 本次录制的开始时间
+    session_started: Option<Instant>,
+
+    /// 本次录制累计的暂停时长
+    paused_total: Duration,
+
+    /// 当前暂停的开始时间
+    paused_at: Option<Instant>,
+
+    /// 存储空间检查，未设置时不检查
+    storage_guard: Option<Arc<dyn StorageGuard>>,
+
+    /// 上次检查存储空间的时间
+    last_storage_check: Option<Instant>,
 }
 
 impl VideoRecorder {
-    /// 创建新的视频录制器
+    /// 创建新的视频录制器，使用内置的编码后端
     pub fn new(config: RecordingConfig) -> Self {
+        Self::with_registry(config, Arc::new(EncoderRegistry::with_defaults()))
+    }
+
+    /// 使用指定的编码后端注册表创建视频录制器
+    pub fn with_registry(config: RecordingConfig, registry: Arc<EncoderRegistry>) -> Self {
         Self {
             config,
-            recording: false,
+            registry,
+            state: RecorderState::Idle,
+            last_error: None,
             current_file: None,
+            encoder: None,
+            frame_count: 0,
+            session_time: Local::now(),
+            segment_index: 0,
+            finished_segments: Vec::new(),
+            camera_id: "unknown".to_string(),
+            camera_config: None,
+            segment_stats: None,
+            last_frame_at: None,
+            dropped_frames: 0,
+            triggers: Vec::new(),
+            session_started: None,
+            paused_total: Duration::ZERO,
+            paused_at: None,
+            storage_guard: None,
+            last_storage_check: None,
+        }
+    }
+
+    /// 设置录制所用摄像头的信息，写入每个录制文件的元数据
+    pub fn set_camera(&mut self, camera: &CameraConfig) {
+        self.camera_id = camera.camera_id();
+        self.camera_config = Some(camera.clone());
+    }
+
+    /// 设置存储空间检查，开始录制前和录制过程中定期检查
+    pub fn set_storage_guard(&mut self, guard: Arc<dyn StorageGuard>) {
+        self.storage_guard = Some(guard);
+    }
+
+    /// 检查录制目录的存储空间
+    fn check_storage(&mut self) -> Result<()> {
+        if let Some(guard) = &self.storage_guard {
+            self.last_storage_check = Some(Instant::now());
+            guard.check(StorageJob::Recording, Path::new(&self.config.output_dir), 0)?;
+        }
+        Ok(())
+    }
+
+    /// 切换录制状态，不允许的切换返回错误
+    fn transition(&mut self, next: RecorderState) -> Result<()> {
+        if !self.state.can_transition_to(next) {
+            return Err(Error::VideoProcessing(format!(
+                "录制状态无法从 {:?} 切换到 {:?}", self.state, next
+            )));
+        }
+
+        debug!("录制状态: {:?} -> {:?}", self.state, next);
+        self.state = next;
+        Ok(())
+    }
+
+    /// 录制出错时尽量写完当前文件并进入失败状态
+    fn fail(&mut self, err: &Error) {
+        error!("录制失败: {}", err);
+        if let Err(e) = self.finish_segment() {
+            error!("结束录制文件失败: {}", e);
         }
+        self.last_error = Some(err.to_string());
+        self.state = RecorderState::Failed;
     }
 
     /// 开始录制
     pub fn start_recording(&mut self) -> Result<PathBuf> {
-        if self.recording {
-            if let Some(path) = &self.current_file {
-                return Ok(path.clone());
-            }
+        self.start_session(None)
+    }
+
+    /// 由事件或计划触发开始录制，触发来源会用于文件名中的 `{trigger}` 占位符
+    pub fn start_triggered(&mut self, source: TriggerSource) -> Result<PathBuf> {
+        self.start_session(Some(source))
+    }
+
+    /// 开始新的录制会话，已有录制（包括暂停中的录制）时返回错误
+    fn start_session(&mut self, trigger: Option<TriggerSource>) -> Result<PathBuf> {
+        if self.state.is_active() {
+            return Err(Error::VideoProcessing(format!(
+                "已经在录制中: {}",
+                self.current_file.as_deref().unwrap_or(Path::new("")).display()
+            )));
         }
 
-        // 创建输出目录
-        std::fs::create_dir_all(&self.config.output_dir)?;
+        self.transition(RecorderState::Starting)?;
 
-        // 生成输出文件名
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("video_{}.{}", timestamp, self.config.container);
-        let output_path = Path::new(&self.config.output_dir).join(filename);
+        self.triggers = trigger.into_iter().collect();
 
-        // 这里将来会添加 FFmpeg 初始化和开始录制的代码
+        let output_path = match self.prepare_session() {
+            Ok(path) => path,
+            Err(e) => {
+                self.fail(&e);
+                return Err(e);
+            }
+        };
 
         info!("开始录制视频: {}", output_path.display());
-        self.recording = true;
         self.current_file = Some(output_path.clone());
+        self.frame_count = 0;
+        self.dropped_frames = 0;
+        self.last_frame_at = None;
+        self.session_started = Some(Instant::now());
+        self.paused_total = Duration::ZERO;
+        self.paused_at = None;
+        self.last_error = None;
+        self.transition(RecorderState::Recording)?;
+
+        Ok(output_path)
+    }
+
+    /// 校验配置并准备输出目录和分段信息，返回第一个分段的文件路径
+    fn prepare_session(&mut self) -> Result<PathBuf> {
+        // 确认录制配置可用
+        self.validate_config(&self.config)?;
+
+        // 空间不足时拒绝开始录制
+        self.check_storage()?;
+
+        self.session_time = Local::now();
+        self.segment_index = 1;
+        self.finished_segments.clear();
+
+        // 创建输出目录（含日期子目录）
+        let output_path = self.segment_path()?;
+        if let Some(dir) = output_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
 
         Ok(output_path)
     }
 
-    /// 停止录制
-    pub fn stop_recording(&mut self) -> Result<Option<PathBuf>> {
-        if !self.recording {
-            return Ok(None);
+    /// 记录触发本次录制的事件来源，写入元数据
+    pub fn add_trigger(&mut self, source: TriggerSource) {
+        if !self.triggers.contains(&source) {
+            self.triggers.push(source);
+        }
+    }
+
+    /// 暂停录制，恢复后继续写入同一文件
+    pub fn pause(&mut self) -> Result<()> {
+        self.transition(RecorderState::Paused)?;
+        self.paused_at = Some(Instant::now());
+        info!("录制已暂停");
+        Ok(())
+    }
+
+    /// 恢复录制
+    pub fn resume(&mut self) -> Result<()> {
+        self.transition(RecorderState::Recording)?;
+        self.end_pause();
+        info!("录制已恢复");
+        Ok(())
+    }
+
+    /// 结束当前暂停，累计暂停时长
+    fn end_pause(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused = paused_at.elapsed();
+            self.paused_total += paused;
+            if let Some(stats) = &mut self.segment_stats {
+                stats.paused += paused;
+            }
+        }
+        // 暂停期间没有写入帧，不计入丢帧
+        self.last_frame_at = None;
+    }
+
+    /// 本次录制的时长，不含暂停时间
+    fn active_duration(&self) -> Duration {
+        let elapsed = match self.session_started {
+            Some(started) => started.elapsed(),
+            None => return Duration::ZERO,
+        };
+        let paused = self.paused_total + self.paused_at.map(|t| t.elapsed()).unwrap_or_default();
+        elapsed.saturating_sub(paused)
+    }
+
+    /// 按文件名模板生成当前分段的输出文件路径
+    ///
+    /// 启用分段或因容器大小上限切换过文件，而模板中没有 `{seq}` 时，文件名末尾
+    /// 自动追加三位分段序号，如 `video_20250513_120000_001.avi`。
+    /// 已存在同名文件时追加后缀避免覆盖。
+    fn segment_path(&self) -> Result<PathBuf> {
+        let template = self.config.filename_template()?;
+        // 未配置分段时只有因容器大小上限切换到第二个文件后才加序号，第一个文件名不变
+        let segmented = self.config.max_segment_duration.is_some()
+            || self.config.max_segment_bytes.is_some()
+            || self.segment_index > 1;
+
+        let context = NamingContext {
+            camera: Some(&self.camera_id),
+            seq: Some(self.segment_index),
+            trigger: self.triggers.first().copied(),
+            ..NamingContext::new(self.session_time)
+        };
+
+        let mut name = template.render(&context);
+        if segmented && !template.has_seq() {
+            name.push_str(&format!("_{:03}", self.segment_index));
+        }
+
+        let dir = naming::dated_dir(Path::new(&self.config.output_dir), &self.session_time, self.config.date_subdirs);
+        Ok(naming::unique_path(dir.join(format!("{}.{}", name, self.config.container))))
+    }
+
+    /// 当前分段是否已达到时长或大小上限
+    ///
+    /// 未配置分段时，容器格式接近自身的大小上限（如 AVI 的 4GB）也会切换到新文件。
+    fn segment_full(&self, encoder: &dyn Encoder) -> bool {
+        if encoder.near_size_limit() {
+            return true;
+        }
+
+        if let Some(max_duration) = self.config.max_segment_duration {
+            // 按帧数计算时长，与视频时间轴保持一致
+            let max_frames = max_duration.saturating_mul(self.config.fps.max(1) as u64);
+            if encoder.frame_count() >= max_frames {
+                return true;
+            }
+        }
+
+        if let Some(max_bytes) = self.config.max_segment_bytes {
+            if encoder.bytes_written() >= max_bytes {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// 结束当前分段并切换到下一个分段文件
+    ///
+    /// 新分段的编码器在写入下一帧时创建，因此切换过程不会丢帧。
+    fn roll_segment(&mut self) -> Result<()> {
+        self.finish_segment()?;
+
+        if let Some(path) = self.current_file.take() {
+            info!("录制分段已完成: {}", path.display());
+        }
+
+        self.segment_index += 1;
+        let next_path = self.segment_path()?;
+        info!("切换到新的录制分段: {}", next_path.display());
+        self.current_file = Some(next_path);
+
+        Ok(())
+    }
+
+    /// 结束当前分段的编码器，写出最终元数据并记入已完成分段
+    fn finish_segment(&mut self) -> Result<()> {
+        let encoder = match self.encoder.take() {
+            Some(encoder) => encoder,
+            None => return Ok(()),
+        };
+
+        let frames = encoder.frame_count();
+        let result = encoder.finish();
+
+        if let (Some(path), Some(stats)) = (self.current_file.clone(), self.segment_stats.take()) {
+            let mut metadata = self.build_metadata(&path, &stats, frames, Some(Local::now()));
+            if let Some(collector) = &stats.preview {
+                match collector.write(&path) {
+                    Ok(info) => metadata.preview = info,
+                    Err(e) => error!("写入录制预览图失败: {}: {}", path.display(), e),
+                }
+            }
+            if let Err(e) = metadata.save_for(&path) {
+                error!("写入录制元数据失败: {}: {}", path.display(), e);
+            }
+            self.finished_segments.push(path);
         }
 
-        // 这里将来会添加停止录制的代码
+        result
+    }
 
-        info!("停止录制视频");
-        let result = self.current_file.clone();
-        self.recording = false;
+    /// 生成当前分段的元数据
+    fn build_metadata(
+        &self,
+        path: &Path,
+        stats: &SegmentStats,
+        frame_count: u64,
+        ended_at: Option<DateTime<Local>>,
+    ) -> RecordingMetadata {
+        let duration_secs = stats.started.elapsed().saturating_sub(stats.paused).as_secs_f64();
+        let effective_fps = if duration_secs > 0.0 {
+            frame_count as f64 / duration_secs
+        } else {
+            0.0
+        };
 
-        Ok(result)
+        RecordingMetadata {
+            software_version: RecordingMetadata::software_version(),
+            camera_id: self.camera_id.clone(),
+            camera: self.camera_config.clone(),
+            recording: self.config.clone(),
+            encoding: Some(stats.encoding.clone()),
+            file_name: path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            segment: self.segment_index,
+            width: stats.stream.width,
+            height: stats.stream.height,
+            start_time: stats.started_at.to_rfc3339(),
+            end_time: ended_at.map(|t| t.to_rfc3339()),
+            duration_secs,
+            frame_count,
+            dropped_frames: stats.dropped_frames,
+            effective_fps,
+            triggers: self.triggers.clone(),
+            preview: None,
+        }
+    }
+
+    /// 根据与上一帧的间隔估算丢失的帧数
+    fn detect_dropped_frames(&mut self) -> u64 {
+        let now = Instant::now();
+        let missed = match self.last_frame_at {
+            Some(last) => {
+                let interval = 1.0 / self.config.fps.max(1) as f64;
+                let elapsed = now.duration_since(last).as_secs_f64();
+                // 间隔超过标称值的 1.5 倍才认为丢帧，避免调度抖动误报
+                if elapsed > interval * 1.5 {
+                    ((elapsed / interval).round() as u64).saturating_sub(1)
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+        self.last_frame_at = Some(now);
+        missed
+    }
+
+    /// 写入一帧图像
+    ///
+    /// 写入失败时会结束当前文件并进入失败状态。
+    pub fn write_frame(&mut self, frame: &image::RgbImage) -> Result<()> {
+        if self.state != RecorderState::Recording {
+            return Err(Error::VideoProcessing(format!(
+                "录制未在进行，当前状态: {:?}", self.state
+            )));
+        }
+
+        let result = self.write_frame_inner(frame);
+        if let Err(e) = &result {
+            self.fail(e);
+        }
+        result
+    }
+
+    /// 写入一帧图像，必要时切换分段和创建编码器
+    fn write_frame_inner(&mut self, frame: &image::RgbImage) -> Result<()> {
+        // 录制过程中定期检查存储空间，不足时由 write_frame 结束当前文件并进入失败状态
+        let storage_due = self.last_storage_check
+            .map(|t| t.elapsed() >= STORAGE_CHECK_INTERVAL)
+            .unwrap_or(true);
+        if storage_due {
+            self.check_storage()?;
+        }
+
+        let missed = self.detect_dropped_frames();
+
+        let segment_full = match &self.encoder {
+            Some(encoder) => self.segment_full(encoder.as_ref()),
+            None => false,
+        };
+        if segment_full {
+            self.roll_segment()?;
+        }
+
+        if self.encoder.is_none() {
+            let path = self.current_file.clone()
+                .ok_or_else(|| Error::VideoProcessing("录制文件路径未设置".to_string()))?;
+            let stream = StreamParams {
+                width: frame.width(),
+                height: frame.height(),
+                fps: self.config.fps,
+            };
+
+            let backend = self.registry.validate(&self.config)?;
+            let encoding = backend.settings(&self.config, &stream);
+            info!("使用编码后端 {}: {}x{} @ {}fps, {:?}",
+                backend.name(), stream.width, stream.height, stream.fps, encoding);
+            self.encoder = Some(backend.create(&path, &self.config, &stream)?);
+
+            let stats = SegmentStats {
+                started_at: Local::now(),
+                started: Instant::now(),
+                stream,
+                encoding,
+                dropped_frames: 0,
+                paused: Duration::ZERO,
+                preview: self.config.preview.enabled
+                    .then(|| PreviewCollector::new(self.config.preview.clone(), self.config.fps)),
+            };
+            // 先写一份未结束的元数据，异常中断时也能保留录制信息
+            let metadata = self.build_metadata(&path, &stats, 0, None);
+            if let Err(e) = metadata.save_for(&path) {
+                error!("写入录制元数据失败: {}: {}", path.display(), e);
+            }
+            self.segment_stats = Some(stats);
+        }
+
+        if let Some(stats) = &mut self.segment_stats {
+            stats.dropped_frames += missed;
+        }
+        self.dropped_frames += missed;
+
+        if let Some(encoder) = &mut self.encoder {
+            encoder.write_frame(frame)?;
+        }
+
+        // 预览图只是附加信息，采集失败不影响录制
+        if let Some(collector) = self.segment_stats.as_mut().and_then(|s| s.preview.as_mut()) {
+            if let Err(e) = collector.push(frame) {
+                error!("采集录制预览图失败: {}", e);
+            }
+        }
+
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// 停止录制，返回本次录制的汇总信息
+    ///
+    /// 录制失败后调用会清除失败状态，汇总信息为失败前已写入的内容。
+    pub fn stop_recording(&mut self) -> Result<RecordingSummary> {
+        self.transition(RecorderState::Finalizing)?;
+        self.end_pause();
+
+        let duration = self.active_duration();
+        let result = self.finish_segment();
+        self.session_started = None;
+
+        if let Err(e) = result {
+            self.fail(&e);
+            return Err(e);
+        }
+
+        let summary = RecordingSummary {
+            path: self.current_file.clone(),
+            segments: self.finished_segments.clone(),
+            duration_secs: duration.as_secs_f64(),
+            frames: self.frame_count,
+            dropped_frames: self.dropped_frames,
+        };
+
+        info!("停止录制视频，共 {} 帧，时长 {:.1} 秒，丢帧 {}",
+            summary.frames, summary.duration_secs, summary.dropped_frames);
+        self.transition(RecorderState::Idle)?;
+
+        Ok(summary)
+    }
+
+    /// 获取录制状态
+    pub fn state(&self) -> RecorderState {
+        self.state
+    }
+
+    /// 获取录制状态快照
+    pub fn status(&self) -> RecorderStatus {
+        RecorderStatus {
+            state: self.state,
+            recording: self.is_recording(),
+            current_file: self.current_file.clone(),
+            segment: self.segment_index,
+            frame_count: self.frame_count,
+            dropped_frames: self.dropped_frames,
+            duration_secs: self.active_duration().as_secs_f64(),
+            last_error: self.last_error.clone(),
+        }
     }
 
     /// 获取是否正在录制
     pub fn is_recording(&self) -> bool {
-        self.recording
+        self.state == RecorderState::Recording
     }
 
     /// 获取当前录制文件路径
@@ -78,27 +679,73 @@ impl VideoRecorder {
         self.current_file.as_ref()
     }
 
+    /// 获取本次录制已写入帧数
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// 获取本次录制中检测到的丢帧数
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+
+    /// 获取本次录制中已完成的分段文件
+    pub fn finished_segments(&self) -> &[PathBuf] {
+        &self.finished_segments
+    }
+
     /// 获取录制配置
     pub fn config(&self) -> &RecordingConfig {
         &self.config
     }
 
+    /// 获取编码后端注册表
+    pub fn registry(&self) -> &Arc<EncoderRegistry> {
+        &self.registry
+    }
+
     /// 设置录制配置
     pub fn set_config(&mut self, config: RecordingConfig) -> Result<()> {
-        if self.recording {
+        if !matches!(self.state, RecorderState::Idle | RecorderState::Failed) {
             return Err(Error::VideoProcessing(
                 "无法在录制过程中更改配置，请先停止录制".to_string()
             ));
         }
 
+        self.validate_config(&config)?;
+
         self.config = config;
         Ok(())
     }
+
+    /// 校验录制配置
+    fn validate_config(&self, config: &RecordingConfig) -> Result<()> {
+        if config.fps == 0 {
+            return Err(Error::Config("录制帧率不能为0".to_string()));
+        }
+
+        if config.max_segment_duration == Some(0) {
+            return Err(Error::Config("分段最大时长不能为0".to_string()));
+        }
+
+        if config.max_segment_bytes == Some(0) {
+            return Err(Error::Config("分段最大大小不能为0".to_string()));
+        }
+
+        config.filename_template()?;
+
+        if config.preview.enabled {
+            preview::validate(&config.preview)?;
+        }
+
+        self.registry.validate(config)?;
+        Ok(())
+    }
 }
 
 impl Drop for VideoRecorder {
     fn drop(&mut self) {
-        if self.recording {
+        if self.state.is_active() {
             if let Err(e) = self.stop_recording() {
                 error!("关闭录制器时出错: {}", e);
             }
@@ -107,18 +754,24 @@ impl Drop for VideoRecorder {
 }
 
 /// 视频拆分器
+///
+/// 拆分请求先进入队列，按 `max_concurrent` 同时运行，结束后移入历史记录。
+/// 队列状态需要定期调用 `poll` 推进，每次状态变化都会写入队列文件。
 pub struct VideoSplitter {
     /// 拆分配置
     config: SplitConfig,
 
-    /// 是否正在拆分
-    splitting: bool,
+    /// 排队中的任务，按加入顺序
+    queue: VecDeque<SplitTaskStatus>,
 
-    /// 当前拆分任务ID
-    current_task_id: Option<String>,
+    /// 运行中的任务
+    running: Vec<SplitTask>,
 
-    /// 当前拆分进度 (0.0 - 1.0)
-    progress: f32,
+    /// 已结束的任务，最新的在前
+    history: VecDeque<SplitTaskStatus>,
+
+    /// 存储空间检查，未设置时不检查
+    storage_guard: Option<Arc<dyn StorageGuard>>,
 }
 
 impl VideoSplitter {
@@ -126,77 +779,260 @@ impl VideoSplitter {
     pub fn new(config: SplitConfig) -> Self {
         Self {
             config,
-            splitting: false,
-            current_task_id: None,
-            progress: 0.0,
+            queue: VecDeque::new(),
+            running: Vec::new(),
+            history: VecDeque::new(),
+            storage_guard: None,
         }
     }
 
-    /// 开始拆分视频
-    pub fn start_splitting(&mut self, video_path: &Path) -> Result<String> {
-        if self.splitting {
-            return Err(Error::VideoProcessing(
-                "已有拆分任务正在进行".to_string()
-            ));
+    /// 设置存储空间检查，空间不足时拒绝开始拆分或中止拆分
+    pub fn set_storage_guard(&mut self, guard: Arc<dyn StorageGuard>) {
+        self.storage_guard = Some(guard);
+    }
+
+    /// 从队列文件恢复上次未完成的任务和历史记录，返回恢复的任务数
+    pub fn load_queue(&mut self) -> Result<usize> {
+        let path = self.config.queue_path();
+        let saved = SplitQueueFile::load(&path)?;
+        let restored = saved.pending.len();
+
+        self.queue.extend(saved.pending);
+        self.history.extend(saved.history);
+        self.history.truncate(self.config.history_limit);
+
+        if restored > 0 {
+            info!("从 {} 恢复 {} 个拆分任务", path.display(), restored);
         }
 
-        // 检查视频文件是否存在
-        if !video_path.exists() {
-            return Err(Error::VideoProcessing(format!(
-                "视频文件不存在: {}", video_path.display()
-            )));
+        self.poll();
+        Ok(restored)
+    }
+
+    /// 是否有拆分任务正在运行或排队
+    pub fn is_splitting(&self) -> bool {
+        !self.running.is_empty() || !self.queue.is_empty()
+    }
+
+    /// 将视频加入拆分队列，使用拆分器的当前配置，返回任务ID
+    pub fn start_splitting(&mut self, video_path: &Path) -> Result<String> {
+        self.enqueue(video_path, None)
+    }
+
+    /// 使用单独的拆分配置（如抽帧方式和时间段）将视频加入拆分队列，返回任务ID
+    pub fn start_splitting_with(&mut self, video_path: &Path, config: SplitConfig) -> Result<String> {
+        self.enqueue(video_path, Some(config))
+    }
+
+    /// 校验配置并加入队列
+    fn enqueue(&mut self, video_path: &Path, job_config: Option<SplitConfig>) -> Result<String> {
+        let config = job_config.as_ref().unwrap_or(&self.config);
+        split::validate(config)?;
+
+        // 检查视频文件是否存在且格式可以解码，裁剪区域是否在画面内；
+        // 只读取视频流信息，完整的探测在任务开始时进行
+        let info = probe_video(video_path)?;
+        split::check_source(config, &info)?;
+
+        let pending = self.queue.iter()
+            .cloned()
+            .chain(self.running.iter().map(|task| task.status()));
+        for job in pending {
+            if job.video_path == video_path {
+                return Err(Error::VideoProcessing(format!(
+                    "该视频已在拆分队列中: {} ({})", video_path.display(), job.task_id
+                )));
+            }
         }
 
-        // 创建输出目录
+        // 输出目录在加入队列时确定，便于提前告知调用方
         let video_name = video_path.file_stem()
             .and_then(|s| s.to_str())
             .unwrap_or("unknown");
 
-        let output_dir = Path::new(&self.config.output_dir)
-            .join(format!("frames_{}", video_name));
+        let now = Local::now();
+        let context = NamingContext {
+            video: Some(video_name),
+            ..NamingContext::new(now)
+        };
+        let folder = config.folder_template()?.render(&context);
+        let dated = naming::dated_dir(Path::new(&config.output_dir), &now, config.date_subdirs);
 
-        std::fs::create_dir_all(&output_dir)?;
+        // 每个任务独占一个新建的目录，已有的拆分结果不会被覆盖或在取消时被删除；
+        // 加入队列时就创建，排队中的任务之间也不会选到同一个目录
+        std::fs::create_dir_all(&dated)?;
+        let output_dir = naming::unique_path(dated.join(folder));
+        std::fs::create_dir(&output_dir)?;
 
         // 生成任务ID
         let task_id = format!("split_{}", uuid::Uuid::new_v4());
 
-        // 这里将来会添加异步拆分任务的代码
-
-        info!("开始拆分视频: {} -> {}", video_path.display(), output_dir.display());
-        self.splitting = true;
-        self.current_task_id = Some(task_id.clone());
-        self.progress = 0.0;
+        info!("拆分任务加入队列: {} -> {} ({})", video_path.display(), output_dir.display(), task_id);
+        let mut job = SplitTaskStatus::queued(task_id.clone(), video_path, output_dir);
+        job.config = job_config;
+        self.queue.push_back(job);
+        self.save_queue();
+        self.poll();
 
         Ok(task_id)
     }
 
-    /// 获取拆分任务状态
-    pub fn get_task_status(&self, task_id: &str) -> Option<(bool, f32)> {
-        if let Some(current_id) = &self.current_task_id {
-            if current_id == task_id {
-                return Some((self.splitting, self.progress));
+    /// 将多个视频依次加入拆分队列，返回每个视频的任务ID或错误
+    pub fn start_splitting_all(&mut self, video_paths: &[PathBuf]) -> Vec<Result<String>> {
+        video_paths.iter()
+            .map(|path| self.start_splitting(path))
+            .collect()
+    }
+
+    /// 回收已结束的任务并按并发数启动排队中的任务
+    ///
+    /// 会等待已结束任务的工作线程退出、打开视频文件并写入队列文件，
+    /// 在异步运行时中应放到阻塞线程执行。
+    pub fn poll(&mut self) {
+        let mut changed = false;
+
+        let mut index = 0;
+        while index < self.running.len() {
+            if self.running[index].is_running() {
+                index += 1;
+                continue;
             }
+
+            let mut task = self.running.remove(index);
+            task.join();
+            self.push_history(task.status());
+            changed = true;
         }
 
-        None
+        while self.running.len() < self.config.max_concurrent {
+            let job = match self.queue.pop_front() {
+                Some(job) => job,
+                None => break,
+            };
+
+            match self.spawn(job.clone()) {
+                Ok(task) => self.running.push(task),
+                Err(e) => {
+                    error!("无法开始拆分 {}: {}", job.video_path.display(), e);
+                    let mut job = job;
+                    job.finish(SplitState::Failed, Some(e.to_string()));
+                    self.push_history(job);
+                }
+            }
+            changed = true;
+        }
+
+        if changed {
+            self.save_queue();
+        }
     }
 
-    /// 取消拆分任务
+    /// 启动一个排队中的任务
+    fn spawn(&self, job: SplitTaskStatus) -> Result<SplitTask> {
+        let source = open_video(&job.video_path)?;
+        let video_info = source.info();
+        info!("视频信息: {}x{} @ {:.2}fps, {:?}",
+            video_info.width, video_info.height, video_info.fps, video_info.format);
+
+        if let Some(guard) = &self.storage_guard {
+            guard.check(StorageJob::Split, &job.output_dir, 0)?;
+        }
+
+        std::fs::create_dir_all(&job.output_dir)?;
+
+        info!("开始拆分视频: {} -> {}", job.video_path.display(), job.output_dir.display());
+        let config = job.config.clone().unwrap_or_else(|| self.config.clone());
+        SplitTask::spawn(job, source, &config, self.storage_guard.clone())
+    }
+
+    /// 记录已结束的任务
+    fn push_history(&mut self, job: SplitTaskStatus) {
+        self.history.push_front(job);
+        self.history.truncate(self.config.history_limit);
+    }
+
+    /// 写入队列文件，失败只记录日志
+    fn save_queue(&self) {
+        let saved = SplitQueueFile {
+            pending: self.running.iter()
+                .map(|task| task.status())
+                .chain(self.queue.iter().cloned())
+                .collect(),
+            history: self.history.iter().cloned().collect(),
+        };
+
+        let path = self.config.queue_path();
+        if let Err(e) = saved.save(&path) {
+            warn!("保存拆分队列失败 {}: {}", path.display(), e);
+        }
+    }
+
+    /// 获取拆分任务状态，返回是否仍未结束和进度
+    pub fn get_task_status(&self, task_id: &str) -> Option<(bool, f32)> {
+        self.task_status(task_id)
+            .map(|status| (!status.state.is_finished(), status.progress))
+    }
+
+    /// 获取拆分任务的详细状态，包括排队中和历史任务
+    pub fn task_status(&self, task_id: &str) -> Option<SplitTaskStatus> {
+        self.running.iter()
+            .map(|task| task.status())
+            .chain(self.queue.iter().cloned())
+            .chain(self.history.iter().cloned())
+            .find(|status| status.task_id == task_id)
+    }
+
+    /// 运行中和排队中的任务，运行中的在前
+    pub fn pending_tasks(&self) -> Vec<SplitTaskStatus> {
+        self.running.iter()
+            .map(|task| task.status())
+            .chain(self.queue.iter().cloned())
+            .collect()
+    }
+
+    /// 已结束的任务，最新的在前
+    pub fn history(&self) -> Vec<SplitTaskStatus> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// 取消拆分任务，排队中的任务直接移出队列
+    ///
+    /// 运行中的任务只发出取消请求，不等待工作线程退出：工作线程处理完当前帧后停止并
+    /// 删除输出，之后由 `poll` 移入历史记录。
     pub fn cancel_task(&mut self, task_id: &str) -> Result<bool> {
-        if let Some(current_id) = &self.current_task_id {
-            if current_id == task_id && self.splitting {
-                // 这里将来会添加取消任务的代码
+        if let Some(pos) = self.queue.iter().position(|job| job.task_id == task_id) {
+            info!("取消排队中的拆分任务: {}", task_id);
+            let mut job = self.queue.remove(pos).expect("队列中的任务");
+            // 加入队列时创建的目录还是空的
+            let _ = std::fs::remove_dir(&job.output_dir);
+            job.finish(SplitState::Cancelled, None);
+            self.push_history(job);
+            self.save_queue();
+            return Ok(true);
+        }
 
+        match self.running.iter_mut().find(|task| task.task_id() == task_id) {
+            Some(task) if task.is_running() => {
                 info!("取消拆分任务: {}", task_id);
-                self.splitting = false;
-                self.progress = 0.0;
-                self.current_task_id = None;
-
-                return Ok(true);
+                task.cancel();
+                Ok(true)
             }
+            _ => Ok(false),
         }
+    }
 
-        Ok(false)
+    /// 服务关闭时中断所有运行中的任务并等待工作线程退出
+    ///
+    /// 已输出的图片保留，队列文件中这些任务仍为未完成，下次启动时重新拆分。
+    pub fn shutdown(&mut self) {
+        self.save_queue();
+
+        for task in &self.running {
+            task.interrupt();
+        }
+        for mut task in self.running.drain(..) {
+            task.join();
+        }
     }
 
     /// 获取拆分配置
@@ -205,14 +1041,13 @@ impl VideoSplitter {
     }
 
     /// 设置拆分配置
+    ///
+    /// 运行中的任务继续使用原配置，排队中的任务开始时使用新配置。
     pub fn set_config(&mut self, config: SplitConfig) -> Result<()> {
-        if self.splitting {
-            return Err(Error::VideoProcessing(
-                "无法在拆分过程中更改配置，请先取消拆分任务".to_string()
-            ));
-        }
+        split::validate(&config)?;
 
         self.config = config;
+        self.poll();
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,624 @@
+//! YUV4MPEG2 (Y4M) 原始视频读写模块
+//!
+//! Y4M 是一种不经过任何压缩的原始视频格式，文件由一行文本头和若干
+//! "FRAME" 帧块组成，不依赖任何编解码库。录制时默认使用 4:4:4 全范围
+//! 色彩空间，不做色度下采样，RGB 与 YUV 互转的舍入误差不超过 ±1。
+//! 如果需要逐位一致，可通过 `write_raw_frame` 直接写入摄像头输出的YUV数据。
+
+use crate::{Error, Result};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Y4M 文件魔数
+const Y4M_MAGIC: &str = "YUV4MPEG2";
+
+/// 帧块起始标记
+const FRAME_MAGIC: &str = "FRAME";
+
+/// 单帧数据大小上限，足够容纳 8K 4:4:4 画面，防止损坏的文件头导致分配过大的内存
+const MAX_FRAME_SIZE: usize = 256 * 1024 * 1024;
+
+/// Y4M 色彩空间（色度采样方式）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Y4mColorspace {
+    /// 4:2:0，JPEG 采样位置（Y4M 默认值）
+    C420jpeg,
+    /// 4:2:0，PAL-DV 采样位置
+    C420paldv,
+    /// 4:2:0，MPEG-2 采样位置
+    C420mpeg2,
+    /// 4:2:0
+    C420,
+    /// 4:2:2
+    C422,
+    /// 4:4:4，无色度下采样
+    C444,
+    /// 仅亮度
+    Mono,
+}
+
+impl Y4mColorspace {
+    /// 从头部 C 参数解析色彩空间
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "420jpeg" => Ok(Self::C420jpeg),
+            "420paldv" => Ok(Self::C420paldv),
+            "420mpeg2" => Ok(Self::C420mpeg2),
+            "420" => Ok(Self::C420),
+            "422" => Ok(Self::C422),
+            "444" => Ok(Self::C444),
+            "mono" => Ok(Self::Mono),
+            other => Err(Error::VideoProcessing(format!(
+                "不支持的Y4M色彩空间: {}", other
+            ))),
+        }
+    }
+
+    /// 头部 C 参数中的名称
+    fn name(&self) -> &'static str {
+        match self {
+            Self::C420jpeg => "420jpeg",
+            Self::C420paldv => "420paldv",
+            Self::C420mpeg2 => "420mpeg2",
+            Self::C420 => "420",
+            Self::C422 => "422",
+            Self::C444 => "444",
+            Self::Mono => "mono",
+        }
+    }
+
+    /// 色度平面相对亮度平面的尺寸，单色时返回 None
+    fn chroma_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        match self {
+            Self::C420jpeg | Self::C420paldv | Self::C420mpeg2 | Self::C420 => {
+                Some((width.div_ceil(2), height.div_ceil(2)))
+            }
+            Self::C422 => Some((width.div_ceil(2), height)),
+            Self::C444 => Some((width, height)),
+            Self::Mono => None,
+        }
+    }
+}
+
+/// Y4M 文件头信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct Y4mHeader {
+    /// 帧宽度
+    pub width: u32,
+    /// 帧高度
+    pub height: u32,
+    /// 帧率分子
+    pub fps_num: u32,
+    /// 帧率分母
+    pub fps_den: u32,
+    /// 色彩空间
+    pub colorspace: Y4mColorspace,
+    /// 是否为全范围（0-255）色彩，否则为 TV 范围（16-235）
+    pub full_range: bool,
+}
+
+impl Y4mHeader {
+    /// 创建 4:4:4 全范围的文件头
+    pub fn new(width: u32, height: u32, fps: u32) -> Self {
+        Self {
+            width,
+            height,
+            fps_num: fps.max(1),
+            fps_den: 1,
+            colorspace: Y4mColorspace::C444,
+            full_range: true,
+        }
+    }
+
+    /// 帧率
+    pub fn fps(&self) -> f64 {
+        if self.fps_den == 0 {
+            return 0.0;
+        }
+        self.fps_num as f64 / self.fps_den as f64
+    }
+
+    /// 单帧数据（不含 FRAME 行）的字节数
+    pub fn frame_size(&self) -> usize {
+        let luma = self.width as usize * self.height as usize;
+        match self.colorspace.chroma_size(self.width, self.height) {
+            Some((cw, ch)) => luma + 2 * cw as usize * ch as usize,
+            None => luma,
+        }
+    }
+
+    /// 按溢出检查计算单帧数据的字节数，溢出时返回 None
+    fn checked_frame_size(&self) -> Option<usize> {
+        let luma = (self.width as usize).checked_mul(self.height as usize)?;
+        match self.colorspace.chroma_size(self.width, self.height) {
+            Some((cw, ch)) => (cw as usize).checked_mul(ch as usize)?
+                .checked_mul(2)?
+                .checked_add(luma),
+            None => Some(luma),
+        }
+    }
+
+    /// 生成文件头文本行
+    fn to_line(&self) -> String {
+        let range = if self.full_range { "FULL" } else { "LIMITED" };
+        format!(
+            "{} W{} H{} F{}:{} Ip A1:1 C{} XCOLORRANGE={}\n",
+            Y4M_MAGIC, self.width, self.height, self.fps_num, self.fps_den,
+            self.colorspace.name(), range
+        )
+    }
+
+    /// 解析文件头文本行
+    fn parse(line: &str) -> Result<Self> {
+        let mut tokens = line.split_whitespace();
+
+        if tokens.next() != Some(Y4M_MAGIC) {
+            return Err(Error::VideoProcessing("不是有效的Y4M文件".to_string()));
+        }
+
+        let mut width = None;
+        let mut height = None;
+        let mut fps = (25, 1);
+        let mut colorspace = Y4mColorspace::C420jpeg;
+        let mut full_range = false;
+
+        for token in tokens {
+            // 标记为第一个字符，损坏的文件头可能以多字节字符开头
+            let tag = token.chars().next().unwrap_or_default();
+            let value = &token[tag.len_utf8()..];
+            match tag {
+                'W' => width = value.parse::<u32>().ok(),
+                'H' => height = value.parse::<u32>().ok(),
+                'F' => {
+                    let mut parts = value.splitn(2, ':');
+                    let num = parts.next().and_then(|s| s.parse::<u32>().ok());
+                    let den = parts.next().and_then(|s| s.parse::<u32>().ok());
+                    if let (Some(num), Some(den)) = (num, den) {
+                        fps = (num, den);
+                    }
+                }
+                'C' => colorspace = Y4mColorspace::parse(value)?,
+                'X' => {
+                    if let Some(range) = value.strip_prefix("COLORRANGE=") {
+                        full_range = range.eq_ignore_ascii_case("FULL");
+                    }
+                }
+                // 隔行、宽高比等参数对解码没有影响，直接忽略
+                _ => {}
+            }
+        }
+
+        let header = match (width, height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => Self {
+                width,
+                height,
+                fps_num: fps.0,
+                fps_den: fps.1,
+                colorspace,
+                full_range,
+            },
+            _ => return Err(Error::VideoProcessing("Y4M文件头缺少有效的宽高".to_string())),
+        };
+
+        match header.checked_frame_size() {
+            Some(size) if size <= MAX_FRAME_SIZE => Ok(header),
+            _ => Err(Error::VideoProcessing(format!(
+                "Y4M画面尺寸过大: {}x{}", header.width, header.height
+            ))),
+        }
+    }
+}
+
+/// Y4M 写入器
+pub struct Y4mWriter<W: Write> {
+    /// 底层输出
+    inner: W,
+    /// 文件头
+    header: Y4mHeader,
+    /// 已写入帧数
+    frame_count: u64,
+    /// 已写入字节数
+    bytes_written: u64,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// 创建写入器并写出文件头
+    pub fn new(mut inner: W, header: Y4mHeader) -> Result<Self> {
+        let line = header.to_line();
+        inner.write_all(line.as_bytes())?;
+
+        Ok(Self {
+            inner,
+            header,
+            frame_count: 0,
+            bytes_written: line.len() as u64,
+        })
+    }
+
+    /// 写入一帧RGB图像，按文件头的色彩空间转换为YUV
+    pub fn write_frame(&mut self, frame: &image::RgbImage) -> Result<()> {
+        if frame.width() != self.header.width || frame.height() != self.header.height {
+            return Err(Error::VideoProcessing(format!(
+                "帧尺寸 {}x{} 与Y4M文件头 {}x{} 不一致",
+                frame.width(), frame.height(), self.header.width, self.header.height
+            )));
+        }
+
+        let data = rgb_to_yuv(frame, self.header.colorspace, self.header.full_range);
+        self.write_raw_frame(&data)
+    }
+
+    /// 写入一帧已按平面排列的YUV原始数据
+    pub fn write_raw_frame(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() != self.header.frame_size() {
+            return Err(Error::VideoProcessing(format!(
+                "YUV帧数据大小错误: 期望 {} 字节，实际 {} 字节",
+                self.header.frame_size(), data.len()
+            )));
+        }
+
+        self.inner.write_all(FRAME_MAGIC.as_bytes())?;
+        self.inner.write_all(b"\n")?;
+        self.inner.write_all(data)?;
+
+        self.frame_count += 1;
+        self.bytes_written += (FRAME_MAGIC.len() + 1 + data.len()) as u64;
+
+        Ok(())
+    }
+
+    /// 获取文件头
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    /// 获取已写入帧数
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// 获取已写入字节数
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// 刷新缓冲并取回底层输出
+    pub fn finish(mut self) -> Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Y4M 读取器
+pub struct Y4mReader<R: BufRead> {
+    /// 底层输入
+    inner: R,
+    /// 文件头
+    header: Y4mHeader,
+    /// 文件头所占字节数
+    header_len: u64,
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    /// 创建读取器并解析文件头
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut line = String::new();
+        let header_len = inner.read_line(&mut line)? as u64;
+        let header = Y4mHeader::parse(line.trim_end())?;
+
+        Ok(Self {
+            inner,
+            header,
+            header_len,
+        })
+    }
+
+    /// 获取文件头
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    /// 根据文件总大小估算帧数（假设帧块不带额外参数）
+    pub fn estimate_frame_count(&self, file_size: u64) -> u64 {
+        let frame_block = (FRAME_MAGIC.len() + 1 + self.header.frame_size()) as u64;
+        file_size.saturating_sub(self.header_len) / frame_block
+    }
+
+    /// 读取下一帧的YUV原始数据，到达文件末尾时返回 None
+    pub fn next_raw_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut line = String::new();
+        if self.inner.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        if !line.starts_with(FRAME_MAGIC) {
+            return Err(Error::VideoProcessing("Y4M帧块标记错误".to_string()));
+        }
+
+        let mut data = vec![0u8; self.header.frame_size()];
+        match self.inner.read_exact(&mut data) {
+            Ok(()) => Ok(Some(data)),
+            // 末尾残缺的帧（例如录制中断电）直接丢弃
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 读取下一帧并转换为RGB图像，到达文件末尾时返回 None
+    pub fn next_frame(&mut self) -> Result<Option<image::RgbImage>> {
+        match self.next_raw_frame()? {
+            Some(data) => Ok(Some(yuv_to_rgb(&data, &self.header))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// 修复未正常结束的 Y4M 文件
+///
+/// Y4M 没有索引，只需截掉末尾写了一半的帧块。逐个读取帧块的 FRAME 行
+/// （可能带有参数）并跳过帧数据，在第一个不完整或标记错误的帧块处截断。
+/// 文件完整时返回 `Ok(None)`，否则返回截断后保留的帧数。
+pub fn repair_y4m(path: &Path) -> Result<Option<u64>> {
+    let file = OpenOptions::new().read(true).write(true).open(path)?;
+    let file_size = file.metadata()?.len();
+
+    let mut reader = Y4mReader::new(BufReader::new(&file))?;
+    let frame_size = reader.header.frame_size() as u64;
+    let mut position = reader.header_len;
+    let mut frames = 0u64;
+    let mut line = Vec::new();
+
+    let valid_len = loop {
+        line.clear();
+        let line_len = reader.inner.read_until(b'\n', &mut line)? as u64;
+        if line_len == 0 {
+            return Ok(None);
+        }
+
+        let complete = line.ends_with(b"\n")
+            && line.starts_with(FRAME_MAGIC.as_bytes())
+            && position + line_len + frame_size <= file_size;
+        if !complete {
+            break position;
+        }
+
+        reader.inner.seek_relative(frame_size as i64)?;
+        position += line_len + frame_size;
+        frames += 1;
+    };
+
+    file.set_len(valid_len)?;
+    file.sync_all()?;
+
+    Ok(Some(frames))
+}
+
+/// RGB 转 YUV（BT.601），返回按平面排列的数据
+pub(crate) fn rgb_to_yuv(frame: &image::RgbImage, colorspace: Y4mColorspace, full_range: bool) -> Vec<u8> {
+    let width = frame.width();
+    let height = frame.height();
+    let luma_len = width as usize * height as usize;
+
+    // 先按 4:4:4 计算完整平面，再按需下采样
+    let mut y_plane = Vec::with_capacity(luma_len);
+    let mut u_full = Vec::with_capacity(luma_len);
+    let mut v_full = Vec::with_capacity(luma_len);
+
+    for pixel in frame.pixels() {
+        let r = pixel[0] as f32;
+        let g = pixel[1] as f32;
+        let b = pixel[2] as f32;
+
+        let y = 0.299 * r + 0.587 * g + 0.114 * b;
+        let u = -0.168_736 * r - 0.331_264 * g + 0.5 * b;
+        let v = 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+
+        if full_range {
+            y_plane.push(clamp_u8(y));
+            u_full.push(clamp_u8(u + 128.0));
+            v_full.push(clamp_u8(v + 128.0));
+        } else {
+            y_plane.push(clamp_u8(16.0 + y * 219.0 / 255.0));
+            u_full.push(clamp_u8(128.0 + u * 224.0 / 255.0));
+            v_full.push(clamp_u8(128.0 + v * 224.0 / 255.0));
+        }
+    }
+
+    let mut data = y_plane;
+
+    if let Some((cw, ch)) = colorspace.chroma_size(width, height) {
+        let sx = if cw == width { 1 } else { 2 };
+        let sy = if ch == height { 1 } else { 2 };
+        for plane in [&u_full, &v_full] {
+            if sx == 1 && sy == 1 {
+                data.extend_from_slice(plane);
+                continue;
+            }
+            for cy in 0..ch {
+                for cx in 0..cw {
+                    // 对采样块内的像素取平均
+                    let mut sum = 0u32;
+                    let mut count = 0u32;
+                    for dy in 0..sy {
+                        for dx in 0..sx {
+                            let x = cx * sx + dx;
+                            let y = cy * sy + dy;
+                            if x < width && y < height {
+                                sum += plane[(y * width + x) as usize] as u32;
+                                count += 1;
+                            }
+                        }
+                    }
+                    data.push(((sum + count / 2) / count) as u8);
+                }
+            }
+        }
+    }
+
+    data
+}
+
+/// YUV 平面数据转 RGB（BT.601）
+fn yuv_to_rgb(data: &[u8], header: &Y4mHeader) -> image::RgbImage {
+    let width = header.width;
+    let height = header.height;
+    let luma_len = width as usize * height as usize;
+    let chroma = header.colorspace.chroma_size(width, height);
+
+    let mut img = image::RgbImage::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let luma = data[(y * width + x) as usize] as f32;
+
+            let (u, v) = match chroma {
+                Some((cw, ch)) => {
+                    let cx = x * cw / width;
+                    let cy = y * ch / height;
+                    let offset = (cy * cw + cx) as usize;
+                    let plane_len = cw as usize * ch as usize;
+                    (
+                        data[luma_len + offset] as f32,
+                        data[luma_len + plane_len + offset] as f32,
+                    )
+                }
+                None => (128.0, 128.0),
+            };
+
+            let (luma, u, v) = if header.full_range {
+                (luma, u - 128.0, v - 128.0)
+            } else {
+                (
+                    (luma - 16.0) * 255.0 / 219.0,
+                    (u - 128.0) * 255.0 / 224.0,
+                    (v - 128.0) * 255.0 / 224.0,
+                )
+            };
+
+            let r = luma + 1.402 * v;
+            let g = luma - 0.344_136 * u - 0.714_136 * v;
+            let b = luma + 1.772 * u;
+
+            img.put_pixel(x, y, image::Rgb([clamp_u8(r), clamp_u8(g), clamp_u8(b)]));
+        }
+    }
+
+    img
+}
+
+/// 四舍五入并截断到 0-255
+fn clamp_u8(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn header_round_trip() {
+        let mut header = Y4mHeader::new(5, 3, 30);
+        header.colorspace = Y4mColorspace::C420;
+        header.full_range = false;
+
+        let data = Y4mWriter::new(Vec::new(), header.clone()).unwrap().finish().unwrap();
+        let reader = Y4mReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(reader.header(), &header);
+    }
+
+    #[test]
+    fn malformed_header_is_an_error() {
+        assert!(Y4mHeader::parse("YUV4MPEG2 ÉW4 H4").is_err());
+        assert!(Y4mHeader::parse("YUV4MPEG2 W4 H4 é").is_ok());
+        assert!(Y4mHeader::parse("YUV4MPEG2 W65535 H65535 C444").is_err());
+        assert!(Y4mHeader::parse("YUV4MPEG2 W0 H4").is_err());
+    }
+
+    #[test]
+    fn rgb_frame_round_trip_within_rounding() {
+        let frame = image::RgbImage::from_fn(8, 6, |x, y| {
+            image::Rgb([(x * 37) as u8, (y * 53) as u8, ((x + y) * 11) as u8])
+        });
+
+        let mut writer = Y4mWriter::new(Vec::new(), Y4mHeader::new(8, 6, 25)).unwrap();
+        writer.write_frame(&frame).unwrap();
+        let data = writer.finish().unwrap();
+
+        let mut reader = Y4mReader::new(Cursor::new(data)).unwrap();
+        let decoded = reader.next_frame().unwrap().expect("帧");
+        for (a, b) in decoded.as_raw().iter().zip(frame.as_raw()) {
+            assert!(a.abs_diff(*b) <= 1, "像素误差过大: {} vs {}", a, b);
+        }
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn raw_frames_round_trip_with_odd_chroma() {
+        let mut header = Y4mHeader::new(5, 3, 25);
+        header.colorspace = Y4mColorspace::C420;
+        // 5x3 的 4:2:0 色度平面为 3x2
+        assert_eq!(header.frame_size(), 15 + 2 * 6);
+
+        let frames: Vec<Vec<u8>> = (0..2u8)
+            .map(|i| (0..header.frame_size() as u8).map(|b| b.wrapping_mul(7) ^ i).collect())
+            .collect();
+        let mut writer = Y4mWriter::new(Vec::new(), header).unwrap();
+        for frame in &frames {
+            writer.write_raw_frame(frame).unwrap();
+        }
+        let data = writer.finish().unwrap();
+
+        let mut reader = Y4mReader::new(Cursor::new(data)).unwrap();
+        for frame in &frames {
+            assert_eq!(&reader.next_raw_frame().unwrap().expect("帧"), frame);
+        }
+        assert!(reader.next_raw_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn repair_truncates_partial_frame_block() {
+        let header = Y4mHeader::new(4, 2, 25);
+        let frame = vec![128u8; header.frame_size()];
+        let mut complete = header.to_line().into_bytes();
+        for _ in 0..2 {
+            complete.extend_from_slice(b"FRAME\n");
+            complete.extend_from_slice(&frame);
+        }
+
+        // 帧数据写了一半，或 FRAME 行写了一半
+        for tail in [&b"FRAME\n\x80\x80\x80"[..], &b"FRA"[..]] {
+            let path = std::env::temp_dir().join(format!("repair-{}.y4m", uuid::Uuid::new_v4()));
+            std::fs::write(&path, [&complete[..], tail].concat()).unwrap();
+
+            assert_eq!(repair_y4m(&path).unwrap(), Some(2));
+            assert_eq!(std::fs::read(&path).unwrap(), complete);
+            assert_eq!(repair_y4m(&path).unwrap(), None);
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn repair_handles_frame_parameters() {
+        let header = Y4mHeader::new(4, 2, 25);
+        let frame = vec![128u8; header.frame_size()];
+        let mut data = header.to_line().into_bytes();
+        for _ in 0..2 {
+            data.extend_from_slice(b"FRAME Ip XTAG=1\n");
+            data.extend_from_slice(&frame);
+        }
+        let complete_len = data.len() as u64;
+        data.extend_from_slice(b"FRAME Ip\n");
+        data.extend_from_slice(&frame[..3]);
+
+        let path = std::env::temp_dir().join(format!("repair-{}.y4m", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(repair_y4m(&path).unwrap(), Some(2));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), complete_len);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
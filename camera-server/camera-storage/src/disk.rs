@@ -1,8 +1,16 @@
 //! 存储空间管理模块
 
 use crate::{Error, Result};
-use log::{info, error, debug};
+use camera_core::guard::{StorageEvent, StorageGuard, StorageJob};
+use log::{info, error, debug, warn};
 use std::path::Path;
+use tokio::sync::broadcast;
+
+/// 存储空间事件通道容量
+const EVENT_CAPACITY: usize = 16;
+
+/// 1 MB
+const MB: u64 = 1024 * 1024;
 
 /// 磁盘空间信息
 #[derive(Debug, Clone)]
@@ -147,3 +155,110 @@ impl DiskManager {
         Ok(total_size)
     }
 }
+
+/// 存储空间保护配置
+#[derive(Debug, Clone)]
+pub struct DiskGuardConfig {
+    /// 最大磁盘使用率 (0.0 - 1.0)，超过后拒绝或停止写入
+    pub max_usage: f32,
+    /// 至少保留的可用空间（字节）
+    pub min_free_bytes: u64,
+}
+
+/// 基于磁盘使用率和剩余空间的存储空间保护
+///
+/// 检查不通过时通过广播通道发出 `StorageEvent`，应用可以订阅后更新服务状态或通知用户。
+pub struct DiskGuard {
+    /// 磁盘管理器
+    disk: DiskManager,
+    /// 配置
+    config: DiskGuardConfig,
+    /// 事件发送端
+    events: broadcast::Sender<StorageEvent>,
+}
+
+impl DiskGuard {
+    /// 创建存储空间保护
+    pub fn new(config: DiskGuardConfig) -> Result<Self> {
+        if !(config.max_usage > 0.0 && config.max_usage <= 1.0) {
+            return Err(Error::Config(format!(
+                "最大磁盘使用率必须在 (0, 1] 范围内: {}", config.max_usage
+            )));
+        }
+
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        Ok(Self {
+            disk: DiskManager::new(),
+            config,
+            events,
+        })
+    }
+
+    /// 订阅存储空间不足事件
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+
+    /// 检查路径所在磁盘，返回不满足限制时的说明
+    fn evaluate(&self, path: &Path, required: u64) -> Result<Option<(DiskSpaceInfo, String)>> {
+        let info = self.disk.get_disk_space(path)?;
+
+        if !self.disk.has_enough_space(path, required.saturating_add(self.config.min_free_bytes))? {
+            let message = format!(
+                "可用空间 {} MB，需要 {} MB 并保留 {} MB",
+                info.available / MB, required / MB, self.config.min_free_bytes / MB
+            );
+            return Ok(Some((info, message)));
+        }
+
+        let usage_after = if info.total > 0 {
+            info.used.saturating_add(required) as f32 / info.total as f32
+        } else {
+            0.0
+        };
+        if usage_after >= self.config.max_usage {
+            let message = format!(
+                "磁盘使用率 {:.1}% 超过上限 {:.1}%",
+                usage_after * 100.0, self.config.max_usage * 100.0
+            );
+            return Ok(Some((info, message)));
+        }
+
+        Ok(None)
+    }
+}
+
+impl StorageGuard for DiskGuard {
+    fn check(&self, job: StorageJob, path: &Path, required: u64) -> camera_core::Result<()> {
+        // 路径可能尚未创建，检查最近的已存在上级目录
+        let existing = match path.ancestors().find(|p| p.exists()) {
+            Some(existing) => existing,
+            None => Path::new("."),
+        };
+
+        let (info, message) = match self.evaluate(existing, required) {
+            Ok(Some(result)) => result,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                // 无法获取磁盘信息时不阻止写入，避免在不支持的文件系统上完全无法录制
+                debug!("获取磁盘空间失败，跳过检查: {}: {}", existing.display(), e);
+                return Ok(());
+            }
+        };
+
+        warn!("存储空间不足，{} 任务被拒绝: {}: {}", job.as_str(), path.display(), message);
+
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.events.send(StorageEvent {
+            job,
+            path: path.to_path_buf(),
+            usage_ratio: info.usage_ratio,
+            available: info.available,
+            message: message.clone(),
+            occurred_at: chrono::Local::now().to_rfc3339(),
+        });
+
+        Err(camera_core::Error::StorageFull(format!("{}: {}", path.display(), message)))
+    }
+}
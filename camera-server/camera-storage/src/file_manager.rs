@@ -1,10 +1,26 @@
 //! 文件系统操作和管理模块
 
 use crate::{Error, Result};
+use camera_core::metadata::{sidecar_path, RecordingMetadata};
+use camera_core::preview::{poster_path, thumbnails_path};
+use camera_core::recovery::{recover_video, RecoveryOutcome};
+use camera_core::sheet::sheet_files;
 use log::{info, error, debug};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+/// 视频文件扩展名
+const VIDEO_EXTENSIONS: &[&str] = &["avi", "mp4", "mkv", "mov", "ts", "y4m"];
+
+/// 已修复标记文件的后缀
+const RECOVERED_SUFFIX: &str = ".recovered";
+
+/// 固定标记文件的后缀，带有该标记的文件或文件夹不会被保留策略删除
+const PINNED_SUFFIX: &str = ".pinned";
+
+/// 按日期分目录（`YYYY/MM/DD`）保存时，文件相对根目录的最大深度
+pub(crate) const DATED_DEPTH: usize = 4;
+
 /// 文件类型
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileType {
@@ -31,6 +47,16 @@ pub struct FileInfo {
     pub created_at: u64,
     /// 修改时间（Unix时间戳）
     pub modified_at: u64,
+    /// 是否为异常中断后修复的录制文件
+    pub recovered: bool,
+    /// 是否已固定，固定的文件不会被保留策略删除
+    pub pinned: bool,
+    /// 录制元数据，仅视频文件且存在附属元数据文件时有值
+    pub metadata: Option<RecordingMetadata>,
+    /// 封面图路径，仅视频文件且已生成时有值
+    pub poster: Option<PathBuf>,
+    /// 缩略图条路径，仅视频文件且已生成时有值
+    pub thumbnails: Option<PathBuf>,
 }
 
 /// 文件管理器
@@ -65,17 +91,14 @@ impl FileManager {
     /// 获取图像帧目录列表
     pub fn list_frame_dirs(&self) -> Result<Vec<FileInfo>> {
         // 只列出目录
-        let entries = WalkDir::new(&self.frames_dir)
-            .max_depth(1)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path() != self.frames_dir && e.file_type().is_dir());
+        let entries = frame_dirs(&self.frames_dir);
             
         let mut result = Vec::new();
         
         for entry in entries {
             let path = entry.path().to_path_buf();
             let name = entry.file_name().to_string_lossy().to_string();
+            let pinned = Self::is_pinned(&path);
             
             let metadata = entry.metadata()?;
             let size = metadata.len();
@@ -95,24 +118,119 @@ impl FileManager {
                 file_type: FileType::Other, // 目录类型
                 created_at,
                 modified_at,
+                recovered: false,
+                pinned,
+                metadata: None,
+                poster: None,
+                thumbnails: None,
             });
         }
         
         Ok(result)
     }
     
+    /// 检查并修复视频目录中异常中断的录制文件
+    pub fn recover_videos(&self) -> Result<Vec<PathBuf>> {
+        Self::recover_dir(&self.video_dir)
+    }
+    
+    /// 检查并修复指定目录中异常中断的录制文件，返回修复过的文件
+    ///
+    /// 修复过的文件旁会写入 `<文件名>.recovered` 标记，`list_videos` 据此设置 `recovered`。
+    /// 已有修复标记或元数据中记录了结束时间（正常结束）的文件不再检查。
+    /// 单个文件修复失败只记录日志，不影响其他文件。
+    pub fn recover_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let entries = WalkDir::new(dir)
+            .max_depth(DATED_DEPTH)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || e.file_type().is_file() || is_date_dir(e))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && is_video_file(e.path()));
+            
+        let mut recovered = Vec::new();
+        
+        for entry in entries {
+            let path = entry.path();
+            if recovered_marker(path).exists() {
+                continue;
+            }
+            
+            let metadata = RecordingMetadata::load_for(path).ok().flatten();
+            if metadata.as_ref().is_some_and(|m| m.end_time.is_some()) {
+                continue;
+            }
+            
+            match recover_video(path) {
+                Ok(RecoveryOutcome::Repaired { frames }) => {
+                    info!("录制文件已修复: {}, 保留 {} 帧", path.display(), frames);
+                    if let Err(e) = std::fs::write(recovered_marker(path), frames.to_string()) {
+                        error!("写入修复标记失败: {}: {}", path.display(), e);
+                    }
+                    
+                    // 元数据中的帧数是录制开始时写入的，以修复结果为准
+                    if let Some(mut metadata) = metadata {
+                        metadata.frame_count = frames;
+                        if let Err(e) = metadata.save_for(path) {
+                            error!("更新录制元数据失败: {}: {}", path.display(), e);
+                        }
+                    }
+                    recovered.push(path.to_path_buf());
+                }
+                Ok(_) => debug!("录制文件无需修复: {}", path.display()),
+                Err(e) => error!("修复录制文件失败: {}: {}", path.display(), e),
+            }
+        }
+        
+        Ok(recovered)
+    }
+    
+    /// 读取视频文件的录制元数据
+    ///
+    /// 没有附属元数据文件（如旧版本录制的文件）或解析失败时返回 None。
+    pub fn read_metadata<P: AsRef<Path>>(&self, path: P) -> Option<RecordingMetadata> {
+        let path = path.as_ref();
+        match RecordingMetadata::load_for(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                error!("读取录制元数据失败: {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+    
     /// 列出指定目录中的文件
+    ///
+    /// 视频文件会同时列出日期子目录中的文件。
     fn list_files(&self, dir: &Path, file_type: FileType) -> Result<Vec<FileInfo>> {
+        let max_depth = if file_type == FileType::Video { DATED_DEPTH } else { 1 };
         let entries = WalkDir::new(dir)
-            .max_depth(1)
+            .max_depth(max_depth)
             .into_iter()
+            .filter_entry(|e| e.depth() == 0 || e.file_type().is_file() || is_date_dir(e))
             .filter_map(|e| e.ok())
-            .filter(|e| e.path() != dir && e.file_type().is_file());
+            .filter(|e| e.path() != dir && e.file_type().is_file())
+            .filter(|e| file_type != FileType::Video || is_video_file(e.path()));
             
         let mut result = Vec::new();
         
         for entry in entries {
             let path = entry.path().to_path_buf();
+            let recovered = file_type == FileType::Video && recovered_marker(&path).exists();
+            let pinned = Self::is_pinned(&path);
+            let (recording, poster, thumbnails) = if file_type == FileType::Video {
+                (
+                    self.read_metadata(&path),
+                    Some(poster_path(&path)).filter(|p| p.exists()),
+                    Some(thumbnails_path(&path)).filter(|p| p.exists()),
+                )
+            } else {
+                (None, None, None)
+            };
             let name = entry.file_name().to_string_lossy().to_string();
             
             let metadata = entry.metadata()?;
@@ -133,6 +251,11 @@ impl FileManager {
                 file_type: file_type.clone(),
                 created_at,
                 modified_at,
+                recovered,
+                pinned,
+                metadata: recording,
+                poster,
+                thumbnails,
             });
         }
         
@@ -158,13 +281,54 @@ impl FileManager {
         
         if path.is_file() {
             std::fs::remove_file(path)?;
+            
+            for companion in companion_files(path) {
+                if companion.exists() {
+                    std::fs::remove_file(companion)?;
+                }
+            }
         } else if path.is_dir() {
             std::fs::remove_dir_all(path)?;
+            
+            // 帧文件夹的固定标记和联系表、雪碧图写在文件夹旁边
+            for companion in std::iter::once(pinned_marker(path)).chain(sheet_files(path)) {
+                if companion.exists() {
+                    std::fs::remove_file(companion)?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 固定文件或帧文件夹，固定后不会被保留策略自动删除
+    pub fn pin<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        
+        if !path.exists() {
+            return Err(Error::FileSystem(format!(
+                "文件不存在: {}", path.display()
+            )));
         }
         
+        std::fs::write(pinned_marker(path), b"")?;
+        Ok(())
+    }
+    
+    /// 取消固定
+    pub fn unpin<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let marker = pinned_marker(path.as_ref());
+        if marker.exists() {
+            std::fs::remove_file(marker)?;
+        }
         Ok(())
     }
     
+    /// 文件或帧文件夹是否已固定
+    pub fn is_pinned<P: AsRef<Path>>(path: P) -> bool {
+        pinned_marker(path.as_ref()).exists()
+    }
+    
     /// 重命名文件
     pub fn rename_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
         let from = from.as_ref();
@@ -185,6 +349,84 @@ impl FileManager {
         
         std::fs::rename(from, to)?;
         
+        for (companion, target) in companion_files(from).into_iter().zip(companion_files(to)) {
+            if companion.exists() {
+                std::fs::rename(companion, target)?;
+            }
+        }
+        
         Ok(())
     }
 }
+
+/// 是否为按日期分目录时生成的 `YYYY`、`MM`、`DD` 目录
+///
+/// 依次要求第 1 层为 4 位数字、第 2、3 层为 2 位数字。
+pub(crate) fn is_date_dir(entry: &walkdir::DirEntry) -> bool {
+    let digits = match entry.depth() {
+        1 => 4,
+        2 | 3 => 2,
+        _ => return false,
+    };
+    let name = entry.file_name().to_string_lossy();
+    entry.file_type().is_dir()
+        && name.len() == digits
+        && name.chars().all(|c| c.is_ascii_digit())
+}
+
+/// 列出帧根目录下的帧文件夹，按日期分目录保存时进入日期子目录查找
+pub(crate) fn frame_dirs(root: &Path) -> Vec<walkdir::DirEntry> {
+    let mut walker = WalkDir::new(root).max_depth(DATED_DEPTH).into_iter();
+    let mut dirs = Vec::new();
+    
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if entry.depth() == 0 || !entry.file_type().is_dir() || is_date_dir(&entry) {
+            continue;
+        }
+        
+        // 帧文件夹内部不再继续查找
+        walker.skip_current_dir();
+        dirs.push(entry);
+    }
+    
+    dirs
+}
+
+/// 根据扩展名判断是否为视频文件
+fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| VIDEO_EXTENSIONS.iter().any(|v| v.eq_ignore_ascii_case(ext)))
+        .unwrap_or(false)
+}
+
+/// 与视频文件一起删除和重命名的附属文件
+fn companion_files(path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![
+        recovered_marker(path),
+        sidecar_path(path),
+        pinned_marker(path),
+        poster_path(path),
+        thumbnails_path(path),
+    ];
+    files.extend(sheet_files(path));
+    files
+}
+
+/// 已修复标记文件路径
+fn recovered_marker(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(RECOVERED_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// 固定标记文件路径
+fn pinned_marker(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(PINNED_SUFFIX);
+    PathBuf::from(name)
+}
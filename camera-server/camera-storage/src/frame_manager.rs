@@ -1,6 +1,11 @@
 //! 静态帧文件夹管理模块
 
 use crate::{Error, Result};
+use crate::file_manager::frame_dirs;
+use camera_core::config::{DedupConfig, SheetConfig, SheetKind};
+use camera_core::dedup::{self, DedupReport};
+use camera_core::manifest::{is_frame_file, SplitManifest};
+use camera_core::sheet::{self, SheetIndex};
 use log::{info, error, debug};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
@@ -29,6 +34,10 @@ pub struct FrameInfo {
     pub name: String,
     /// 帧序号
     pub frame_number: usize,
+    /// 原视频中的显示时间戳（秒），没有拆分清单时为 None
+    pub timestamp: Option<f64>,
+    /// 去重标记为重复时，与之相似的保留图片文件名
+    pub duplicate_of: Option<String>,
     /// 文件大小（字节）
     pub size: u64,
 }
@@ -54,11 +63,7 @@ impl FrameManager {
     
     /// 获取所有帧文件夹信息
     pub fn list_frame_dirs(&self) -> Result<Vec<FrameDirInfo>> {
-        let entries = WalkDir::new(&self.frames_root_dir)
-            .max_depth(1)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path() != self.frames_root_dir && e.file_type().is_dir());
+        let entries = frame_dirs(&self.frames_root_dir);
             
         let mut result = Vec::new();
         
@@ -80,7 +85,7 @@ impl FrameManager {
                 .max_depth(1)
                 .into_iter()
                 .filter_map(|e| e.ok())
-                .filter(|e| e.path() != path && e.file_type().is_file())
+                .filter(|e| e.path() != path && e.file_type().is_file() && is_frame_file(e.path()))
             {
                 frame_count += 1;
                 total_size += file_entry.metadata()?.len();
@@ -99,6 +104,9 @@ impl FrameManager {
     }
     
     /// 获取指定帧文件夹中的所有帧
+    ///
+    /// 帧序号和时间戳从拆分清单中读取；没有清单的文件夹（早期版本拆分或手动创建）
+    /// 从文件名最后一个下划线之后的数字推断帧序号。
     pub fn list_frames<P: AsRef<Path>>(&self, frame_dir: P) -> Result<Vec<FrameInfo>> {
         let frame_dir = frame_dir.as_ref();
         
@@ -108,34 +116,47 @@ impl FrameManager {
             )));
         }
         
+        let manifest = match SplitManifest::load(frame_dir) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                error!("读取拆分清单失败，从文件名推断帧序号: {}", e);
+                None
+            }
+        };
+        
         let entries = WalkDir::new(frame_dir)
             .max_depth(1)
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.path() != frame_dir && e.file_type().is_file());
+            .filter(|e| e.path() != frame_dir && e.file_type().is_file() && is_frame_file(e.path()));
             
         let mut result = Vec::new();
         
         for entry in entries {
             let path = entry.path().to_path_buf();
             let name = entry.file_name().to_string_lossy().to_string();
-            
-            // 尝试从文件名中提取帧序号
-            let frame_number = name
-                .split('_')
-                .last()
-                .and_then(|s| s.split('.').next())
-                .and_then(|s| s.parse::<usize>().ok())
-                .unwrap_or(0);
-                
             let size = entry.metadata()?.len();
             
-            result.push(FrameInfo {
-                path,
-                name,
-                frame_number,
-                size,
-            });
+            let info = match manifest.as_ref().and_then(|m| m.frame(&name)) {
+                Some(frame) => FrameInfo {
+                    path,
+                    frame_number: frame.frame_index as usize,
+                    timestamp: Some(frame.pts),
+                    duplicate_of: frame.duplicate_of.clone(),
+                    name,
+                    size,
+                },
+                None => FrameInfo {
+                    path,
+                    frame_number: frame_number_from_name(&name),
+                    timestamp: None,
+                    duplicate_of: None,
+                    name,
+                    size,
+                },
+            };
+            
+            result.push(info);
         }
         
         // 按帧序号排序
@@ -159,6 +180,61 @@ impl FrameManager {
         Ok(dir_path)
     }
     
+    /// 对帧文件夹中的图片做相似帧去重，并在文件夹中写出去重报告
+    ///
+    /// 处理方式为 `drop` 时删除重复的图片，为 `flag` 时只生成报告。
+    pub fn dedup_frame_dir<P: AsRef<Path>>(&self, frame_dir: P, config: &DedupConfig) -> Result<DedupReport> {
+        let frame_dir = frame_dir.as_ref();
+        
+        if !frame_dir.exists() || !frame_dir.is_dir() {
+            return Err(Error::FileSystem(format!(
+                "帧文件夹不存在: {}", frame_dir.display()
+            )));
+        }
+        
+        if !frame_dir.starts_with(&self.frames_root_dir) {
+            return Err(Error::FileSystem(format!(
+                "不允许处理该目录: {}", frame_dir.display()
+            )));
+        }
+        
+        let report = dedup::dedup_dir(frame_dir, config)
+            .map_err(|e| Error::Other(format!("帧文件夹去重失败: {}", e)))?;
+        
+        info!("帧文件夹去重完成: {}，共 {} 张，重复 {} 张",
+            frame_dir.display(), report.total, report.duplicates);
+        
+        Ok(report)
+    }
+    
+    /// 为帧文件夹生成联系表或雪碧图，写在文件夹旁边
+    ///
+    /// 联系表为 `<文件夹名>.contact.jpg`，雪碧图为 `<文件夹名>.sprite.jpg`，
+    /// 雪碧图同时写出 `.sprite.vtt` 和 `.sprite.json` 索引。
+    pub fn build_frame_sheet<P: AsRef<Path>>(&self, frame_dir: P, config: &SheetConfig) -> Result<SheetIndex> {
+        let frame_dir = frame_dir.as_ref();
+        
+        if !frame_dir.exists() || !frame_dir.is_dir() {
+            return Err(Error::FileSystem(format!(
+                "帧文件夹不存在: {}", frame_dir.display()
+            )));
+        }
+        
+        if !frame_dir.starts_with(&self.frames_root_dir) || frame_dir == self.frames_root_dir {
+            return Err(Error::FileSystem(format!(
+                "不允许处理该目录: {}", frame_dir.display()
+            )));
+        }
+        
+        let index = sheet::build_for_dir(frame_dir, config)
+            .map_err(|e| Error::Other(format!("生成{}失败: {}", sheet_name(config), e)))?;
+        
+        info!("{}生成完成: {}，共 {} 张缩略图",
+            sheet_name(config), index.image, index.tiles.len());
+        
+        Ok(index)
+    }
+    
     /// 删除帧文件夹
     pub fn delete_frame_dir<P: AsRef<Path>>(&self, frame_dir: P) -> Result<()> {
         let frame_dir = frame_dir.as_ref();
@@ -178,6 +254,29 @@ impl FrameManager {
         
         std::fs::remove_dir_all(frame_dir)?;
         
+        for file in sheet::sheet_files(frame_dir) {
+            if file.exists() {
+                std::fs::remove_file(file)?;
+            }
+        }
+        
         Ok(())
     }
 }
+
+/// 日志中使用的网格图类型名
+fn sheet_name(config: &SheetConfig) -> &'static str {
+    match config.kind {
+        SheetKind::Contact => "联系表",
+        SheetKind::Sprite => "雪碧图",
+    }
+}
+
+/// 从文件名最后一个下划线之后的数字推断帧序号，无法解析时为 0
+fn frame_number_from_name(name: &str) -> usize {
+    name.split('_')
+        .next_back()
+        .and_then(|s| s.split('.').next())
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0)
+}
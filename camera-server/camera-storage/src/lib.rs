@@ -8,6 +8,7 @@ pub mod file_manager;
 pub mod frame_manager;
 pub mod package;
 pub mod disk;
+pub mod retention;
 
 pub use error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,12 +1,17 @@
 //! 文件打包功能模块
 
 use crate::{Error, Result};
+use camera_core::guard::{StorageGuard, StorageJob};
 use log::{info, error, debug};
 use std::path::{Path, PathBuf};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write, Seek, SeekFrom};
 use walkdir::WalkDir;
 use zip::{ZipWriter, write::FileOptions};
+use std::sync::Arc;
+
+/// 打包过程中每写入多少字节检查一次存储空间
+const STORAGE_CHECK_BYTES: u64 = 64 * 1024 * 1024;
 
 /// 打包任务状态
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -58,6 +63,8 @@ pub struct PackageManager {
     output_dir: PathBuf,
     /// 当前任务
     current_task: Option<PackageTaskInfo>,
+    /// 存储空间检查，未设置时不检查
+    storage_guard: Option<Arc<dyn StorageGuard>>,
 }
 
 impl PackageManager {
@@ -71,9 +78,24 @@ impl PackageManager {
         Ok(Self {
             output_dir,
             current_task: None,
+            storage_guard: None,
         })
     }
     
+    /// 设置存储空间检查，打包前按源文件大小预估，打包过程中定期检查
+    pub fn set_storage_guard(&mut self, guard: Arc<dyn StorageGuard>) {
+        self.storage_guard = Some(guard);
+    }
+    
+    /// 检查打包目录的存储空间
+    fn check_storage(&self, target: &Path, required: u64) -> Result<()> {
+        if let Some(guard) = &self.storage_guard {
+            guard.check(StorageJob::Package, target, required)
+                .map_err(|e| Error::DiskSpace(e.to_string()))?;
+        }
+        Ok(())
+    }
+    
     /// 开始打包任务
     pub fn start_package_task<P: AsRef<Path>>(&mut self, source_dir: P, name: &str) -> Result<String> {
         let source_dir = source_dir.as_ref().to_path_buf();
@@ -107,6 +129,9 @@ impl PackageManager {
             total_size += entry.metadata().map(|m| m.len()).unwrap_or(0);
         }
         
+        // 压缩后的大小未知，按源文件总大小预估
+        self.check_storage(&target_file, total_size)?;
+        
         // 创建任务信息
         let task_info = PackageTaskInfo {
             id: task_id.clone(),
@@ -148,6 +173,13 @@ impl PackageManager {
                 if let Some(task) = &mut self.current_task {
                     task.status = PackageTaskStatus::Failed;
                     task.error = Some(e.to_string());
+                    
+                    // 空间不足时删除未完成的ZIP文件，释放已占用的空间
+                    if matches!(e, Error::DiskSpace(_)) && task.target_file.exists() {
+                        if let Err(e) = std::fs::remove_file(&task.target_file) {
+                            error!("删除未完成的打包文件失败: {}: {}", task.target_file.display(), e);
+                        }
+                    }
                 }
                 return Err(e);
             }
@@ -175,6 +207,7 @@ impl PackageManager {
             
         let source_dir = &task.source_dir;
         let source_path = source_dir.as_path();
+        let mut unchecked_bytes = 0;
         
         // 遍历源目录中的所有文件
         for entry in WalkDir::new(source_dir)
@@ -206,6 +239,16 @@ impl PackageManager {
                 
                 // 更新已处理大小
                 task.processed_size += file_size;
+                
+                // 定期检查存储空间
+                unchecked_bytes += file_size;
+                if unchecked_bytes >= STORAGE_CHECK_BYTES {
+                    unchecked_bytes = 0;
+                    if let Some(guard) = &self.storage_guard {
+                        guard.check(StorageJob::Package, &task.target_file, 0)
+                            .map_err(|e| Error::DiskSpace(e.to_string()))?;
+                    }
+                }
             } else if name.as_os_str().len() != 0 {
                 // 添加目录到ZIP
                 zip.add_directory(
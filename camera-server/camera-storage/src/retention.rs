@@ -0,0 +1,564 @@
+//! 文件保留策略模块
+//!
+//! 按“最多保留 N 天”、“总大小不超过 X GB”、“最多保留 N 个”等规则清理
+//! 录制文件、帧文件夹和打包文件。清理时总是从最旧的开始删除，
+//! 正在录制的文件、已固定的文件以及匹配保护规则的文件不会被删除，
+//! 它们仍计入总大小，但不占用数量限制。受保护的文件本身就超过大小上限时，
+//! 不再为满足大小限制删除其他文件，只在报告中记录超出的大小。
+
+use crate::{Error, Result};
+use crate::disk::DiskManager;
+use crate::file_manager::{FileInfo, FileManager};
+use log::{info, warn, error, debug};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// 1 GB
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// 一天的秒数
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// 单类文件的保留规则，未设置的限制不生效
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionRule {
+    /// 最多保留的天数
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+
+    /// 总大小上限（GB）
+    #[serde(default)]
+    pub max_size_gb: Option<f64>,
+
+    /// 最多保留的数量
+    #[serde(default)]
+    pub max_count: Option<usize>,
+}
+
+impl RetentionRule {
+    /// 是否设置了任何限制
+    fn is_empty(&self) -> bool {
+        self.max_age_days.is_none() && self.max_size_gb.is_none() && self.max_count.is_none()
+    }
+}
+
+/// 保留策略配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// 是否启用自动清理
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 只生成清理报告，不实际删除
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// 检查间隔（秒）
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+
+    /// 录制文件的保留规则
+    #[serde(default)]
+    pub recordings: RetentionRule,
+
+    /// 帧文件夹的保留规则
+    #[serde(default)]
+    pub frames: RetentionRule,
+
+    /// 打包文件的保留规则
+    #[serde(default)]
+    pub packages: RetentionRule,
+
+    /// 受保护的文件名模式，支持 `*` 和 `?` 通配符，如 `incident_*`
+    #[serde(default)]
+    pub protected: Vec<String>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dry_run: false,
+            check_interval: default_check_interval(),
+            recordings: RetentionRule::default(),
+            frames: RetentionRule::default(),
+            packages: RetentionRule::default(),
+            protected: Vec::new(),
+        }
+    }
+}
+
+fn default_check_interval() -> u64 {
+    3600
+}
+
+/// 保留策略管理的文件类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionCategory {
+    /// 录制文件
+    Recordings,
+    /// 帧文件夹
+    Frames,
+    /// 打包文件
+    Packages,
+}
+
+/// 被清理（或计划清理）的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionItem {
+    /// 文件类别
+    pub category: RetentionCategory,
+    /// 文件或文件夹路径
+    pub path: PathBuf,
+    /// 大小（字节），帧文件夹为其中所有文件的大小
+    pub size: u64,
+    /// 修改时间（Unix时间戳）
+    pub modified_at: u64,
+    /// 清理原因
+    pub reason: String,
+}
+
+/// 受保护的文件超出大小上限的类别
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionOverage {
+    /// 文件类别
+    pub category: RetentionCategory,
+    /// 受保护文件的总大小（字节）
+    pub protected_bytes: u64,
+    /// 大小上限（字节）
+    pub limit_bytes: u64,
+}
+
+/// 清理报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionReport {
+    /// 是否为只报告不删除
+    pub dry_run: bool,
+    /// 已删除（或计划删除）的文件
+    pub deleted: Vec<RetentionItem>,
+    /// 因受保护而保留、但按规则本应删除的文件
+    pub protected: Vec<PathBuf>,
+    /// 只靠受保护的文件就超出大小上限的类别
+    pub overages: Vec<RetentionOverage>,
+    /// 释放（或可释放）的空间（字节）
+    pub freed_bytes: u64,
+    /// 删除失败的文件及原因
+    pub errors: Vec<String>,
+}
+
+/// 待评估的文件
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+    modified_at: u64,
+    protected: bool,
+}
+
+/// 保留策略管理器
+pub struct RetentionManager {
+    /// 配置
+    config: RetentionConfig,
+    /// 录制文件目录，录制输出目录与存储目录不同时会有多个
+    video_dirs: Vec<PathBuf>,
+    /// 帧文件夹根目录
+    frames_dir: PathBuf,
+    /// 打包文件目录
+    package_dir: PathBuf,
+    /// 磁盘管理器
+    disk: DiskManager,
+}
+
+impl RetentionManager {
+    /// 创建保留策略管理器
+    pub fn new<P: AsRef<Path>>(
+        config: RetentionConfig,
+        video_dirs: &[P],
+        frames_dir: P,
+        package_dir: P,
+    ) -> Result<Self> {
+        for (name, rule) in [("录制文件", &config.recordings), ("帧文件夹", &config.frames), ("打包文件", &config.packages)] {
+            if let Some(size) = rule.max_size_gb {
+                if !size.is_finite() || size <= 0.0 {
+                    return Err(Error::Config(format!("{}的总大小上限必须为正数: {}", name, size)));
+                }
+            }
+        }
+
+        if config.check_interval == 0 {
+            return Err(Error::Config("保留策略检查间隔不能为0".to_string()));
+        }
+
+        Ok(Self {
+            config,
+            video_dirs: video_dirs.iter().map(|d| d.as_ref().to_path_buf()).collect(),
+            frames_dir: frames_dir.as_ref().to_path_buf(),
+            package_dir: package_dir.as_ref().to_path_buf(),
+            disk: DiskManager::new(),
+        })
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> &RetentionConfig {
+        &self.config
+    }
+
+    /// 按配置执行清理，配置为 `dry_run` 时只生成报告
+    ///
+    /// `active` 为正在写入的文件，不论规则如何都不会删除。
+    pub fn enforce(&self, active: &[PathBuf]) -> Result<RetentionReport> {
+        self.apply(active, self.config.dry_run)
+    }
+
+    /// 生成清理报告，不删除任何文件
+    pub fn report(&self, active: &[PathBuf]) -> Result<RetentionReport> {
+        self.apply(active, true)
+    }
+
+    /// 按规则清理三类文件
+    fn apply(&self, active: &[PathBuf], dry_run: bool) -> Result<RetentionReport> {
+        let mut report = RetentionReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        let file_manager = FileManager::new(
+            self.video_dirs.first().map(|d| d.as_path()).unwrap_or(Path::new(".")),
+            &self.frames_dir,
+        )?;
+
+        if !self.config.recordings.is_empty() {
+            let mut candidates = Vec::new();
+            for dir in &self.video_dirs {
+                let files = FileManager::new(dir.as_path(), &self.frames_dir)?.list_videos()?;
+                candidates.extend(files.into_iter().map(|f| self.candidate(f, active)));
+            }
+            self.apply_rule(RetentionCategory::Recordings, &self.config.recordings, candidates, dry_run, &mut report, |path| {
+                // 由对应目录的文件管理器删除，同时删除附属文件
+                let dir = self.video_dirs.iter()
+                    .find(|d| path.starts_with(d))
+                    .cloned()
+                    .unwrap_or_default();
+                FileManager::new(dir.as_path(), &self.frames_dir)?.delete_file(path)
+            });
+        }
+
+        if !self.config.frames.is_empty() {
+            let mut candidates = Vec::new();
+            for mut dir in file_manager.list_frame_dirs()? {
+                dir.size = self.disk.get_directory_size(&dir.path).unwrap_or(0);
+                candidates.push(self.candidate(dir, active));
+            }
+            self.apply_rule(RetentionCategory::Frames, &self.config.frames, candidates, dry_run, &mut report, |path| {
+                file_manager.delete_file(path)
+            });
+        }
+
+        if !self.config.packages.is_empty() {
+            let candidates = self.list_packages(active)?;
+            self.apply_rule(RetentionCategory::Packages, &self.config.packages, candidates, dry_run, &mut report, |path| {
+                std::fs::remove_file(path).map_err(Error::from)
+            });
+        }
+
+        if !report.deleted.is_empty() {
+            info!("保留策略{}清理 {} 个文件，释放 {} MB",
+                if dry_run { "计划" } else { "已" },
+                report.deleted.len(),
+                report.freed_bytes / (1024 * 1024));
+        }
+
+        Ok(report)
+    }
+
+    /// 按单条规则从最旧的文件开始清理
+    fn apply_rule<F>(
+        &self,
+        category: RetentionCategory,
+        rule: &RetentionRule,
+        mut candidates: Vec<Candidate>,
+        dry_run: bool,
+        report: &mut RetentionReport,
+        delete: F,
+    ) where
+        F: Fn(&Path) -> Result<()>,
+    {
+        candidates.sort_by_key(|c| c.modified_at);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let max_size = rule.max_size_gb.map(|gb| (gb * GB) as u64);
+
+        // 受保护的文件计入总大小，但不计入数量
+        let mut remaining_count = candidates.iter().filter(|c| !c.protected).count();
+        let mut remaining_size: u64 = candidates.iter().map(|c| c.size).sum();
+        let protected_size: u64 = candidates.iter().filter(|c| c.protected).map(|c| c.size).sum();
+
+        // 删除所有未受保护的文件也无法满足大小限制时，不按大小删除
+        let max_size = match max_size {
+            Some(max) if protected_size > max => {
+                warn!("受保护的{:?}共 {} MB，超过大小上限 {} GB，不按大小清理",
+                    category, protected_size / (1024 * 1024), rule.max_size_gb.unwrap_or_default());
+                report.overages.push(RetentionOverage {
+                    category,
+                    protected_bytes: protected_size,
+                    limit_bytes: max,
+                });
+                None
+            }
+            other => other,
+        };
+
+        for candidate in candidates {
+            let expired = rule.max_age_days
+                .map(|days| now.saturating_sub(candidate.modified_at) > days as u64 * DAY_SECS)
+                .unwrap_or(false);
+            let over_count = rule.max_count.map(|max| remaining_count > max).unwrap_or(false);
+            let over_size = max_size.map(|max| remaining_size > max).unwrap_or(false);
+
+            let reason = if expired {
+                format!("超过 {} 天", rule.max_age_days.unwrap_or_default())
+            } else if over_count {
+                format!("超过 {} 个", rule.max_count.unwrap_or_default())
+            } else if over_size {
+                format!("总大小超过 {} GB", rule.max_size_gb.unwrap_or_default())
+            } else {
+                // 按时间排序，后面的文件更新，只可能因数量或大小超限被删除，
+                // 而数量和大小此时都已满足
+                break;
+            };
+
+            if candidate.protected {
+                debug!("保留受保护的文件: {} ({})", candidate.path.display(), reason);
+                report.protected.push(candidate.path);
+                continue;
+            }
+
+            if !dry_run {
+                if let Err(e) = delete(&candidate.path) {
+                    error!("保留策略删除文件失败: {}: {}", candidate.path.display(), e);
+                    report.errors.push(format!("{}: {}", candidate.path.display(), e));
+                    continue;
+                }
+                info!("保留策略删除文件: {} ({})", candidate.path.display(), reason);
+            }
+
+            remaining_count -= 1;
+            remaining_size = remaining_size.saturating_sub(candidate.size);
+            report.freed_bytes += candidate.size;
+            report.deleted.push(RetentionItem {
+                category,
+                path: candidate.path,
+                size: candidate.size,
+                modified_at: candidate.modified_at,
+                reason,
+            });
+        }
+    }
+
+    /// 列出打包目录中的打包文件
+    fn list_packages(&self, active: &[PathBuf]) -> Result<Vec<Candidate>> {
+        if !self.package_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut result = Vec::new();
+
+        for entry in WalkDir::new(&self.package_dir)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().map(|ext| ext == "zip").unwrap_or(false))
+        {
+            let path = entry.path().to_path_buf();
+            let metadata = entry.metadata()
+                .map_err(|e| Error::FileSystem(format!("读取文件信息失败: {}: {}", path.display(), e)))?;
+            let modified_at = metadata.modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+            result.push(Candidate {
+                protected: self.is_protected(&path, active),
+                path,
+                size: metadata.len(),
+                modified_at,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// 由文件信息生成待评估的文件
+    fn candidate(&self, file: FileInfo, active: &[PathBuf]) -> Candidate {
+        Candidate {
+            protected: file.pinned || self.is_protected(&file.path, active),
+            path: file.path,
+            size: file.size,
+            modified_at: file.modified_at,
+        }
+    }
+
+    /// 文件是否正在写入、已固定或匹配保护规则
+    fn is_protected(&self, path: &Path, active: &[PathBuf]) -> bool {
+        if active.iter().any(|a| a == path || a.starts_with(path)) {
+            return true;
+        }
+
+        if FileManager::is_pinned(path) {
+            return true;
+        }
+
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.config.protected.iter().any(|pattern| wildcard_match(pattern, &name))
+    }
+}
+
+/// 简单的通配符匹配，`*` 匹配任意字符序列，`?` 匹配单个字符
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 在 dir 下创建指定大小、指定天数之前修改的打包文件
+    fn package(dir: &Path, name: &str, size: usize, age_days: u64) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![0u8; size]).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_days * DAY_SECS + 60);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        path
+    }
+
+    fn run(dir: &Path, config: RetentionConfig, active: &[PathBuf]) -> RetentionReport {
+        let manager = RetentionManager::new(config, &[dir.join("videos")], dir.join("frames"), dir.to_path_buf()).unwrap();
+        manager.enforce(active).unwrap()
+    }
+
+    fn deleted(report: &RetentionReport) -> Vec<PathBuf> {
+        report.deleted.iter().map(|item| item.path.clone()).collect()
+    }
+
+    #[test]
+    fn count_and_age_limits_delete_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("retention-count-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let newest = package(&dir, "d.zip", 10, 1);
+        let oldest = package(&dir, "a.zip", 10, 20);
+        let older = package(&dir, "b.zip", 10, 3);
+        let newer = package(&dir, "c.zip", 10, 2);
+
+        let config = RetentionConfig {
+            packages: RetentionRule { max_age_days: Some(10), ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(deleted(&run(&dir, config, &[])), vec![oldest.clone()]);
+        assert!(!oldest.exists());
+
+        let config = RetentionConfig {
+            packages: RetentionRule { max_count: Some(2), ..Default::default() },
+            ..Default::default()
+        };
+        let report = run(&dir, config, &[]);
+        assert_eq!(deleted(&report), vec![older.clone()]);
+        assert_eq!(report.freed_bytes, 10);
+        assert!(newer.exists() && newest.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn protected_files_are_not_counted() {
+        let dir = std::env::temp_dir().join(format!("retention-protected-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let kept = package(&dir, "keep_a.zip", 10, 3);
+        let old = package(&dir, "b.zip", 10, 2);
+        let active = package(&dir, "c.zip", 10, 1);
+        package(&dir, "d.zip", 10, 0);
+
+        let config = RetentionConfig {
+            packages: RetentionRule { max_count: Some(1), ..Default::default() },
+            protected: vec!["keep_*".to_string()],
+            ..Default::default()
+        };
+        // 正在写入和匹配保护规则的文件不占用数量限制，只删除最旧的普通文件
+        let report = run(&dir, config, std::slice::from_ref(&active));
+        assert_eq!(deleted(&report), vec![old]);
+        assert_eq!(report.protected, vec![kept]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn size_limit_stops_at_protected_overage() {
+        let dir = std::env::temp_dir().join(format!("retention-size-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let oldest = package(&dir, "a.zip", 100, 3);
+        package(&dir, "b.zip", 100, 2);
+        package(&dir, "c.zip", 100, 1);
+
+        let config = RetentionConfig {
+            packages: RetentionRule { max_size_gb: Some(250.0 / GB), ..Default::default() },
+            ..Default::default()
+        };
+        let report = run(&dir, config, &[]);
+        assert_eq!(deleted(&report), vec![oldest]);
+        assert!(report.overages.is_empty());
+
+        // 受保护的文件本身就超过上限时不删除其他文件，只报告超出
+        package(&dir, "keep_x.zip", 300, 4);
+        let config = RetentionConfig {
+            packages: RetentionRule { max_size_gb: Some(250.0 / GB), ..Default::default() },
+            protected: vec!["keep_*".to_string()],
+            ..Default::default()
+        };
+        let report = run(&dir, config, &[]);
+        assert!(report.deleted.is_empty());
+        assert_eq!(report.overages.len(), 1);
+        assert_eq!(report.overages[0].category, RetentionCategory::Packages);
+        assert_eq!(report.overages[0].protected_bytes, 300);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wildcard_patterns() {
+        assert!(wildcard_match("incident_*", "incident_2024.zip"));
+        assert!(wildcard_match("cam?_*", "cam1_x"));
+        assert!(!wildcard_match("cam?_*", "cam12_x"));
+        assert!(!wildcard_match("incident_*", "other.zip"));
+    }
+}
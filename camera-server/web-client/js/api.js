@@ -17,10 +17,13 @@ const API_ENDPOINTS = {
     // 录制相关
     RECORDING_START: '/api/recording/start',
     RECORDING_STOP: '/api/recording/stop',
+    RECORDING_PAUSE: '/api/recording/pause',
+    RECORDING_RESUME: '/api/recording/resume',
     RECORDING_STATUS: '/api/recording/status',
     RECORDING_CONFIG: '/api/recording/config',
     RECORDING_FILES: '/api/recording/files',
     RECORDING_DELETE: '/api/recording/delete',
+    RECORDING_PREVIEW: '/api/recording/preview',
     
     // 拆分相关
     SPLIT_START: '/api/split/start',
@@ -185,6 +188,22 @@ class ApiClient {
         return this.post(API_ENDPOINTS.RECORDING_STOP);
     }
     
+    /**
+     * 暂停录制
+     * @returns {Promise<Object>} - 响应数据
+     */
+    async pauseRecording() {
+        return this.post(API_ENDPOINTS.RECORDING_PAUSE);
+    }
+    
+    /**
+     * 恢复录制
+     * @returns {Promise<Object>} - 响应数据
+     */
+    async resumeRecording() {
+        return this.post(API_ENDPOINTS.RECORDING_RESUME);
+    }
+    
     /**
      * 获取录制状态
      * @returns {Promise<Object>} - 状态数据
@@ -219,6 +238,17 @@ class ApiClient {
         return this.delete(API_ENDPOINTS.RECORDING_DELETE, { file_path: filePath });
     }
     
+    /**
+     * 获取录制预览图（封面图或缩略图条）地址
+     * @param {string} imagePath - 预览图路径
+     * @returns {string} - 图片URL
+     */
+    getRecordingPreviewUrl(imagePath) {
+        const url = new URL(API_BASE_URL + API_ENDPOINTS.RECORDING_PREVIEW);
+        url.searchParams.append('file_path', imagePath);
+        return url.toString();
+    }
+    
     // 拆分相关API
     
     /**
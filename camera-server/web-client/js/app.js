@@ -17,6 +17,7 @@ class AppManager {
             },
             recording: {
                 active: false,
+                state: 'idle',
                 format: '',
                 bitrate: 0,
                 currentFile: null
@@ -150,10 +151,16 @@ class AppManager {
             const status = await api.getRecordingStatus();
             
             this.state.recording.active = status.recording;
+            this.state.recording.state = status.state;
             
             if (status.recording) {
                 this.state.recording.currentFile = status.current_file;
                 ui.updateRecordingStatus('录制中', true);
+            } else if (status.state === 'paused') {
+                this.state.recording.currentFile = status.current_file;
+                ui.updateRecordingStatus('已暂停', true);
+            } else if (status.state === 'failed') {
+                ui.updateRecordingStatus(`录制失败: ${status.last_error || '未知错误'}`, false);
             } else {
                 ui.updateRecordingStatus('未录制', false);
             }
@@ -179,6 +179,7 @@ class UiManager {
         for (const file of files) {
             html += `
                 <div class="file-item" data-path="${file.path}">
+                    ${this.renderRecordingPreview(file)}
                     <div class="file-info">
                         <div class="file-name">${file.name}</div>
                         <div class="file-size">${this.formatBytes(file.size)}</div>
@@ -210,6 +211,53 @@ class UiManager {
                 this.onDeleteRecordingFile(path);
             });
         });
+        
+        // 鼠标在封面上移动时按位置切换到缩略图条中对应的画面
+        const previews = this.recordingFilesContainer.querySelectorAll('.file-poster[data-thumbs]');
+        
+        previews.forEach(preview => {
+            const count = parseInt(preview.getAttribute('data-count'), 10);
+            
+            preview.addEventListener('mousemove', (event) => {
+                const rect = preview.getBoundingClientRect();
+                const ratio = (event.clientX - rect.left) / rect.width;
+                const index = Math.min(count - 1, Math.max(0, Math.floor(ratio * count)));
+                preview.style.backgroundImage = `url("${preview.getAttribute('data-thumbs')}")`;
+                preview.style.backgroundSize = `${count * 100}% 100%`;
+                preview.style.backgroundPosition = `${count > 1 ? index * 100 / (count - 1) : 0}% 0`;
+                preview.classList.add('scrubbing');
+            });
+            
+            preview.addEventListener('mouseleave', () => {
+                preview.style.backgroundImage = '';
+                preview.classList.remove('scrubbing');
+            });
+        });
+    }
+    
+    /**
+     * 生成录制文件的预览图
+     * @param {Object} file - 文件信息
+     * @returns {string} - HTML
+     */
+    renderRecordingPreview(file) {
+        if (!file.poster) {
+            return '<div class="file-poster empty"></div>';
+        }
+        
+        const preview = file.metadata && file.metadata.preview;
+        const posterUrl = api.getRecordingPreviewUrl(file.poster);
+        let thumbs = '';
+        
+        if (file.thumbnails && preview && preview.thumbnail_count > 0) {
+            thumbs = `data-thumbs="${api.getRecordingPreviewUrl(file.thumbnails)}" data-count="${preview.thumbnail_count}"`;
+        }
+        
+        return `
+            <div class="file-poster" ${thumbs}>
+                <img src="${posterUrl}" alt="${file.name}" loading="lazy">
+            </div>
+        `;
     }
     
     /**